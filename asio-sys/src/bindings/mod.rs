@@ -93,6 +93,8 @@ pub struct SampleRate {
 pub struct CallbackInfo {
     pub buffer_index: i32,
     pub system_time: ai::ASIOTimeStamp,
+    /// The sample position of the buffer since `ASIOStart()`.
+    pub sample_position: ai::ASIOSamples,
 }
 
 /// Holds the pointer to the callbacks that come from cpal
@@ -999,6 +1001,7 @@ extern "C" fn buffer_switch_time_info(
     let callback_info = CallbackInfo {
         buffer_index: double_buffer_index,
         system_time: asio_time.time_info.system_time,
+        sample_position: asio_time.time_info.sample_position,
     };
     for &mut (_, ref mut bc) in bcs.iter_mut() {
         bc.run(&callback_info);
@@ -0,0 +1,695 @@
+//! Streams adapting the sample format, channels, sample rate and layout of the device to those
+//! requested by the data callback.
+
+use std::time::Duration;
+
+use crate::buffer::{build_with_buffer, build_with_state};
+use crate::convert::{build_stream_error, native_format, with_sample_type, Converter};
+use crate::duplex::fill_equilibrium;
+use crate::mix::native_channels;
+use crate::planar::{deinterleave, interleave};
+use crate::resample::{native_rate, offset_instant, Resampler};
+use crate::traits::DeviceTrait;
+use crate::{
+    BuildStreamError, ChannelMix, Data, Dither, FromSample, InputCallbackInfo,
+    InputStreamTimestamp, OutputCallbackInfo, OutputStreamTimestamp, SampleFormat, SampleLayout,
+    SampleRate, SizedSample, StreamConfig, StreamError, StreamPosition, SupportedStreamConfigRange,
+};
+
+/// How a stream adapts the device to the stream config and the sample type of its data callback,
+/// see [`DeviceTrait::build_input_stream_adapted`].
+///
+/// The sample format and the [`SampleLayout`] are always adapted. By default, the device has to
+/// support the channels and the sample rate of the stream config.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AdapterConfig {
+    /// The dither applied when converting floating point samples to integer samples.
+    pub dither: Dither,
+    /// How the channels of the device are mapped to those of the stream config.
+    pub channels: ChannelMapping,
+    /// Whether to resample from or to the closest sample rate the device supports if it does not
+    /// support the sample rate of the stream config. The timestamps passed to the data callback
+    /// account for the delay of the resampling filter.
+    pub resample: bool,
+}
+
+/// How the channels of the device are mapped to those of the stream config, see
+/// [`AdapterConfig::channels`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ChannelMapping {
+    /// The device is opened with the channels of the stream config.
+    #[default]
+    Exact,
+    /// The device is opened with the channels of the stream config if it supports them, otherwise
+    /// with the closest supported number of channels, which are mixed with
+    /// [`ChannelMix::standard`].
+    Standard,
+    /// The device is opened with the channels of the device side of the mix, i.e. its source
+    /// channels for input streams and its destination channels for output streams. The other
+    /// side must match the channels of the stream config.
+    Mix(ChannelMix),
+}
+
+/// Build an input stream delivering samples of type `T` as requested by `config`, adapted from
+/// the device as allowed by `adapter`.
+pub(crate) fn build_input_stream_adapted<Dev, T, D, E>(
+    device: &Dev,
+    config: &StreamConfig,
+    adapter: &AdapterConfig,
+    mut data_callback: D,
+    error_callback: E,
+    timeout: Option<Duration>,
+) -> Result<Dev::Stream, BuildStreamError>
+where
+    Dev: DeviceTrait,
+    T: SizedSample + FromSample<f64> + Send + 'static,
+    f64: FromSample<T>,
+    D: FnMut(&[T], &InputCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    let configs = device
+        .supported_input_configs()
+        .map_err(build_stream_error)?
+        .collect();
+    let native = Native::new(configs, config, adapter, true, T::FORMAT)?;
+
+    if native.converts_only(config) {
+        if native.sample_format == T::FORMAT {
+            return device.build_input_stream(config, data_callback, error_callback, timeout);
+        }
+        // Samples are converted one by one, so the host takes care of the layout.
+        let channels = config.channels as usize;
+        return with_sample_type!(native.sample_format, S => {
+            let mut converter = Converter::new(adapter.dither, S::FORMAT, T::FORMAT);
+            build_with_buffer(
+                config.buffer_size,
+                |frames| frames * channels,
+                T::EQUILIBRIUM,
+                |buffer| {
+                    device.build_input_stream_raw(
+                        config,
+                        S::FORMAT,
+                        move |data, info| {
+                            let samples = data
+                                .as_slice::<S>()
+                                .expect("host supplied incorrect sample type");
+                            // Only fails while the stream is being built.
+                            let Some(mut buffer) =
+                                buffer.lock_buffer(samples.len(), T::EQUILIBRIUM)
+                            else {
+                                return;
+                            };
+                            let buffer = &mut buffer[..samples.len()];
+                            converter.convert::<S, T>(samples, buffer);
+                            data_callback(buffer, info);
+                        },
+                        error_callback,
+                        timeout,
+                    )
+                },
+            )
+        });
+    }
+
+    let pipeline = InputPipeline::<T>::new(&native, config, adapter.dither);
+    let native_channels = native.config.channels as usize;
+    with_sample_type!(native.sample_format, S => {
+        let mut converter = Converter::new(Dither::None, S::FORMAT, SampleFormat::F32);
+        build_with_state(
+            config.buffer_size,
+            pipeline,
+            |pipeline, frames| pipeline.prepare(frames),
+            |pipeline| {
+                device.build_input_stream_raw(
+                    &native.config,
+                    S::FORMAT,
+                    move |data, info| {
+                        // Only fails while the stream is being built.
+                        let Some(mut pipeline) = pipeline.lock() else {
+                            return;
+                        };
+                        let samples = data
+                            .as_slice::<S>()
+                            .expect("host supplied incorrect sample type");
+                        let frames = samples.len() / native_channels;
+                        converter.convert::<S, f32>(samples, pipeline.device(frames));
+                        pipeline.process(frames, info, &mut data_callback);
+                    },
+                    error_callback,
+                    timeout,
+                )
+            },
+        )
+    })
+}
+
+/// Build an output stream expecting samples of type `T` as requested by `config`, adapted to the
+/// device as allowed by `adapter`.
+pub(crate) fn build_output_stream_adapted<Dev, T, D, E>(
+    device: &Dev,
+    config: &StreamConfig,
+    adapter: &AdapterConfig,
+    mut data_callback: D,
+    error_callback: E,
+    timeout: Option<Duration>,
+) -> Result<Dev::Stream, BuildStreamError>
+where
+    Dev: DeviceTrait,
+    T: SizedSample + FromSample<f64> + Send + 'static,
+    f64: FromSample<T>,
+    D: FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    let configs = device
+        .supported_output_configs()
+        .map_err(build_stream_error)?
+        .collect();
+    let native = Native::new(configs, config, adapter, false, T::FORMAT)?;
+
+    if native.converts_only(config) {
+        if native.sample_format == T::FORMAT {
+            return device.build_output_stream(config, data_callback, error_callback, timeout);
+        }
+        // Samples are converted one by one, so the host takes care of the layout.
+        let channels = config.channels as usize;
+        return with_sample_type!(native.sample_format, S => {
+            let mut converter = Converter::new(adapter.dither, T::FORMAT, S::FORMAT);
+            build_with_buffer(
+                config.buffer_size,
+                |frames| frames * channels,
+                T::EQUILIBRIUM,
+                |buffer| {
+                    device.build_output_stream_raw(
+                        config,
+                        S::FORMAT,
+                        move |data: &mut Data, info| {
+                            // Only fails while the stream is being built.
+                            let Some(mut buffer) = buffer.lock_buffer(data.len(), T::EQUILIBRIUM)
+                            else {
+                                fill_equilibrium(data, 0);
+                                return;
+                            };
+                            let samples = data
+                                .as_slice_mut::<S>()
+                                .expect("host supplied incorrect sample type");
+                            let buffer = &mut buffer[..samples.len()];
+                            data_callback(buffer, info);
+                            converter.convert::<T, S>(buffer, samples);
+                        },
+                        error_callback,
+                        timeout,
+                    )
+                },
+            )
+        });
+    }
+
+    let pipeline = OutputPipeline::<T>::new(&native, config);
+    let native_channels = native.config.channels as usize;
+    with_sample_type!(native.sample_format, S => {
+        let mut converter = Converter::new(adapter.dither, SampleFormat::F32, S::FORMAT);
+        build_with_state(
+            config.buffer_size,
+            pipeline,
+            |pipeline, frames| pipeline.prepare(frames),
+            |pipeline| {
+                device.build_output_stream_raw(
+                    &native.config,
+                    S::FORMAT,
+                    move |data: &mut Data, info| {
+                        // Only fails while the stream is being built.
+                        let Some(mut pipeline) = pipeline.lock() else {
+                            fill_equilibrium(data, 0);
+                            return;
+                        };
+                        let samples = data
+                            .as_slice_mut::<S>()
+                            .expect("host supplied incorrect sample type");
+                        let frames = samples.len() / native_channels;
+                        let device = pipeline.process(frames, info, &mut data_callback);
+                        converter.convert::<f32, S>(device, samples);
+                    },
+                    error_callback,
+                    timeout,
+                )
+            },
+        )
+    })
+}
+
+// The config to open the device with, and how it is adapted to the requested config.
+struct Native {
+    config: StreamConfig,
+    sample_format: SampleFormat,
+    // The mix from the device channels for input streams and to them for output streams, if the
+    // device is opened with other channels than requested.
+    mix: Option<ChannelMix>,
+}
+
+impl Native {
+    fn new(
+        configs: Vec<SupportedStreamConfigRange>,
+        config: &StreamConfig,
+        adapter: &AdapterConfig,
+        is_input: bool,
+        preferred: SampleFormat,
+    ) -> Result<Self, BuildStreamError> {
+        // Split data cannot be passed to a typed callback.
+        if config.layout == SampleLayout::Split {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        let (channels, mix) = match adapter.channels {
+            ChannelMapping::Exact => (config.channels, None),
+            ChannelMapping::Standard => {
+                let channels = native_channels(configs.iter().cloned(), config, adapter.resample)?;
+                let mix = match (channels == config.channels, is_input) {
+                    (true, _) => None,
+                    (false, true) => Some(ChannelMix::standard(channels, config.channels)),
+                    (false, false) => Some(ChannelMix::standard(config.channels, channels)),
+                };
+                (channels, mix)
+            }
+            ChannelMapping::Mix(ref mix) => {
+                let (device, callback) = if is_input {
+                    (mix.from_channels(), mix.to_channels())
+                } else {
+                    (mix.to_channels(), mix.from_channels())
+                };
+                if callback != config.channels {
+                    return Err(BuildStreamError::StreamConfigNotSupported);
+                }
+                (device, Some(mix.clone()))
+            }
+        };
+
+        // Samples that are mixed or resampled are processed as `f32`, so the device is opened at
+        // that format if possible rather than at the sample type of the callback.
+        let mut native_config = StreamConfig {
+            channels,
+            layout: SampleLayout::Interleaved,
+            ..config.clone()
+        };
+        if adapter.resample {
+            native_config.sample_rate = native_rate(configs.iter().cloned(), &native_config)?;
+        }
+        let preferred = if mix.is_none() && native_config.sample_rate == config.sample_rate {
+            preferred
+        } else {
+            SampleFormat::F32
+        };
+        let sample_format = native_format(configs.into_iter(), &native_config, preferred)?;
+        Ok(Native {
+            config: native_config,
+            sample_format,
+            mix,
+        })
+    }
+
+    // Whether only the sample format may differ from the requested config.
+    fn converts_only(&self, config: &StreamConfig) -> bool {
+        self.mix.is_none() && self.config.sample_rate == config.sample_rate
+    }
+}
+
+// The first `len` items of `buffer`, which only grows if the host passes more frames to the
+// callback than it reported.
+fn grow<T: Clone>(buffer: &mut Vec<T>, len: usize, fill: T) -> &mut [T] {
+    if buffer.len() < len {
+        buffer.resize(len, fill);
+    }
+    &mut buffer[..len]
+}
+
+// The buffers passed to the data callback of an adapted stream.
+struct CallbackBuffers<T> {
+    channels: usize,
+    // The interleaved samples.
+    samples: Vec<T>,
+    // The planar samples, if requested.
+    planar: Option<Vec<T>>,
+}
+
+impl<T: SizedSample> CallbackBuffers<T> {
+    fn new(config: &StreamConfig) -> Self {
+        CallbackBuffers {
+            channels: config.channels as usize,
+            samples: Vec::new(),
+            planar: (config.layout == SampleLayout::Planar).then(Vec::new),
+        }
+    }
+
+    fn prepare(&mut self, frames: usize) {
+        let len = frames * self.channels;
+        self.samples.resize(len, T::EQUILIBRIUM);
+        if let Some(ref mut planar) = self.planar {
+            planar.resize(len, T::EQUILIBRIUM);
+        }
+    }
+}
+
+// The stages of an adapted input stream: mixing the channels of the device, resampling, and
+// converting to the sample type and layout of the callback.
+struct InputPipeline<T> {
+    device_channels: usize,
+    channels: usize,
+    // The samples of the device as `f32`.
+    device: Vec<f32>,
+    mix: Option<ChannelMix>,
+    mixed: Vec<f32>,
+    resampler: Option<Resampler>,
+    resampled: Vec<f32>,
+    device_rate: SampleRate,
+    // The number of frames passed to the callback, if resampling.
+    position: u64,
+    converter: Converter,
+    callback: CallbackBuffers<T>,
+}
+
+impl<T: SizedSample + FromSample<f64>> InputPipeline<T> {
+    fn new(native: &Native, config: &StreamConfig, dither: Dither) -> Self {
+        let channels = config.channels as usize;
+        let device_rate = native.config.sample_rate;
+        InputPipeline {
+            device_channels: native.config.channels as usize,
+            channels,
+            device: Vec::new(),
+            mix: native.mix.clone(),
+            mixed: Vec::new(),
+            resampler: (device_rate != config.sample_rate)
+                .then(|| Resampler::new(channels, device_rate, config.sample_rate)),
+            resampled: Vec::new(),
+            device_rate,
+            position: 0,
+            converter: Converter::new(dither, SampleFormat::F32, T::FORMAT),
+            callback: CallbackBuffers::new(config),
+        }
+    }
+
+    // Allocate the buffers for callbacks of up to `frames` frames of the device.
+    fn prepare(&mut self, frames: usize) {
+        self.device.resize(frames * self.device_channels, 0.0);
+        if self.mix.is_some() {
+            self.mixed.resize(frames * self.channels, 0.0);
+        }
+        let callback_frames = match self.resampler {
+            Some(ref mut resampler) => {
+                resampler.reserve(frames);
+                let frames = resampler.output_frames_for(frames);
+                self.resampled.resize(frames * self.channels, 0.0);
+                frames
+            }
+            None => frames,
+        };
+        self.callback.prepare(callback_frames);
+    }
+
+    // The buffer to store `frames` frames of the device in.
+    fn device(&mut self, frames: usize) -> &mut [f32] {
+        grow(&mut self.device, frames * self.device_channels, 0.0)
+    }
+
+    // Adapt the `frames` frames of the device stored in `device` and pass them to the callback.
+    fn process<D>(&mut self, frames: usize, info: &InputCallbackInfo, data_callback: &mut D)
+    where
+        D: FnMut(&[T], &InputCallbackInfo),
+    {
+        let channels = self.channels;
+        let mut samples = &self.device[..frames * self.device_channels];
+        if let Some(ref mix) = self.mix {
+            let mixed = grow(&mut self.mixed, frames * channels, 0.0);
+            mix.apply(samples, mixed);
+            samples = mixed;
+        }
+
+        let mut info = info.clone();
+        if let Some(ref mut resampler) = self.resampler {
+            // The position of the first output frame relative to the first frame of the device,
+            // in frames of the device. It is negative, as the filter looks ahead of it.
+            let offset = resampler.position() - resampler.buffered_frames() as f64;
+            resampler.push(samples);
+            let frames = resampler.available_frames();
+            let resampled = grow(&mut self.resampled, frames * channels, 0.0);
+            resampler.process(resampled);
+            samples = resampled;
+            info = InputCallbackInfo {
+                timestamp: InputStreamTimestamp {
+                    callback: info.timestamp.callback,
+                    capture: offset_instant(info.timestamp.capture, offset, self.device_rate),
+                },
+                position: StreamPosition {
+                    frames: self.position,
+                    discontinuity: info.position.discontinuity,
+                },
+            };
+            self.position += frames as u64;
+        }
+
+        let buffers = &mut self.callback;
+        let converted = grow(&mut buffers.samples, samples.len(), T::EQUILIBRIUM);
+        self.converter.convert::<f32, T>(samples, converted);
+        match buffers.planar {
+            Some(ref mut planar) => {
+                let planar = grow(planar, converted.len(), T::EQUILIBRIUM);
+                deinterleave(converted, planar, channels, 1);
+                data_callback(planar, &info);
+            }
+            None => data_callback(converted, &info),
+        }
+    }
+}
+
+// The stages of an adapted output stream: converting from the sample type and layout of the
+// callback, resampling, and mixing into the channels of the device.
+struct OutputPipeline<T> {
+    device_channels: usize,
+    channels: usize,
+    // The samples for the device as `f32`.
+    device: Vec<f32>,
+    mix: Option<ChannelMix>,
+    mixed: Vec<f32>,
+    resampler: Option<Resampler>,
+    rate: SampleRate,
+    // The number of frames requested from the callback, if resampling.
+    position: u64,
+    // Whether frames were lost since frames were last requested from the callback.
+    discontinuity: bool,
+    converter: Converter,
+    callback: CallbackBuffers<T>,
+}
+
+impl<T: SizedSample> OutputPipeline<T>
+where
+    f64: FromSample<T>,
+{
+    fn new(native: &Native, config: &StreamConfig) -> Self {
+        let channels = config.channels as usize;
+        let rate = config.sample_rate;
+        OutputPipeline {
+            device_channels: native.config.channels as usize,
+            channels,
+            device: Vec::new(),
+            mix: native.mix.clone(),
+            mixed: Vec::new(),
+            resampler: (native.config.sample_rate != rate)
+                .then(|| Resampler::new(channels, rate, native.config.sample_rate)),
+            rate,
+            position: 0,
+            discontinuity: false,
+            converter: Converter::new(Dither::None, T::FORMAT, SampleFormat::F32),
+            callback: CallbackBuffers::new(config),
+        }
+    }
+
+    // Allocate the buffers for callbacks of up to `frames` frames of the device.
+    fn prepare(&mut self, frames: usize) {
+        self.device.resize(frames * self.device_channels, 0.0);
+        if self.mix.is_some() {
+            self.mixed.resize(frames * self.channels, 0.0);
+        }
+        let callback_frames = match self.resampler {
+            Some(ref mut resampler) => {
+                let frames = resampler.input_frames_for(frames);
+                resampler.reserve(frames);
+                frames
+            }
+            None => frames,
+        };
+        self.callback.prepare(callback_frames);
+    }
+
+    // Request frames from the callback and adapt them to `frames` frames of the device.
+    fn process<D>(
+        &mut self,
+        frames: usize,
+        info: &OutputCallbackInfo,
+        data_callback: &mut D,
+    ) -> &[f32]
+    where
+        D: FnMut(&mut [T], &OutputCallbackInfo),
+    {
+        let channels = self.channels;
+        // The frames with the channels of the callback at the sample rate of the device.
+        let stage = match self.mix {
+            Some(_) => grow(&mut self.mixed, frames * channels, 0.0),
+            None => grow(&mut self.device, frames * channels, 0.0),
+        };
+
+        match self.resampler {
+            Some(ref mut resampler) => {
+                let needed = resampler.input_frames_needed(frames);
+                self.discontinuity |= info.position.discontinuity;
+                if needed > 0 {
+                    // The first requested frame is played once the filter is centered on it.
+                    let offset = resampler.buffered_frames() as f64 - resampler.position();
+                    let info = OutputCallbackInfo {
+                        timestamp: OutputStreamTimestamp {
+                            callback: info.timestamp.callback,
+                            playback: offset_instant(info.timestamp.playback, offset, self.rate),
+                        },
+                        position: StreamPosition {
+                            frames: self.position,
+                            discontinuity: std::mem::take(&mut self.discontinuity),
+                        },
+                    };
+                    self.position += needed as u64;
+                    let samples =
+                        run_output_callback(&mut self.callback, needed, &info, data_callback);
+                    self.converter
+                        .convert::<T, f32>(samples, resampler.push_with(needed));
+                }
+                resampler.process(stage);
+            }
+            None => {
+                let samples = run_output_callback(&mut self.callback, frames, info, data_callback);
+                self.converter.convert::<T, f32>(samples, stage);
+            }
+        }
+
+        let device_len = frames * self.device_channels;
+        if let Some(ref mix) = self.mix {
+            let device = grow(&mut self.device, device_len, 0.0);
+            mix.apply(&self.mixed[..frames * channels], device);
+        }
+        &self.device[..device_len]
+    }
+}
+
+// Request `frames` frames from the callback, returning them interleaved.
+fn run_output_callback<'a, T, D>(
+    buffers: &'a mut CallbackBuffers<T>,
+    frames: usize,
+    info: &OutputCallbackInfo,
+    data_callback: &mut D,
+) -> &'a [T]
+where
+    T: SizedSample,
+    D: FnMut(&mut [T], &OutputCallbackInfo),
+{
+    let len = frames * buffers.channels;
+    let samples = grow(&mut buffers.samples, len, T::EQUILIBRIUM);
+    match buffers.planar {
+        Some(ref mut planar) => {
+            let planar = grow(planar, len, T::EQUILIBRIUM);
+            planar.fill(T::EQUILIBRIUM);
+            data_callback(planar, info);
+            interleave(planar, samples, buffers.channels, 1);
+        }
+        None => {
+            samples.fill(T::EQUILIBRIUM);
+            data_callback(samples, info);
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn test_adapted_streams() {
+        use super::{AdapterConfig, ChannelMapping};
+        use crate::host::dummy::{Device, ManualClock};
+        use crate::traits::{DeviceTrait, StreamTrait};
+        use crate::{
+            BufferSize, SampleFormat, SampleLayout, SampleRate, StreamConfig, SupportedBufferSize,
+            SupportedStreamConfigRange,
+        };
+        use std::sync::{Arc, Mutex};
+
+        let range = SupportedStreamConfigRange::new(
+            6,
+            SampleRate(48_000),
+            SampleRate(48_000),
+            SupportedBufferSize::Unknown,
+            SampleFormat::I16,
+        );
+        let clock = ManualClock::new();
+        let device = Device::new("test")
+            .with_input_configs(vec![range])
+            .with_output_configs(vec![range])
+            .with_clock(clock.clone());
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(44_100),
+            buffer_size: BufferSize::Fixed(480),
+            layout: SampleLayout::Planar,
+        };
+        let adapter = AdapterConfig {
+            channels: ChannelMapping::Standard,
+            resample: true,
+            ..Default::default()
+        };
+
+        // The position and number of frames of every callback of either stream.
+        let input_frames = Arc::new(Mutex::new(vec![]));
+        let output_frames = Arc::new(Mutex::new(vec![]));
+        let frames = input_frames.clone();
+        let input = device
+            .build_input_stream_adapted(
+                &config,
+                &adapter,
+                move |data: &[f32], info| {
+                    assert!(data.iter().all(|&sample| sample == 0.0));
+                    let position = info.position().frames;
+                    frames.lock().unwrap().push((position, data.len() / 2));
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        let frames = output_frames.clone();
+        let output = device
+            .build_output_stream_adapted(
+                &config,
+                &adapter,
+                move |data: &mut [f32], info| {
+                    assert_eq!(data.len() % 2, 0);
+                    let (left, right) = data.split_at_mut(data.len() / 2);
+                    left.fill(0.5);
+                    right.fill(-0.5);
+                    let position = info.position().frames;
+                    frames.lock().unwrap().push((position, data.len() / 2));
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        input.play().unwrap();
+        output.play().unwrap();
+        for _ in 0..20 {
+            clock.advance(480);
+        }
+
+        // The callbacks cover contiguous frames at the requested rate.
+        for frames in [input_frames, output_frames] {
+            let frames = frames.lock().unwrap();
+            let mut expected = 0;
+            for &(position, len) in frames.iter() {
+                assert_eq!(position, expected);
+                expected += len as u64;
+            }
+            let seconds = 20.0 * 480.0 / 48_000.0;
+            assert!((expected as f64 - seconds * 44_100.0).abs() < 100.0);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Adapters passing blocks of a fixed number of frames to data callbacks.
+//!
+//! Hosts hand data callbacks as many frames as the device is ready for, which may vary from one
+//! call to the next. Algorithms processing fixed-size blocks, e.g. FFT-based ones, can wrap their
+//! callback in one of these adapters instead of buffering themselves:
+//!
+//! ```no_run
+//! use cpal::traits::{DeviceTrait, HostTrait};
+//! # let host = cpal::default_host();
+//! # let device = host.default_output_device().unwrap();
+//! # let config: cpal::StreamConfig = device.default_output_config().unwrap().into();
+//! let channels = config.channels as usize;
+//! let data_fn = cpal::fixed_block_output(&config, 256, move |data: &mut [f32], _| {
+//!     assert_eq!(data.len(), 256 * channels);
+//!     data.fill(0.0);
+//! });
+//! let stream = device.build_output_stream(&config, data_fn, |_err| {}, None);
+//! ```
+
+use crate::{
+    frames_to_duration, InputCallbackInfo, InputStreamTimestamp, OutputCallbackInfo,
+    OutputStreamTimestamp, Sample, SampleLayout, StreamConfig, StreamPosition,
+};
+
+/// Wrap an input data callback, so that it receives blocks of exactly `block_frames` frames.
+///
+/// Captured frames are collected until a block is complete, so the callback is called zero or
+/// more times per callback of the stream. The capture timestamp and position passed to the
+/// callback are those of the first frame of the block, which accounts for the time the block was
+/// buffered.
+///
+/// The buffer is allocated when calling this function, the returned callback never allocates.
+///
+/// # Panics
+///
+/// Panics if `block_frames` is zero or `config` uses the planar [`SampleLayout`].
+pub fn fixed_block_input<T, D>(
+    config: &StreamConfig,
+    block_frames: usize,
+    mut data_callback: D,
+) -> impl FnMut(&[T], &InputCallbackInfo) + Send + 'static
+where
+    T: Sample + Send + 'static,
+    D: FnMut(&[T], &InputCallbackInfo) + Send + 'static,
+{
+    assert!(block_frames > 0, "block_frames must be greater than zero");
+    assert_eq!(
+        config.layout,
+        SampleLayout::Interleaved,
+        "fixed blocks require interleaved samples"
+    );
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate;
+    let mut block = vec![T::EQUILIBRIUM; block_frames * channels];
+    // The number of samples of `block` that have been captured.
+    let mut filled = 0;
+    // Whether frames were lost since the last block was passed to the callback.
+    let mut discontinuity = false;
+    move |data, info| {
+        discontinuity |= info.position.discontinuity;
+        let mut read = 0;
+        while read < data.len() {
+            let len = (block.len() - filled).min(data.len() - read);
+            block[filled..filled + len].copy_from_slice(&data[read..read + len]);
+            filled += len;
+            read += len;
+            if filled < block.len() {
+                break;
+            }
+
+            // The first frame of the block may have been captured during a previous callback.
+            let capture = info.timestamp.capture;
+            let capture = if read >= block.len() {
+                capture.add(frames_to_duration(
+                    (read - block.len()) / channels,
+                    sample_rate,
+                ))
+            } else {
+                capture.sub(frames_to_duration(
+                    (block.len() - read) / channels,
+                    sample_rate,
+                ))
+            };
+            let timestamp = InputStreamTimestamp {
+                callback: info.timestamp.callback,
+                capture: capture.unwrap_or(info.timestamp.capture),
+            };
+            let position = StreamPosition {
+                frames: (info.position.frames + (read / channels) as u64)
+                    .saturating_sub(block_frames as u64),
+                discontinuity: std::mem::take(&mut discontinuity),
+            };
+            let info = InputCallbackInfo {
+                timestamp,
+                position,
+            };
+            data_callback(&block, &info);
+            filled = 0;
+        }
+    }
+}
+
+/// Wrap an output data callback, so that it is asked for blocks of exactly `block_frames` frames.
+///
+/// The callback is called whenever the previous block has been handed to the stream completely,
+/// so zero or more times per callback of the stream. Frames of a block that do not fit into the
+/// buffer of the stream are played during the following callbacks. The playback timestamp and
+/// position passed to the callback are those of the first frame of the block, which accounts for
+/// this delay.
+///
+/// The buffer is allocated when calling this function, the returned callback never allocates.
+///
+/// # Panics
+///
+/// Panics if `block_frames` is zero or `config` uses the planar [`SampleLayout`].
+pub fn fixed_block_output<T, D>(
+    config: &StreamConfig,
+    block_frames: usize,
+    mut data_callback: D,
+) -> impl FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static
+where
+    T: Sample + Send + 'static,
+    D: FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
+{
+    assert!(block_frames > 0, "block_frames must be greater than zero");
+    assert_eq!(
+        config.layout,
+        SampleLayout::Interleaved,
+        "fixed blocks require interleaved samples"
+    );
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate;
+    let mut block = vec![T::EQUILIBRIUM; block_frames * channels];
+    // The number of samples of `block` that have been written to the stream.
+    let mut consumed = block.len();
+    // Whether frames were lost since the last block was requested from the callback.
+    let mut discontinuity = false;
+    move |data, info| {
+        discontinuity |= info.position.discontinuity;
+        let mut written = 0;
+        while written < data.len() {
+            if consumed == block.len() {
+                let offset = frames_to_duration(written / channels, sample_rate);
+                let timestamp = OutputStreamTimestamp {
+                    callback: info.timestamp.callback,
+                    playback: info
+                        .timestamp
+                        .playback
+                        .add(offset)
+                        .unwrap_or(info.timestamp.playback),
+                };
+                let position = StreamPosition {
+                    frames: info.position.frames + (written / channels) as u64,
+                    discontinuity: std::mem::take(&mut discontinuity),
+                };
+                let info = OutputCallbackInfo {
+                    timestamp,
+                    position,
+                };
+                data_callback(&mut block, &info);
+                consumed = 0;
+            }
+            let len = (block.len() - consumed).min(data.len() - written);
+            data[written..written + len].copy_from_slice(&block[consumed..consumed + len]);
+            consumed += len;
+            written += len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BufferSize, SampleRate, StreamInstant};
+
+    fn config() -> StreamConfig {
+        StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(1000),
+            buffer_size: BufferSize::Default,
+            layout: SampleLayout::Interleaved,
+        }
+    }
+
+    #[test]
+    fn test_fixed_block_input() {
+        let mut blocks = 0;
+        let mut data_fn = fixed_block_input(&config(), 3, move |data: &[f32], info| {
+            assert_eq!(data.len(), 6);
+            // Every block is captured 3 frames, i.e. 3 ms, after the previous one, even if it
+            // started in an earlier callback.
+            let capture = info.timestamp().capture.as_nanos();
+            assert!((capture - blocks * 3_000_000).abs() <= 1);
+            assert_eq!(info.position().frames, blocks as u64 * 3);
+            for (i, &sample) in data.iter().enumerate() {
+                assert_eq!(sample, (blocks * 6 + i as i128) as f32);
+            }
+            blocks += 1;
+        });
+
+        for callback in 0..4 {
+            let instant = StreamInstant::new(0, callback * 4_000_000);
+            let info = InputCallbackInfo {
+                timestamp: InputStreamTimestamp {
+                    callback: instant,
+                    capture: instant,
+                },
+                position: StreamPosition {
+                    frames: callback as u64 * 4,
+                    discontinuity: false,
+                },
+            };
+            let input: Vec<f32> = (0..8).map(|i| (callback * 8 + i) as f32).collect();
+            data_fn(&input, &info);
+        }
+    }
+
+    #[test]
+    fn test_fixed_block_output() {
+        let mut blocks = 0;
+        let mut data_fn = fixed_block_output(&config(), 3, move |data: &mut [f32], info| {
+            assert_eq!(data.len(), 6);
+            // Every block starts 3 frames, i.e. 3 ms, after the previous one.
+            let playback = info.timestamp().playback.as_nanos();
+            assert!((playback - blocks * 3_000_000).abs() <= 1);
+            assert_eq!(info.position().frames, blocks as u64 * 3);
+            for (i, sample) in data.iter_mut().enumerate() {
+                *sample = (blocks * 6 + i as i128) as f32;
+            }
+            blocks += 1;
+        });
+
+        let mut output = [0.0f32; 8];
+        for callback in 0..4 {
+            let instant = StreamInstant::new(0, callback * 4_000_000);
+            let info = OutputCallbackInfo {
+                timestamp: OutputStreamTimestamp {
+                    callback: instant,
+                    playback: instant,
+                },
+                position: StreamPosition {
+                    frames: callback as u64 * 4,
+                    discontinuity: false,
+                },
+            };
+            data_fn(&mut output, &info);
+            for (i, &sample) in output.iter().enumerate() {
+                assert_eq!(sample, (callback as usize * 8 + i) as f32);
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! State of the data callbacks of stream adapters, allocated before the stream is played.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::traits::StreamTrait;
+use crate::{max_callback_frames, BufferSize, BuildStreamError};
+
+/// The state of the data callback of a stream adapter, prepared by [`build_with_state`] once
+/// the stream is built.
+pub(crate) struct CallbackState<T> {
+    state: Arc<Mutex<T>>,
+}
+
+impl<T> CallbackState<T> {
+    /// Lock the state. Returns `None` while the stream is being built.
+    pub(crate) fn lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.state.try_lock().ok()
+    }
+}
+
+impl<T: Clone> CallbackState<Vec<T>> {
+    /// Lock the buffer, holding at least `len` samples. Returns `None` while the stream is being
+    /// built.
+    ///
+    /// The buffer only grows if the host passes more frames to the callback than it reported.
+    pub(crate) fn lock_buffer(&self, len: usize, fill: T) -> Option<MutexGuard<'_, Vec<T>>> {
+        let mut samples = self.lock()?;
+        if samples.len() < len {
+            samples.resize(len, fill);
+        }
+        Some(samples)
+    }
+}
+
+/// Build a stream via `build`, whose data callback uses the given state. Once the stream is
+/// built, the state is passed to `prepare` with the maximum number of frames per callback of the
+/// stream, e.g. to allocate buffers. `buffer_size` is the requested buffer size, used if the
+/// stream does not report its buffer size.
+pub(crate) fn build_with_state<S, T, B, P>(
+    buffer_size: BufferSize,
+    state: T,
+    prepare: P,
+    build: B,
+) -> Result<S, BuildStreamError>
+where
+    S: StreamTrait,
+    B: FnOnce(CallbackState<T>) -> Result<S, BuildStreamError>,
+    P: FnOnce(&mut T, usize),
+{
+    let state = Arc::new(Mutex::new(state));
+    // Held while the stream is being built, so that callbacks run in the meantime do not use
+    // the state before it is prepared.
+    let mut building = state.lock().unwrap();
+    let stream = build(CallbackState {
+        state: state.clone(),
+    })?;
+    prepare(&mut building, max_callback_frames(&stream, buffer_size));
+    drop(building);
+    Ok(stream)
+}
+
+/// Build a stream via `build`, whose data callback uses the given buffer. Once the stream is
+/// built, the buffer is filled with `samples(frames)` samples, where `frames` is the maximum number
+/// of frames per callback of the stream.
+pub(crate) fn build_with_buffer<S, T, B, N>(
+    buffer_size: BufferSize,
+    samples: N,
+    fill: T,
+    build: B,
+) -> Result<S, BuildStreamError>
+where
+    S: StreamTrait,
+    T: Clone,
+    B: FnOnce(CallbackState<Vec<T>>) -> Result<S, BuildStreamError>,
+    N: FnOnce(usize) -> usize,
+{
+    build_with_state(
+        buffer_size,
+        Vec::new(),
+        |buffer, frames| buffer.resize(samples(frames), fill),
+        build,
+    )
+}
@@ -0,0 +1,263 @@
+//! Converting between the sample type of the data callback and the sample format of the device.
+
+use crate::{
+    BuildStreamError, FromSample, SampleFormat, StreamConfig, SupportedStreamConfigRange,
+    SupportedStreamConfigsError,
+};
+
+/// The dither added to samples converted to a sample format of lower precision.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dither {
+    /// Round samples without adding any noise.
+    #[default]
+    None,
+    /// Add triangular probability density function (TPDF) noise with a peak amplitude of one
+    /// least significant bit when converting floating point samples to integer samples. This
+    /// turns the quantization error into uncorrelated noise.
+    Triangular,
+}
+
+// The sample formats the device is opened at if it does not support the sample type of the
+// callback, from most to least preferred. Floating point formats come first, as they convert
+// to and from any other format without clipping, followed by integer formats from the most to
+// the least precise.
+const PREFERRED_FORMATS: [SampleFormat; 14] = [
+    SampleFormat::F64,
+    SampleFormat::F32,
+    SampleFormat::I64,
+    SampleFormat::U64,
+    SampleFormat::I48,
+    SampleFormat::U48,
+    SampleFormat::I32,
+    SampleFormat::U32,
+    SampleFormat::I24,
+    SampleFormat::U24,
+    SampleFormat::I16,
+    SampleFormat::U16,
+    SampleFormat::I8,
+    SampleFormat::U8,
+];
+
+// Run `$body` with `$S` being the sample type of `$format`.
+macro_rules! with_sample_type {
+    ($format:expr, $S:ident => $body:expr) => {
+        match $format {
+            SampleFormat::I8 => {
+                type $S = i8;
+                $body
+            }
+            SampleFormat::I16 => {
+                type $S = i16;
+                $body
+            }
+            SampleFormat::I24 => {
+                type $S = crate::I24;
+                $body
+            }
+            SampleFormat::I32 => {
+                type $S = i32;
+                $body
+            }
+            SampleFormat::I48 => {
+                type $S = crate::I48;
+                $body
+            }
+            SampleFormat::I64 => {
+                type $S = i64;
+                $body
+            }
+            SampleFormat::U8 => {
+                type $S = u8;
+                $body
+            }
+            SampleFormat::U16 => {
+                type $S = u16;
+                $body
+            }
+            SampleFormat::U24 => {
+                type $S = crate::U24;
+                $body
+            }
+            SampleFormat::U32 => {
+                type $S = u32;
+                $body
+            }
+            SampleFormat::U48 => {
+                type $S = crate::U48;
+                $body
+            }
+            SampleFormat::U64 => {
+                type $S = u64;
+                $body
+            }
+            SampleFormat::F32 => {
+                type $S = f32;
+                $body
+            }
+            SampleFormat::F64 => {
+                type $S = f64;
+                $body
+            }
+        }
+    };
+}
+pub(crate) use with_sample_type;
+
+// The sample format to open the device at for the given config: `preferred` if it is supported,
+// otherwise the supported format that comes first in `PREFERRED_FORMATS`.
+pub(crate) fn native_format(
+    configs: impl Iterator<Item = SupportedStreamConfigRange>,
+    config: &StreamConfig,
+    preferred: SampleFormat,
+) -> Result<SampleFormat, BuildStreamError> {
+    let formats: Vec<_> = configs
+        .filter(|range| {
+            range.channels() == config.channels
+                && range.min_sample_rate() <= config.sample_rate
+                && config.sample_rate <= range.max_sample_rate()
+        })
+        .map(|range| range.sample_format())
+        .collect();
+    if formats.contains(&preferred) {
+        return Ok(preferred);
+    }
+    PREFERRED_FORMATS
+        .iter()
+        .find(|format| formats.contains(format))
+        .copied()
+        .ok_or(BuildStreamError::StreamConfigNotSupported)
+}
+
+pub(crate) fn build_stream_error(err: SupportedStreamConfigsError) -> BuildStreamError {
+    match err {
+        SupportedStreamConfigsError::DeviceNotAvailable => BuildStreamError::DeviceNotAvailable,
+        SupportedStreamConfigsError::DeviceBusy => BuildStreamError::DeviceBusy,
+        SupportedStreamConfigsError::InvalidArgument => BuildStreamError::InvalidArgument,
+        SupportedStreamConfigsError::BackendSpecific { err } => err.into(),
+    }
+}
+
+// Converts samples between two formats, with `f64` as the intermediate representation.
+pub(crate) struct Converter {
+    // The amplitude of one least significant bit of the destination format, if dither is added.
+    dither_lsb: Option<f64>,
+    // The state of the xorshift generator used for the dither noise.
+    rng: u64,
+}
+
+impl Converter {
+    pub(crate) fn new(dither: Dither, from: SampleFormat, to: SampleFormat) -> Self {
+        let dither_lsb = match dither {
+            Dither::Triangular if from.is_float() && !to.is_float() => {
+                Some(2.0 / 2f64.powi(bits(to) as i32))
+            }
+            _ => None,
+        };
+        Converter {
+            dither_lsb,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    // Samples are converted through `f64` rather than the float type of either format, which
+    // would drop the lower bits of 32-bit and wider integer samples.
+    pub(crate) fn convert<A, B>(&mut self, from: &[A], to: &mut [B])
+    where
+        A: Copy,
+        B: FromSample<f64>,
+        f64: FromSample<A>,
+    {
+        for (to, &from) in to.iter_mut().zip(from) {
+            let mut sample = f64::from_sample_(from);
+            if let Some(lsb) = self.dither_lsb {
+                sample += (self.next_random() - self.next_random()) * lsb;
+                // Integer conversions truncate towards zero, which would bias the dithered
+                // samples, so round to the nearest step of the destination format first.
+                sample = (sample / lsb).round() * lsb;
+            }
+            *to = B::from_sample_(sample);
+        }
+    }
+
+    // A uniformly distributed number in `0.0..1.0`.
+    fn next_random(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// The number of significant bits of a sample format.
+fn bits(format: SampleFormat) -> u32 {
+    match format {
+        SampleFormat::I24 | SampleFormat::U24 => 24,
+        SampleFormat::I48 | SampleFormat::U48 => 48,
+        format => format.sample_size() as u32 * 8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Converter, Dither};
+    use crate::SampleFormat;
+
+    #[test]
+    fn test_i32_round_trip() {
+        let samples = [i32::MIN, -0x1234_5678, -1, 0, 1, 0x7654_3210, i32::MAX];
+        for format in [SampleFormat::F32, SampleFormat::F64] {
+            let mut float = [0.0; 7];
+            let mut round_trip = [0; 7];
+            Converter::new(Dither::None, SampleFormat::I32, format).convert(&samples, &mut float);
+            Converter::new(Dither::None, format, SampleFormat::I32)
+                .convert(&float, &mut round_trip);
+            assert_eq!(round_trip, samples);
+        }
+
+        let samples = [0, 1, 0x8000_0000, 0xfedc_ba98, u32::MAX];
+        let mut float = [0.0; 5];
+        let mut round_trip = [0; 5];
+        Converter::new(Dither::None, SampleFormat::U32, SampleFormat::F64)
+            .convert(&samples, &mut float);
+        Converter::new(Dither::None, SampleFormat::F64, SampleFormat::U32)
+            .convert(&float, &mut round_trip);
+        assert_eq!(round_trip, samples);
+    }
+
+    #[test]
+    fn test_triangular_dither() {
+        let lsb = 1.0 / 32768.0;
+        let mut output = vec![0i16; 100_000];
+
+        // Without dither, samples on a step of the destination format convert exactly.
+        let input = vec![100.0 * lsb; output.len()];
+        Converter::new(Dither::None, SampleFormat::F64, SampleFormat::I16)
+            .convert(&input, &mut output);
+        assert!(output.iter().all(|&sample| sample == 100));
+
+        // With dither, the noise peaks at one step, which rounds to the neighbouring steps a
+        // quarter of the time.
+        Converter::new(Dither::Triangular, SampleFormat::F64, SampleFormat::I16)
+            .convert(&input, &mut output);
+        assert!(output.iter().all(|&sample| (99..=101).contains(&sample)));
+        let off_step = output.iter().filter(|&&sample| sample != 100).count();
+        let ratio = off_step as f64 / output.len() as f64;
+        assert!((0.23..0.27).contains(&ratio), "{}", ratio);
+        let mean = output.iter().map(|&sample| sample as f64).sum::<f64>() / output.len() as f64;
+        assert!((mean - 100.0).abs() < 0.01, "{}", mean);
+
+        // Halfway between two steps, the dithered samples average to the input.
+        let input = vec![100.5 * lsb; output.len()];
+        Converter::new(Dither::Triangular, SampleFormat::F64, SampleFormat::I16)
+            .convert(&input, &mut output);
+        assert!(output.iter().all(|&sample| (99..=102).contains(&sample)));
+        let mean = output.iter().map(|&sample| sample as f64).sum::<f64>() / output.len() as f64;
+        assert!((mean - 100.5).abs() < 0.01, "{}", mean);
+
+        // Integer to integer conversions are not dithered.
+        let input = vec![100i32 << 16; output.len()];
+        Converter::new(Dither::Triangular, SampleFormat::I32, SampleFormat::I16)
+            .convert(&input, &mut output);
+        assert!(output.iter().all(|&sample| sample == 100));
+    }
+}
@@ -0,0 +1,481 @@
+//! Full-duplex streams and the fallback used by hosts without native duplex support.
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::traits::{DeviceTrait, StreamTrait};
+use crate::{
+    frames_to_duration, max_callback_frames, BuildStreamError, Data, DuplexCallbackInfo,
+    DuplexStreamConfig, DuplexStreamTimestamp, PauseStreamError, PlayStreamError, SampleFormat,
+    SampleRate, SizedSample, StreamBufferSize, StreamBufferSizeError, StreamError, StreamInstant,
+    StreamLatencyError, StreamPositionError, StreamStats, StreamStatsError, I24, I48, U24, U48,
+};
+
+/// A full-duplex stream created via
+/// [`build_duplex_stream`](crate::traits::DeviceTrait::build_duplex_stream).
+///
+/// Depending on the host, the stream is either driven natively by a single callback source, or
+/// made up of a linked input and output stream.
+#[must_use = "If the stream is not stored it will not play."]
+pub struct DuplexStream<S> {
+    inner: DuplexStreamInner<S>,
+}
+
+enum DuplexStreamInner<S> {
+    Native(S),
+    Linked { input: S, output: S },
+}
+
+impl<S> DuplexStream<S> {
+    pub(crate) fn native(stream: S) -> Self {
+        DuplexStream {
+            inner: DuplexStreamInner::Native(stream),
+        }
+    }
+
+    pub(crate) fn linked(input: S, output: S) -> Self {
+        DuplexStream {
+            inner: DuplexStreamInner::Linked { input, output },
+        }
+    }
+
+    /// Whether input and output are driven natively by the host from a single clock.
+    ///
+    /// Returns `false` if the stream is made up of separate input and output streams linked by a
+    /// queue, in which case the two devices may drift apart over time.
+    pub fn is_native(&self) -> bool {
+        matches!(self.inner, DuplexStreamInner::Native(_))
+    }
+
+    /// Convert the underlying stream type, e.g. to wrap a host-specific stream in a dynamically
+    /// dispatched one.
+    pub(crate) fn map<U, F>(self, mut f: F) -> DuplexStream<U>
+    where
+        F: FnMut(S) -> U,
+    {
+        let inner = match self.inner {
+            DuplexStreamInner::Native(stream) => DuplexStreamInner::Native(f(stream)),
+            DuplexStreamInner::Linked { input, output } => DuplexStreamInner::Linked {
+                input: f(input),
+                output: f(output),
+            },
+        };
+        DuplexStream { inner }
+    }
+}
+
+impl<S: StreamTrait> StreamTrait for DuplexStream<S> {
+    fn play(&self) -> Result<(), PlayStreamError> {
+        match self.inner {
+            DuplexStreamInner::Native(ref stream) => stream.play(),
+            DuplexStreamInner::Linked {
+                ref input,
+                ref output,
+            } => {
+                input.play()?;
+                output.play()
+            }
+        }
+    }
+
+    fn pause(&self) -> Result<(), PauseStreamError> {
+        match self.inner {
+            DuplexStreamInner::Native(ref stream) => stream.pause(),
+            DuplexStreamInner::Linked {
+                ref input,
+                ref output,
+            } => {
+                output.pause()?;
+                input.pause()
+            }
+        }
+    }
+
+    fn latency(&self) -> Result<Duration, StreamLatencyError> {
+        match self.inner {
+            DuplexStreamInner::Native(ref stream) => stream.latency(),
+            DuplexStreamInner::Linked {
+                ref input,
+                ref output,
+            } => Ok(input.latency()? + output.latency()?),
+        }
+    }
+
+    fn buffer_size(&self) -> Result<StreamBufferSize, StreamBufferSizeError> {
+        match self.inner {
+            DuplexStreamInner::Native(ref stream) => stream.buffer_size(),
+            // The data callback runs whenever the output stream requests data.
+            DuplexStreamInner::Linked { ref output, .. } => output.buffer_size(),
+        }
+    }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        match self.inner {
+            DuplexStreamInner::Native(ref stream) => stream.position(),
+            DuplexStreamInner::Linked { ref output, .. } => output.position(),
+        }
+    }
+
+    fn enable_stats(&self) -> Result<(), StreamStatsError> {
+        match self.inner {
+            DuplexStreamInner::Native(ref stream) => stream.enable_stats(),
+            DuplexStreamInner::Linked {
+                ref input,
+                ref output,
+            } => {
+                output.enable_stats()?;
+                // Only the xruns and late wakeups of the input are counted, if it can.
+                let _ = input.enable_stats();
+                Ok(())
+            }
+        }
+    }
+
+    fn stats(&self) -> Result<StreamStats, StreamStatsError> {
+        match self.inner {
+            DuplexStreamInner::Native(ref stream) => stream.stats(),
+            // The data callback runs on the thread of the output stream.
+            DuplexStreamInner::Linked {
+                ref input,
+                ref output,
+            } => {
+                let mut stats = output.stats()?;
+                if let Ok(input) = input.stats() {
+                    stats.xruns += input.xruns;
+                    stats.late_wakeups += input.late_wakeups;
+                }
+                Ok(stats)
+            }
+        }
+    }
+}
+
+/// Build a duplex stream out of an input and an output stream on the same device.
+pub(crate) fn build_duplex_stream_fallback<Dev, D, E>(
+    device: &Dev,
+    config: &DuplexStreamConfig,
+    sample_format: SampleFormat,
+    mut data_callback: D,
+    error_callback: E,
+    timeout: Option<Duration>,
+) -> Result<DuplexStream<Dev::Stream>, BuildStreamError>
+where
+    Dev: DeviceTrait,
+    D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    let sample_size = sample_format.sample_size();
+    let input_frame_size = config.input_channels as usize * sample_size;
+    let output_channels = config.output_channels as usize;
+    let sample_rate = config.sample_rate;
+
+    // The queue is sized from the buffer sizes of both streams, so it is created once they are
+    // built. Until then, captured frames are dropped and the output is silent.
+    let queue = Arc::new(OnceLock::<CaptureQueue>::new());
+    let error_callback = Arc::new(Mutex::new(error_callback));
+
+    let input_queue = queue.clone();
+    let input_error_callback = error_callback.clone();
+    let input = device.build_input_stream_raw(
+        &config.input_config(),
+        sample_format,
+        move |data, info| {
+            if let Some(queue) = input_queue.get() {
+                queue.push(
+                    data.bytes(),
+                    info.timestamp().capture,
+                    info.position().discontinuity,
+                );
+            }
+        },
+        move |err| (input_error_callback.lock().unwrap())(err),
+        timeout,
+    )?;
+
+    let output_queue = queue.clone();
+    let output = device.build_output_stream_raw(
+        &config.output_config(),
+        sample_format,
+        move |output, info| {
+            let Some(queue) = output_queue.get() else {
+                fill_equilibrium(output, 0);
+                return;
+            };
+            let frames = output.len() / output_channels;
+            // SAFETY: The output callback is the only consumer of the queue.
+            let captured = unsafe { queue.pop(frames) };
+            let mut input = unsafe {
+                Data::from_parts(
+                    captured.bytes.as_mut_ptr() as *mut (),
+                    captured.bytes.len() / sample_size,
+                    sample_format,
+                )
+            };
+            if captured.filled < captured.bytes.len() {
+                // Not enough input has been captured yet, pad with silence.
+                fill_equilibrium(&mut input, captured.filled / sample_size);
+            }
+
+            let playback = info.timestamp().playback;
+            let timestamp = DuplexStreamTimestamp {
+                callback: info.timestamp().callback,
+                capture: captured.capture.unwrap_or(playback),
+                playback,
+            };
+            let mut position = info.position();
+            position.discontinuity |= captured.discontinuity;
+            let info = DuplexCallbackInfo {
+                timestamp,
+                position,
+            };
+            data_callback(&input, output, &info);
+        },
+        move |err| (error_callback.lock().unwrap())(err),
+        timeout,
+    )?;
+
+    let input_frames = max_callback_frames(&input, config.buffer_size);
+    let output_frames = max_callback_frames(&output, config.buffer_size);
+    // The output callback keeps at most two of its buffers queued, so the queue has room for
+    // those and another input buffer.
+    let _ = queue.set(CaptureQueue::new(
+        input_frame_size,
+        sample_rate,
+        input_frames + 2 * output_frames,
+        output_frames,
+    ));
+
+    Ok(DuplexStream::linked(input, output))
+}
+
+// Captured frames on their way from the input to the output callback of a linked duplex stream.
+//
+// The input callback is the only producer and the output callback the only consumer, which
+// allows the queue to be lock-free. It never allocates once created, unless the output callback
+// receives more frames than its stream reported.
+struct CaptureQueue {
+    frame_size: usize,
+    sample_rate: SampleRate,
+    ring: Box<[UnsafeCell<u8>]>,
+    // The number of bytes written and read since the queue was created. Only the producer
+    // advances `written` and only the consumer advances `read`. Both are multiples of
+    // `frame_size`.
+    written: AtomicUsize,
+    read: AtomicUsize,
+    // The capture instant of the first frame ever written in nanoseconds, derived from the
+    // timestamp of the latest input callback, or `i64::MIN` before the first one.
+    origin_nanos: AtomicI64,
+    // Whether captured frames were lost since the last output callback.
+    discontinuity: AtomicBool,
+    // The captured input passed to the output callback, only accessed by the consumer.
+    input: UnsafeCell<Vec<u8>>,
+}
+
+// SAFETY: The producer only writes the bytes of `ring` that the consumer has already read and
+// publishes them via `written`, the consumer only reads published bytes and releases them via
+// `read`. `input` is only accessed by the consumer.
+unsafe impl Sync for CaptureQueue {}
+
+// The input of a single call of the output callback.
+struct Captured<'a> {
+    bytes: &'a mut [u8],
+    // The number of leading bytes of `bytes` that were captured, the rest is to be silenced.
+    filled: usize,
+    // The capture instant of the first frame of `bytes`.
+    capture: Option<StreamInstant>,
+    discontinuity: bool,
+}
+
+impl CaptureQueue {
+    fn new(
+        frame_size: usize,
+        sample_rate: SampleRate,
+        capacity_frames: usize,
+        callback_frames: usize,
+    ) -> Self {
+        CaptureQueue {
+            frame_size,
+            sample_rate,
+            ring: (0..capacity_frames * frame_size)
+                .map(|_| UnsafeCell::new(0))
+                .collect(),
+            written: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            origin_nanos: AtomicI64::new(i64::MIN),
+            discontinuity: AtomicBool::new(false),
+            input: UnsafeCell::new(vec![0; callback_frames * frame_size]),
+        }
+    }
+
+    // Queue captured frames, the first of which was captured at `capture`. Frames that do not
+    // fit are dropped.
+    fn push(&self, bytes: &[u8], capture: StreamInstant, discontinuity: bool) {
+        let written = self.written.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+        let free = self.ring.len() - (written - read);
+        let len = bytes.len().min(free);
+        let len = len - len % self.frame_size;
+
+        let origin = capture
+            .sub(frames_to_duration(
+                written / self.frame_size,
+                self.sample_rate,
+            ))
+            .map_or(i64::MIN, |origin| {
+                origin
+                    .as_nanos()
+                    .clamp(i64::MIN as i128 + 1, i64::MAX as i128) as i64
+            });
+        self.origin_nanos.store(origin, Ordering::Relaxed);
+        if discontinuity || len < bytes.len() {
+            self.discontinuity.store(true, Ordering::Relaxed);
+        }
+
+        let ring = UnsafeCell::raw_get(self.ring.as_ptr());
+        let start = written % self.ring.len();
+        let first = len.min(self.ring.len() - start);
+        // SAFETY: The written range is free, i.e. not read by the consumer until published.
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ring.add(start), first);
+            std::ptr::copy_nonoverlapping(bytes[first..].as_ptr(), ring, len - first);
+        }
+        self.written.store(written + len, Ordering::Release);
+    }
+
+    // Take the next `frames` frames out of the queue, padded if fewer have been captured.
+    //
+    // SAFETY: Must only be called by the consumer, and the result dropped before calling again.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn pop(&self, frames: usize) -> Captured<'_> {
+        let input = &mut *self.input.get();
+        let needed = frames * self.frame_size;
+        if input.len() < needed {
+            input.resize(needed, 0);
+        }
+
+        let written = self.written.load(Ordering::Acquire);
+        let mut read = self.read.load(Ordering::Relaxed);
+        // Keep at most two callbacks worth of input queued to bound the added latency.
+        let excess = (written - read).saturating_sub(2 * needed);
+        read += excess;
+        let filled = needed.min(written - read);
+
+        let ring = UnsafeCell::raw_get(self.ring.as_ptr());
+        let start = read % self.ring.len();
+        let first = filled.min(self.ring.len() - start);
+        std::ptr::copy_nonoverlapping(ring.add(start), input.as_mut_ptr(), first);
+        std::ptr::copy_nonoverlapping(ring, input[first..].as_mut_ptr(), filled - first);
+
+        let capture = match self.origin_nanos.load(Ordering::Relaxed) {
+            i64::MIN => None,
+            origin => StreamInstant::from_nanos(origin)
+                .add(frames_to_duration(read / self.frame_size, self.sample_rate)),
+        };
+        self.read.store(read + filled, Ordering::Release);
+        let discontinuity = self.discontinuity.swap(false, Ordering::Relaxed) || excess > 0;
+        Captured {
+            bytes: &mut input[..needed],
+            filled,
+            capture,
+            discontinuity,
+        }
+    }
+}
+
+// Fill the samples of `data` starting at `offset` with the sample format's equilibrium.
+pub(crate) fn fill_equilibrium(data: &mut Data, offset: usize) {
+    fn fill<T: SizedSample>(data: &mut Data, offset: usize) {
+        if let Some(samples) = data.as_slice_mut::<T>() {
+            for sample in &mut samples[offset..] {
+                *sample = T::EQUILIBRIUM;
+            }
+        }
+    }
+
+    match data.sample_format() {
+        SampleFormat::I8 => fill::<i8>(data, offset),
+        SampleFormat::I16 => fill::<i16>(data, offset),
+        SampleFormat::I24 => fill::<I24>(data, offset),
+        SampleFormat::I32 => fill::<i32>(data, offset),
+        SampleFormat::I48 => fill::<I48>(data, offset),
+        SampleFormat::I64 => fill::<i64>(data, offset),
+        SampleFormat::U8 => fill::<u8>(data, offset),
+        SampleFormat::U16 => fill::<u16>(data, offset),
+        SampleFormat::U24 => fill::<U24>(data, offset),
+        SampleFormat::U32 => fill::<u32>(data, offset),
+        SampleFormat::U48 => fill::<U48>(data, offset),
+        SampleFormat::U64 => fill::<u64>(data, offset),
+        SampleFormat::F32 => fill::<f32>(data, offset),
+        SampleFormat::F64 => fill::<f64>(data, offset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A queue of mono 8-bit frames at 1 kHz, i.e. of one byte per millisecond.
+    fn mono_queue(capacity_frames: usize) -> CaptureQueue {
+        CaptureQueue::new(1, SampleRate(1000), capacity_frames, 2)
+    }
+
+    fn millis(millis: u32) -> StreamInstant {
+        StreamInstant::new(0, millis * 1_000_000)
+    }
+
+    #[test]
+    fn test_capture_queue() {
+        let queue = mono_queue(8);
+        let captured = unsafe { queue.pop(2) };
+        assert_eq!(captured.filled, 0);
+        assert_eq!(captured.capture, None);
+
+        queue.push(&[1, 2, 3], millis(10), false);
+        let captured = unsafe { queue.pop(2) };
+        assert_eq!(captured.bytes, &[1, 2]);
+        assert_eq!(captured.capture, Some(millis(10)));
+        assert!(!captured.discontinuity);
+        // The third frame is returned with padding.
+        let captured = unsafe { queue.pop(2) };
+        assert_eq!(captured.filled, 1);
+        assert_eq!(captured.bytes[0], 3);
+        assert_eq!(captured.capture, Some(millis(12)));
+
+        // The capture instants follow the latest input callback, across the end of the ring.
+        for i in 0..10 {
+            queue.push(&[i, i + 1], millis(20 + 2 * i as u32), false);
+            let captured = unsafe { queue.pop(2) };
+            assert_eq!(captured.bytes, &[i, i + 1]);
+            assert_eq!(captured.capture, Some(millis(20 + 2 * i as u32)));
+        }
+    }
+
+    #[test]
+    fn test_capture_queue_drops_frames() {
+        // Frames beyond two output buffers are skipped to bound the latency.
+        let queue = mono_queue(8);
+        queue.push(&[1, 2, 3, 4, 5, 6], millis(0), false);
+        let captured = unsafe { queue.pop(1) };
+        assert_eq!(captured.bytes, &[5]);
+        assert_eq!(captured.capture, Some(millis(4)));
+        assert!(captured.discontinuity);
+        let captured = unsafe { queue.pop(1) };
+        assert_eq!(captured.bytes, &[6]);
+        assert!(!captured.discontinuity);
+
+        // Frames that do not fit into the queue are dropped.
+        let queue = mono_queue(4);
+        queue.push(&[1, 2, 3, 4, 5, 6], millis(0), false);
+        let captured = unsafe { queue.pop(2) };
+        assert_eq!(captured.bytes, &[1, 2]);
+        assert!(captured.discontinuity);
+
+        // Discontinuities of the input stream are passed on.
+        queue.push(&[7], millis(10), true);
+        let captured = unsafe { queue.pop(2) };
+        assert_eq!(captured.bytes, &[3, 4]);
+        assert!(captured.discontinuity);
+    }
+}
@@ -13,6 +13,19 @@ impl Display for HostUnavailable {
 
 impl Error for HostUnavailable {}
 
+/// The string does not start with the name of a host supported on this platform, followed by a
+/// colon, and thus is not a valid [`DeviceId`](crate::DeviceId).
+#[derive(Copy, Clone, Debug)]
+pub struct ParseDeviceIdError;
+
+impl Display for ParseDeviceIdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the string is not a valid device id")
+    }
+}
+
+impl Error for ParseDeviceIdError {}
+
 /// Some error has occurred that is specific to the backend from which it was produced.
 ///
 /// This error is often used as a catch-all in cases where:
@@ -65,7 +78,35 @@ impl From<BackendSpecificError> for DevicesError {
     }
 }
 
-/// An error that may occur while attempting to retrieve a device name.
+/// An error that might occur while attempting to watch for device changes on a host.
+#[derive(Clone, Debug)]
+pub enum WatchDevicesError {
+    /// The host does not support device change notifications.
+    NotSupported,
+    /// See the [`BackendSpecificError`] docs for more information about this error variant.
+    BackendSpecific { err: BackendSpecificError },
+}
+
+impl Display for WatchDevicesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BackendSpecific { err } => err.fmt(f),
+            WatchDevicesError::NotSupported => {
+                f.write_str("The host does not support device change notifications.")
+            }
+        }
+    }
+}
+
+impl Error for WatchDevicesError {}
+
+impl From<BackendSpecificError> for WatchDevicesError {
+    fn from(err: BackendSpecificError) -> Self {
+        Self::BackendSpecific { err }
+    }
+}
+
+/// An error that may occur while attempting to retrieve a device name or description.
 #[derive(Clone, Debug)]
 pub enum DeviceNameError {
     /// See the [`BackendSpecificError`] docs for more information about this error variant.
@@ -88,12 +129,43 @@ impl From<BackendSpecificError> for DeviceNameError {
     }
 }
 
+/// An error that may occur while attempting to retrieve a device id.
+#[derive(Clone, Debug)]
+pub enum DeviceIdError {
+    /// The host does not provide stable device identifiers.
+    NotSupported,
+    /// See the [`BackendSpecificError`] docs for more information about this error variant.
+    BackendSpecific { err: BackendSpecificError },
+}
+
+impl Display for DeviceIdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BackendSpecific { err } => err.fmt(f),
+            DeviceIdError::NotSupported => {
+                f.write_str("The host does not provide stable device identifiers.")
+            }
+        }
+    }
+}
+
+impl Error for DeviceIdError {}
+
+impl From<BackendSpecificError> for DeviceIdError {
+    fn from(err: BackendSpecificError) -> Self {
+        Self::BackendSpecific { err }
+    }
+}
+
 /// Error that can happen when enumerating the list of supported formats.
 #[derive(Clone, Debug)]
 pub enum SupportedStreamConfigsError {
     /// The device no longer exists. This can happen if the device is disconnected while the
     /// program is running.
     DeviceNotAvailable,
+    /// The device is in use, e.g. by another application, and cannot be opened until it is
+    /// released.
+    DeviceBusy,
     /// We called something the C-Layer did not understand
     InvalidArgument,
     /// See the [`BackendSpecificError`] docs for more information about this error variant.
@@ -105,6 +177,7 @@ impl Display for SupportedStreamConfigsError {
         match self {
             Self::BackendSpecific { err } => err.fmt(f),
             Self::DeviceNotAvailable => f.write_str("The requested device is no longer available. For example, it has been unplugged."),
+            Self::DeviceBusy => f.write_str("The requested device is busy. It may be in use by another application."),
             Self::InvalidArgument => f.write_str("Invalid argument passed to the backend. For example, this happens when trying to read capture capabilities when the device does not support it.")
         }
     }
@@ -124,6 +197,9 @@ pub enum DefaultStreamConfigError {
     /// The device no longer exists. This can happen if the device is disconnected while the
     /// program is running.
     DeviceNotAvailable,
+    /// The device is in use, e.g. by another application, and cannot be opened until it is
+    /// released.
+    DeviceBusy,
     /// Returned if e.g. the default input format was requested on an output-only audio device.
     StreamTypeNotSupported,
     /// See the [`BackendSpecificError`] docs for more information about this error variant.
@@ -137,6 +213,9 @@ impl Display for DefaultStreamConfigError {
             DefaultStreamConfigError::DeviceNotAvailable => f.write_str(
                 "The requested device is no longer available. For example, it has been unplugged.",
             ),
+            DefaultStreamConfigError::DeviceBusy => f.write_str(
+                "The requested device is busy. It may be in use by another application.",
+            ),
             DefaultStreamConfigError::StreamTypeNotSupported => {
                 f.write_str("The requested stream type is not supported by the device.")
             }
@@ -157,6 +236,9 @@ pub enum BuildStreamError {
     /// The device no longer exists. This can happen if the device is disconnected while the
     /// program is running.
     DeviceNotAvailable,
+    /// The device is in use, e.g. by another application, and cannot be opened until it is
+    /// released.
+    DeviceBusy,
     /// The specified stream configuration is not supported.
     StreamConfigNotSupported,
     /// We called something the C-Layer did not understand
@@ -177,6 +259,9 @@ impl Display for BuildStreamError {
             BuildStreamError::DeviceNotAvailable => f.write_str(
                 "The requested device is no longer available. For example, it has been unplugged.",
             ),
+            BuildStreamError::DeviceBusy => f.write_str(
+                "The requested device is busy. It may be in use by another application.",
+            ),
             BuildStreamError::StreamConfigNotSupported => {
                 f.write_str("The requested stream configuration is not supported by the device.")
             }
@@ -262,12 +347,142 @@ impl From<BackendSpecificError> for PauseStreamError {
     }
 }
 
+/// Errors that might occur when calling [`latency`](crate::traits::StreamTrait::latency).
+#[derive(Clone, Debug)]
+pub enum StreamLatencyError {
+    /// The host does not report stream latency.
+    NotSupported,
+    /// See the [`BackendSpecificError`] docs for more information about this error variant.
+    BackendSpecific { err: BackendSpecificError },
+}
+
+impl Display for StreamLatencyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BackendSpecific { err } => err.fmt(f),
+            StreamLatencyError::NotSupported => {
+                f.write_str("The host does not report stream latency.")
+            }
+        }
+    }
+}
+
+impl Error for StreamLatencyError {}
+
+impl From<BackendSpecificError> for StreamLatencyError {
+    fn from(err: BackendSpecificError) -> Self {
+        Self::BackendSpecific { err }
+    }
+}
+
+/// Errors that might occur when calling [`buffer_size`](crate::traits::StreamTrait::buffer_size).
+#[derive(Clone, Debug)]
+pub enum StreamBufferSizeError {
+    /// The host does not report the buffer size of streams.
+    NotSupported,
+    /// See the [`BackendSpecificError`] docs for more information about this error variant.
+    BackendSpecific { err: BackendSpecificError },
+}
+
+impl Display for StreamBufferSizeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BackendSpecific { err } => err.fmt(f),
+            StreamBufferSizeError::NotSupported => {
+                f.write_str("The host does not report the buffer size of streams.")
+            }
+        }
+    }
+}
+
+impl Error for StreamBufferSizeError {}
+
+impl From<BackendSpecificError> for StreamBufferSizeError {
+    fn from(err: BackendSpecificError) -> Self {
+        Self::BackendSpecific { err }
+    }
+}
+
+/// Errors that might occur when calling [`position`](crate::traits::StreamTrait::position).
+#[derive(Clone, Debug)]
+pub enum StreamPositionError {
+    /// The host does not report the position of streams.
+    NotSupported,
+    /// See the [`BackendSpecificError`] docs for more information about this error variant.
+    BackendSpecific { err: BackendSpecificError },
+}
+
+impl Display for StreamPositionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BackendSpecific { err } => err.fmt(f),
+            StreamPositionError::NotSupported => {
+                f.write_str("The host does not report the position of streams.")
+            }
+        }
+    }
+}
+
+impl Error for StreamPositionError {}
+
+impl From<BackendSpecificError> for StreamPositionError {
+    fn from(err: BackendSpecificError) -> Self {
+        Self::BackendSpecific { err }
+    }
+}
+
+/// Error that might occur when querying the statistics of a stream.
+#[derive(Clone, Debug)]
+pub enum StreamStatsError {
+    /// The host does not collect statistics of streams.
+    NotSupported,
+    /// The collection of statistics was not enabled via
+    /// [`StreamTrait::enable_stats`](crate::traits::StreamTrait::enable_stats).
+    NotEnabled,
+    /// See the [`BackendSpecificError`] docs for more information about this error variant.
+    BackendSpecific { err: BackendSpecificError },
+}
+
+impl Display for StreamStatsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BackendSpecific { err } => err.fmt(f),
+            StreamStatsError::NotSupported => {
+                f.write_str("The host does not collect statistics of streams.")
+            }
+            StreamStatsError::NotEnabled => {
+                f.write_str("The collection of statistics was not enabled for the stream.")
+            }
+        }
+    }
+}
+
+impl Error for StreamStatsError {}
+
+impl From<BackendSpecificError> for StreamStatsError {
+    fn from(err: BackendSpecificError) -> Self {
+        Self::BackendSpecific { err }
+    }
+}
+
 /// Errors that might occur while a stream is running.
 #[derive(Clone, Debug)]
 pub enum StreamError {
     /// The device no longer exists. This can happen if the device is disconnected while the
     /// program is running.
     DeviceNotAvailable,
+    /// The output stream ran out of data to play, for example because the data callback took too
+    /// long. The stream recovers on its own.
+    BufferUnderrun {
+        /// The number of frames that could not be played, or `None` if the host cannot tell.
+        frames_lost: Option<usize>,
+    },
+    /// The input stream captured data faster than it was delivered to the data callback. The
+    /// stream recovers on its own.
+    BufferOverrun {
+        /// The number of captured frames that were dropped, or `None` if the host cannot tell.
+        frames_lost: Option<usize>,
+    },
     /// See the [`BackendSpecificError`] docs for more information about this error variant.
     BackendSpecific { err: BackendSpecificError },
 }
@@ -279,6 +494,26 @@ impl Display for StreamError {
             StreamError::DeviceNotAvailable => f.write_str(
                 "The requested device is no longer available. For example, it has been unplugged.",
             ),
+            StreamError::BufferUnderrun {
+                frames_lost: Some(frames_lost),
+            } => write!(
+                f,
+                "The output buffer ran empty, {} frames were lost.",
+                frames_lost
+            ),
+            StreamError::BufferUnderrun { frames_lost: None } => {
+                f.write_str("The output buffer ran empty.")
+            }
+            StreamError::BufferOverrun {
+                frames_lost: Some(frames_lost),
+            } => write!(
+                f,
+                "The input buffer overflowed, {} frames were lost.",
+                frames_lost
+            ),
+            StreamError::BufferOverrun { frames_lost: None } => {
+                f.write_str("The input buffer overflowed.")
+            }
         }
     }
 }
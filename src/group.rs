@@ -0,0 +1,162 @@
+//! Starting and pausing several streams together.
+
+use crate::traits::StreamTrait;
+use crate::{PauseStreamError, PlayStreamError};
+
+/// A group of streams of the same host that are played and paused together, e.g. a capture and a
+/// playback stream whose frames should stay aligned.
+///
+/// Where the host supports it, all streams of the group start at the same moment. On ALSA, this
+/// links the PCMs of the streams, which works for streams of the same card opened without a sound
+/// server or software mixer in between. As ALSA streams run from when they are built, they are
+/// restarted together, dropping the frames buffered so far. Elsewhere, the streams are played one
+/// after the other.
+///
+/// ```no_run
+/// use cpal::traits::{DeviceTrait, HostTrait};
+/// # let host = cpal::default_host();
+/// # let device = host.default_output_device().unwrap();
+/// # let config: cpal::StreamConfig = device.default_output_config().unwrap().into();
+/// let input = device.build_input_stream(&config, |_: &[f32], _| {}, |_| {}, None).unwrap();
+/// let output = device.build_output_stream(&config, |_: &mut [f32], _| {}, |_| {}, None).unwrap();
+/// let group = cpal::StreamGroup::new(vec![input, output]);
+/// if !group.play().unwrap() {
+///     eprintln!("the streams did not start at the same moment");
+/// }
+/// ```
+pub struct StreamGroup<S> {
+    streams: Vec<S>,
+}
+
+impl<S: StreamTrait> StreamGroup<S> {
+    /// Group the given streams.
+    pub fn new(streams: Vec<S>) -> Self {
+        StreamGroup { streams }
+    }
+
+    /// The streams of the group.
+    pub fn streams(&self) -> &[S] {
+        &self.streams
+    }
+
+    /// Ungroup the streams.
+    pub fn into_streams(self) -> Vec<S> {
+        self.streams
+    }
+
+    /// Run all streams of the group.
+    ///
+    /// Returns whether the streams were started, or resumed, at the same moment. If not, they
+    /// still run, but may be offset from each other by the time it took to play them one after
+    /// the other.
+    pub fn play(&self) -> Result<bool, PlayStreamError> {
+        S::play_all(&self.refs())
+    }
+
+    /// Pause all streams of the group.
+    ///
+    /// Returns whether the streams were paused at the same moment, so that they stay aligned
+    /// when played again.
+    pub fn pause(&self) -> Result<bool, PauseStreamError> {
+        S::pause_all(&self.refs())
+    }
+
+    fn refs(&self) -> Vec<&S> {
+        self.streams.iter().collect()
+    }
+}
+
+/// Play the given streams one after the other, for hosts that cannot start them together.
+pub(crate) fn play_each<S: StreamTrait>(streams: &[&S]) -> Result<bool, PlayStreamError> {
+    for stream in streams {
+        stream.play()?;
+    }
+    Ok(streams.len() <= 1)
+}
+
+/// Pause the given streams one after the other, for hosts that cannot pause them together.
+pub(crate) fn pause_each<S: StreamTrait>(streams: &[&S]) -> Result<bool, PauseStreamError> {
+    for stream in streams {
+        stream.pause()?;
+    }
+    Ok(streams.len() <= 1)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn test_play_and_pause_together() {
+        use super::StreamGroup;
+        use crate::host::dummy::{Device, ManualClock};
+        use crate::traits::{DeviceTrait, StreamTrait};
+        use crate::{
+            BufferSize, SampleFormat, SampleLayout, SampleRate, StreamConfig, SupportedBufferSize,
+            SupportedStreamConfigRange,
+        };
+        use std::sync::{Arc, Mutex};
+
+        let range = SupportedStreamConfigRange::new(
+            1,
+            SampleRate(1000),
+            SampleRate(1000),
+            SupportedBufferSize::Unknown,
+            SampleFormat::F32,
+        );
+        let clock = ManualClock::new();
+        let device = Device::new("test")
+            .with_input_configs(vec![range])
+            .with_output_configs(vec![range])
+            .with_clock(clock.clone());
+        let config = StreamConfig {
+            channels: 1,
+            sample_rate: SampleRate(1000),
+            buffer_size: BufferSize::Fixed(4),
+            layout: SampleLayout::Interleaved,
+        };
+
+        let input_positions = Arc::new(Mutex::new(Vec::new()));
+        let output_positions = Arc::new(Mutex::new(Vec::new()));
+        let input = {
+            let positions = input_positions.clone();
+            device
+                .build_input_stream(
+                    &config,
+                    move |_: &[f32], info| positions.lock().unwrap().push(info.position().frames),
+                    |err| panic!("{err}"),
+                    None,
+                )
+                .unwrap()
+        };
+        let output = {
+            let positions = output_positions.clone();
+            device
+                .build_output_stream(
+                    &config,
+                    move |_: &mut [f32], info| {
+                        positions.lock().unwrap().push(info.position().frames)
+                    },
+                    |err| panic!("{err}"),
+                    None,
+                )
+                .unwrap()
+        };
+        let group = StreamGroup::new(vec![input, output]);
+
+        clock.advance(4);
+        group.play().unwrap();
+        clock.advance(8);
+        group.pause().unwrap();
+        clock.advance(4);
+        group.play().unwrap();
+        clock.advance(4);
+
+        // Both streams process the same frames of the clock.
+        assert_eq!(*input_positions.lock().unwrap(), [0, 4, 8]);
+        assert_eq!(*output_positions.lock().unwrap(), [0, 4, 8]);
+        for stream in group.streams() {
+            assert_eq!(stream.position().unwrap(), 12);
+        }
+        assert_eq!(group.into_streams().len(), 2);
+    }
+}
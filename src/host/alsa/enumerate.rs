@@ -1,109 +1,283 @@
 use super::alsa;
-use super::{Device, DeviceHandles};
-use crate::{BackendSpecificError, DevicesError};
-use std::sync::{Arc, Mutex};
+use super::{Device, DeviceOptions};
+use crate::{BackendSpecificError, DeviceInterface, DevicesError};
+use std::fs;
+use std::path::Path;
 
 /// ALSA's implementation for `Devices`.
 pub struct Devices {
-    builtin_pos: usize,
-    card_iter: alsa::card::Iter,
+    // The names and ids of the PCMs that are yet to be returned. They are not opened during
+    // enumeration, which would fail for busy PCMs and hold the hardware for no reason.
+    pcms: std::vec::IntoIter<(String, String)>,
+    options: DeviceOptions,
 }
 
 impl Devices {
-    pub fn new() -> Result<Self, DevicesError> {
+    pub(super) fn new(options: DeviceOptions) -> Result<Self, DevicesError> {
+        let mut pcms: Vec<(String, String)> = Vec::new();
+        let mut push = |name: String, pcm_id: String| {
+            if !pcms.iter().any(|(_, id)| *id == pcm_id) {
+                pcms.push((name, pcm_id));
+            }
+        };
+
+        push("default".to_owned(), "default".to_owned());
+
+        // The PCMs defined by the ALSA configuration, including user-defined ones, sound servers
+        // and plugins such as dmix and dsnoop. The hardware PCMs of the cards are added below.
+        if let Ok(hints) = alsa::device_name::HintIter::new_str(None, "pcm") {
+            for hint in hints {
+                let Some(pcm_id) = hint.name else { continue };
+                if pcm_id.starts_with("hw:") || pcm_id.starts_with("plughw:") {
+                    continue;
+                }
+                push(pcm_id.clone(), pcm_id);
+            }
+        }
+
+        for card in alsa::card::Iter::new().filter_map(Result::ok) {
+            for (name, pcm_id) in card_pcms(&card, options.plug) {
+                push(name, pcm_id);
+            }
+        }
+
         Ok(Devices {
-            builtin_pos: 0,
-            card_iter: alsa::card::Iter::new(),
+            pcms: pcms.into_iter(),
+            options,
         })
     }
 }
 
-unsafe impl Send for Devices {}
-unsafe impl Sync for Devices {}
+// The names and ids of the PCM devices of a card, and of their subdevices if there are several.
+//
+// If `plug` is set, the PCMs are opened through the plug layer.
+pub(super) fn card_pcms(card: &alsa::Card, plug: bool) -> Vec<(String, String)> {
+    let mut pcms = Vec::new();
+    let Ok(ctl) = alsa::Ctl::from_card(card, false) else {
+        return pcms;
+    };
+    let Ok(cardinfo) = ctl.card_info() else {
+        return pcms;
+    };
+    // Card indices depend on the order in which cards were detected, whereas the card id is
+    // unique and stays the same across reboots, so it is used to address the PCMs.
+    let (Ok(card_name), Ok(card_id)) = (cardinfo.get_name(), cardinfo.get_id()) else {
+        return pcms;
+    };
 
-const BUILTINS: [&'static str; 5] = ["default", "pipewire", "pulse", "jack", "oss"];
+    let prefix = if plug { "plughw" } else { "hw" };
 
-impl Iterator for Devices {
-    type Item = Device;
+    for device in alsa::ctl::DeviceIter::new(&ctl) {
+        let device = device as u32;
+        // A PCM device may only support one direction.
+        let Ok(info) = ctl
+            .pcm_info(device, 0, alsa::Direction::Playback)
+            .or_else(|_| ctl.pcm_info(device, 0, alsa::Direction::Capture))
+        else {
+            continue;
+        };
+        let pcm_name = info.get_name().unwrap_or_default();
+        let name = format!("{}, {}", card_name, pcm_name);
+        let pcm_id = format!("{}:CARD={},DEV={}", prefix, card_id, device);
+        pcms.push((name.clone(), pcm_id.clone()));
 
-    fn next(&mut self) -> Option<Device> {
-        while self.builtin_pos < BUILTINS.len() {
-            let pos = self.builtin_pos;
-            self.builtin_pos += 1;
-            let name = BUILTINS[pos];
-
-            if let Ok(handles) = DeviceHandles::open(&name) {
-                return Some(Device {
-                    name: name.to_string(),
-                    pcm_id: name.to_string(),
-                    handles: Arc::new(Mutex::new(handles)),
-                });
+        // ALSA picks a free subdevice when opening the device, but some uses require a
+        // specific one.
+        let subdevices = info.get_subdevices_count();
+        if subdevices > 1 {
+            for subdevice in 0..subdevices {
+                pcms.push((
+                    format!("{}, subdevice {}", name, subdevice),
+                    format!("{},SUBDEV={}", pcm_id, subdevice),
+                ));
             }
         }
+    }
+    pcms
+}
 
-        loop {
-            let Some(res) = self.card_iter.next() else {
-                return None;
-            };
-            let Ok(card) = res else { continue };
-
-            let ctl_id = format!("hw:{}", card.get_index());
-            let Ok(ctl) = alsa::Ctl::new(&ctl_id, false) else {
-                continue;
-            };
-            let Ok(cardinfo) = ctl.card_info() else {
-                continue;
-            };
-            let Ok(card_name) = cardinfo.get_name() else {
-                continue;
-            };
-
-            // Using plughw adds the ALSA plug layer, which can do sample type conversion,
-            // sample rate convertion, ...
-            // It is convenient, but at the same time not suitable for pro-audio as it hides
-            // the actual device capabilities and perform audio manipulation under your feet,
-            // for example sample rate conversion, sample format conversion, adds dummy channels,
-            // ...
-            // For now, many hardware only support 24bit / 3 bytes, which isn't yet supported by
-            // cpal. So we have to enable plughw (unfortunately) for maximum compatibility.
-            const USE_PLUGHW: bool = true;
-            let pcm_id = if USE_PLUGHW {
-                format!("plughw:{}", card.get_index())
-            } else {
-                ctl_id
-            };
-            if let Ok(handles) = DeviceHandles::open(&pcm_id) {
-                return Some(Device {
-                    name: card_name.to_string(),
-                    pcm_id: pcm_id.to_string(),
-                    handles: Arc::new(Mutex::new(handles)),
-                });
-            }
-        }
+impl Iterator for Devices {
+    type Item = Device;
+
+    fn next(&mut self) -> Option<Device> {
+        let (name, pcm_id) = self.pcms.next()?;
+        Some(Device {
+            name,
+            pcm_id,
+            options: self.options.clone(),
+        })
     }
 }
 
 #[inline]
-pub fn default_input_device() -> Option<Device> {
+pub(super) fn default_input_device(options: DeviceOptions) -> Option<Device> {
     Some(Device {
         name: "default".to_owned(),
         pcm_id: "default".to_owned(),
-        handles: Arc::new(Mutex::new(Default::default())),
+        options,
     })
 }
 
 #[inline]
-pub fn default_output_device() -> Option<Device> {
+pub(super) fn default_output_device(options: DeviceOptions) -> Option<Device> {
     Some(Device {
         name: "default".to_owned(),
         pcm_id: "default".to_owned(),
-        handles: Arc::new(Mutex::new(Default::default())),
+        options,
     })
 }
 
+// The description of a PCM from the ALSA configuration, e.g. "Default ALSA Output (currently
+// PulseAudio Sound Server)".
+pub(super) fn hint_description(pcm_id: &str) -> Option<String> {
+    alsa::device_name::HintIter::new_str(None, "pcm")
+        .ok()?
+        .find(|hint| hint.name.as_deref() == Some(pcm_id))?
+        .desc
+        .map(|desc| join_description_lines(&desc))
+}
+
+// Hint descriptions span several lines, the second one usually being the actual device.
+fn join_description_lines(desc: &str) -> String {
+    desc.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Whether a PCM defined by the ALSA configuration supports the given direction, or `None` if it
+// is not defined there.
+pub(super) fn hint_supports(pcm_id: &str, direction: alsa::Direction) -> Option<bool> {
+    let hint = alsa::device_name::HintIter::new_str(None, "pcm")
+        .ok()?
+        .find(|hint| hint.name.as_deref() == Some(pcm_id))?;
+    // Hints without a direction support both.
+    Some(
+        hint.direction
+            .map_or(true, |hint_direction| hint_direction == direction),
+    )
+}
+
+// Whether a PCM device of a card supports the given direction, as reported by the control
+// interface of the card, or `None` if the PCM is not a card PCM as built by `card_pcms`.
+pub(super) fn card_pcm_supports(pcm_id: &str, direction: alsa::Direction) -> Option<bool> {
+    let (card, device, subdevice) = parse_card_pcm_id(pcm_id)?;
+    let ctl = alsa::Ctl::new(&format!("hw:CARD={}", card), false).ok()?;
+    Some(
+        ctl.pcm_info(device, subdevice.unwrap_or(0), direction)
+            .is_ok(),
+    )
+}
+
+// The card id, device and subdevice of a PCM id as built by `card_pcms`, e.g.
+// `plughw:CARD=PCH,DEV=0`.
+fn parse_card_pcm_id(pcm_id: &str) -> Option<(&str, u32, Option<u32>)> {
+    let args = pcm_id
+        .strip_prefix("hw:")
+        .or_else(|| pcm_id.strip_prefix("plughw:"))?;
+    let mut args = args.split(',');
+    let card = args.next()?.strip_prefix("CARD=")?;
+    let device = args.next()?.strip_prefix("DEV=")?.parse().ok()?;
+    let subdevice = match args.next() {
+        Some(arg) => Some(arg.strip_prefix("SUBDEV=")?.parse().ok()?),
+        None => None,
+    };
+    if args.next().is_some() {
+        return None;
+    }
+    Some((card, device, subdevice))
+}
+
+// The bus a card is connected to, as reported by sysfs.
+pub(super) fn card_interface(card: u32) -> DeviceInterface {
+    sysfs_card_interface(Path::new("/sys"), card)
+}
+
+fn sysfs_card_interface(sysfs: &Path, card: u32) -> DeviceInterface {
+    let subsystem = sysfs.join(format!("class/sound/card{}/device/subsystem", card));
+    match fs::read_link(subsystem)
+        .ok()
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .and_then(|name| name.to_str())
+    {
+        Some("pci") => DeviceInterface::Pci,
+        Some("usb") => DeviceInterface::Usb,
+        _ => DeviceInterface::Unknown,
+    }
+}
+
 impl From<alsa::Error> for DevicesError {
     fn from(err: alsa::Error) -> Self {
         let err: BackendSpecificError = err.into();
         err.into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{join_description_lines, parse_card_pcm_id, sysfs_card_interface};
+    use crate::DeviceInterface;
+
+    #[test]
+    fn test_join_description_lines() {
+        assert_eq!(
+            join_description_lines("Default ALSA Output\n(currently PulseAudio Sound Server)"),
+            "Default ALSA Output, (currently PulseAudio Sound Server)"
+        );
+        assert_eq!(
+            join_description_lines("HDA Intel PCH, ALC892 Analog\nFront output / input\n"),
+            "HDA Intel PCH, ALC892 Analog, Front output / input"
+        );
+        assert_eq!(
+            join_description_lines("Discard all samples"),
+            "Discard all samples"
+        );
+    }
+
+    #[test]
+    fn test_parse_card_pcm_id() {
+        assert_eq!(
+            parse_card_pcm_id("plughw:CARD=PCH,DEV=3"),
+            Some(("PCH", 3, None))
+        );
+        assert_eq!(
+            parse_card_pcm_id("hw:CARD=U192k,DEV=0,SUBDEV=2"),
+            Some(("U192k", 0, Some(2)))
+        );
+        for pcm_id in [
+            "default",
+            "hw:CARD=PCH",
+            "hw:0,0",
+            "dmix:CARD=PCH,DEV=0",
+            "hw:CARD=PCH,DEV=x",
+            "hw:CARD=PCH,DEV=0,SUBDEV=1,FOO=2",
+        ] {
+            assert_eq!(parse_card_pcm_id(pcm_id), None, "{}", pcm_id);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sysfs_card_interface() {
+        use std::fs;
+        use std::os::unix::fs::symlink;
+
+        let sysfs = std::env::temp_dir().join(format!("cpal-sysfs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&sysfs);
+        for (card, bus) in [(0, "pci"), (1, "usb"), (2, "platform")] {
+            let device = sysfs.join(format!("class/sound/card{}/device", card));
+            fs::create_dir_all(&device).unwrap();
+            fs::create_dir_all(sysfs.join("bus").join(bus)).unwrap();
+            symlink(format!("../../../../bus/{}", bus), device.join("subsystem")).unwrap();
+        }
+
+        assert_eq!(sysfs_card_interface(&sysfs, 0), DeviceInterface::Pci);
+        assert_eq!(sysfs_card_interface(&sysfs, 1), DeviceInterface::Usb);
+        assert_eq!(sysfs_card_interface(&sysfs, 2), DeviceInterface::Unknown);
+        // Cards without a device, e.g. virtual ones such as snd-aloop, or missing cards.
+        assert_eq!(sysfs_card_interface(&sysfs, 3), DeviceInterface::Unknown);
+        fs::remove_dir_all(&sysfs).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+//! Device change notifications, based on watching the ALSA device nodes with inotify.
+
+use super::alsa;
+use super::enumerate::card_pcms;
+use super::{trigger, TriggerReceiver};
+use crate::platform::HostId;
+use crate::{BackendSpecificError, DeviceEvent, DeviceId, DeviceWatcher, WatchDevicesError};
+use std::ffi::CString;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// The directory in which the kernel creates and removes the ALSA device nodes.
+const DEV_SND: &str = "/dev/snd";
+
+// The device nodes of a card appear one after another and the card may not be usable until all
+// of them exist, so wait for the directory to settle before rescanning.
+const SETTLE_TIMEOUT: Duration = Duration::from_millis(200);
+
+struct Inotify(libc::c_int);
+
+impl Inotify {
+    fn watch(path: &str) -> Result<Self, BackendSpecificError> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(last_os_error());
+        }
+        let inotify = Inotify(fd);
+        let path = CString::new(path).unwrap();
+        let mask = libc::IN_CREATE | libc::IN_DELETE;
+        if unsafe { libc::inotify_add_watch(fd, path.as_ptr(), mask) } < 0 {
+            return Err(last_os_error());
+        }
+        Ok(inotify)
+    }
+
+    // Discard all pending events. We only care that something changed, not what.
+    fn drain(&self) {
+        let mut buf = [0u8; 4096];
+        while unsafe { libc::read(self.0, buf.as_mut_ptr() as *mut _, buf.len()) } > 0 {}
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+fn last_os_error() -> BackendSpecificError {
+    BackendSpecificError {
+        description: io::Error::last_os_error().to_string(),
+    }
+}
+
+// The id of every card currently known to ALSA, together with the ids of its PCM devices.
+fn cards(plug: bool) -> Vec<(String, Vec<DeviceId>)> {
+    alsa::card::Iter::new()
+        .filter_map(Result::ok)
+        .filter_map(|card| {
+            let ctl = alsa::Ctl::from_card(&card, false).ok()?;
+            let card_id = ctl.card_info().ok()?.get_id().ok()?.to_owned();
+            let pcms = card_pcms(&card, plug)
+                .into_iter()
+                .map(|(_, pcm_id)| DeviceId::new(HostId::Alsa, &pcm_id))
+                .collect();
+            Some((card_id, pcms))
+        })
+        .collect()
+}
+
+// The id of the card the `default` control device belongs to, if any. This is the card the
+// `default` PCM plays on, unless the ALSA configuration routes it elsewhere, e.g. to a sound
+// server.
+fn default_card() -> Option<String> {
+    let ctl = alsa::Ctl::new("default", false).ok()?;
+    let card_id = ctl.card_info().ok()?.get_id().ok()?.to_owned();
+    Some(card_id)
+}
+
+pub fn watch_devices<F>(plug: bool, mut callback: F) -> Result<DeviceWatcher, WatchDevicesError>
+where
+    F: FnMut(DeviceEvent) + Send + 'static,
+{
+    let inotify = Inotify::watch(DEV_SND)?;
+    let (tx, rx) = trigger();
+    let stopped = Arc::new(AtomicBool::new(false));
+    let worker_stopped = stopped.clone();
+    let thread = thread::Builder::new()
+        .name("cpal_alsa_watch".to_owned())
+        .spawn(move || watch_worker(inotify, rx, plug, &worker_stopped, &mut callback))
+        .unwrap();
+    Ok(DeviceWatcher::new(move || {
+        stopped.store(true, Ordering::Relaxed);
+        tx.wakeup();
+        // The watcher may be dropped by the callback, in which case the worker exits once the
+        // callback returns. A panic of the callback has already been reported on the worker.
+        if thread.thread().id() != thread::current().id() {
+            let _ = thread.join();
+        }
+    }))
+}
+
+fn watch_worker(
+    inotify: Inotify,
+    rx: TriggerReceiver,
+    plug: bool,
+    stopped: &AtomicBool,
+    callback: &mut dyn FnMut(DeviceEvent),
+) {
+    let mut known = cards(plug);
+    let mut known_default = default_card();
+    let mut settling = false;
+    loop {
+        let mut fds = [
+            libc::pollfd {
+                fd: rx.0,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: inotify.0,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        let timeout = if settling {
+            SETTLE_TIMEOUT.as_millis() as libc::c_int
+        } else {
+            -1
+        };
+        let res = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if res < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+
+        // The watcher has been dropped.
+        if fds[0].revents != 0 {
+            rx.clear_pipe();
+            return;
+        }
+
+        if fds[1].revents != 0 {
+            inotify.drain();
+            settling = true;
+            continue;
+        }
+
+        if res == 0 && settling {
+            settling = false;
+            let current = cards(plug);
+            let removed = known
+                .iter()
+                .filter(|(card_id, _)| !current.iter().any(|(id, _)| id == card_id))
+                .flat_map(|(_, pcms)| pcms.iter().cloned().map(DeviceEvent::Removed));
+            let added = current
+                .iter()
+                .filter(|(card_id, _)| !known.iter().any(|(id, _)| id == card_id))
+                .flat_map(|(_, pcms)| pcms.iter().cloned().map(DeviceEvent::Added));
+            let current_default = default_card();
+            let default_changed =
+                (current_default != known_default).then_some(DeviceEvent::DefaultChanged);
+            for event in removed.chain(added).chain(default_changed) {
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                callback(event);
+            }
+            known = current;
+            known_default = current_default;
+        }
+    }
+}
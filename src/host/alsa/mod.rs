@@ -2,35 +2,158 @@ extern crate alsa;
 extern crate libc;
 
 use self::alsa::poll::Descriptors;
+use crate::platform::HostId;
+use crate::position::PositionCounter;
+use crate::stats::StatsCollector;
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
-    DefaultStreamConfigError, DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo,
-    PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    frames_to_duration, BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
+    DefaultStreamConfigError, DeviceDescription, DeviceEvent, DeviceId, DeviceIdError,
+    DeviceInterface, DeviceNameError, DeviceWatcher, DevicesError, DuplexCallbackInfo,
+    DuplexStream, DuplexStreamConfig, FrameCount, InputCallbackInfo, OutputCallbackInfo,
+    PauseStreamError, PlayStreamError, SampleFormat, SampleLayout, SampleRate, StreamBufferSize,
+    StreamBufferSizeError, StreamConfig, StreamError, StreamLatencyError, StreamPositionError,
+    StreamStats, StreamStatsError, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError, WatchDevicesError,
 };
 use std::cmp;
 use std::convert::TryInto;
-use std::sync::{Arc, Mutex};
-use std::thread::{self, JoinHandle};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
 use std::time::Duration;
 use std::vec::IntoIter as VecIntoIter;
 
-pub use self::enumerate::{default_input_device, default_output_device, Devices};
+pub use self::enumerate::Devices;
+use self::enumerate::{default_input_device, default_output_device};
+use self::realtime::ThreadOptions;
+pub use self::realtime::ThreadScheduling;
 
 pub type SupportedInputConfigs = VecIntoIter<SupportedStreamConfigRange>;
 pub type SupportedOutputConfigs = VecIntoIter<SupportedStreamConfigRange>;
 
 mod enumerate;
+mod hotplug;
+mod realtime;
 
 /// The default linux, dragonfly, freebsd and netbsd host type.
 #[derive(Debug)]
-pub struct Host;
+pub struct Host {
+    /// The options passed on to the devices of this host.
+    options: DeviceOptions,
+}
 
 impl Host {
     pub fn new() -> Result<Self, crate::HostUnavailable> {
-        Ok(Host)
+        Ok(Host {
+            options: DeviceOptions::default(),
+        })
+    }
+
+    /// Set whether streams should access the ALSA ring buffer directly via mmap, so that the data
+    /// callback reads from or writes to it without an intermediate copy (default is false).
+    ///
+    /// Devices that do not support mmap access fall back to regular reads and writes, as do
+    /// duplex streams and streams of 24-bit samples.
+    pub fn set_mmap(&mut self, mmap: bool) {
+        self.options.mmap = mmap;
+    }
+
+    /// Set whether the PCM devices of sound cards are opened through the ALSA plug layer
+    /// (`plughw`) rather than directly (`hw`) (default is true).
+    ///
+    /// The plug layer converts sample formats and rates and adds or drops channels as needed,
+    /// which is convenient since many devices only support a few sample rates or a fixed number
+    /// of channels. It hides the actual capabilities of the hardware though, and processes the
+    /// audio behind the application's back, which is not suitable for pro-audio use. Without it,
+    /// the supported configs of a device are the limits of the hardware.
+    ///
+    /// Only affects devices enumerated afterwards.
+    pub fn set_plug(&mut self, plug: bool) {
+        self.options.plug = plug;
+    }
+
+    /// Set the number of periods the ring buffer of streams is divided into (default is 4).
+    ///
+    /// The device wakes up the stream once per period, so the buffer holds this many callbacks
+    /// worth of audio. Fewer periods lower the latency, at the risk of xruns if a callback is
+    /// late; 2 or 3 periods are common for low latency setups.
+    ///
+    /// With `BufferSize::Fixed`, the requested buffer size takes precedence and is divided into
+    /// this many periods, unless a period size is set. The buffer size and period size reported
+    /// by [`StreamTrait::buffer_size`] tell what ALSA chose. Streams fail to build with
+    /// [`BuildStreamError::StreamConfigNotSupported`] if the device does not support this many
+    /// periods, e.g. for 0.
+    pub fn set_periods(&mut self, periods: u32) {
+        self.options.periods = periods;
+    }
+
+    /// Set the size of the periods of streams in frames, i.e. the number of frames processed per
+    /// wakeup of the stream. With `None` (default), it is derived from the buffer size of the
+    /// stream config, or is 25 ms for `BufferSize::Default`.
+    ///
+    /// ALSA picks the nearest size supported by the device. Use [`StreamTrait::buffer_size`] to
+    /// check what it chose.
+    pub fn set_period_size(&mut self, frames: Option<FrameCount>) {
+        self.options.period_size = frames;
+    }
+
+    /// Set the scheduling policy of the threads running the data callbacks of streams
+    /// (default is `ThreadScheduling::Normal`).
+    ///
+    /// Real-time scheduling usually requires privileges, e.g. `CAP_SYS_NICE` or an `rtprio`
+    /// limit. If the policy cannot be applied, the stream keeps running at normal priority and
+    /// the failure is reported to its error callback.
+    ///
+    /// Returns an error and keeps the previous policy if the priority is outside of the range
+    /// supported by the policy.
+    pub fn set_thread_scheduling(
+        &mut self,
+        scheduling: ThreadScheduling,
+    ) -> Result<(), BackendSpecificError> {
+        realtime::check_scheduling(scheduling)?;
+        self.options.thread.scheduling = scheduling;
+        Ok(())
+    }
+
+    /// Restrict the threads running the data callbacks of streams to the given CPUs. An empty
+    /// slice allows all CPUs (default).
+    ///
+    /// Only supported on Linux. Failures are reported to the error callback of the stream.
+    ///
+    /// Returns an error and keeps the previous CPUs if a CPU index is too large to be part of a
+    /// CPU set, or if CPUs are given on other platforms.
+    pub fn set_thread_affinity(&mut self, cpus: &[usize]) -> Result<(), BackendSpecificError> {
+        realtime::check_affinity(cpus)?;
+        self.options.thread.affinity = cpus.to_vec();
+        Ok(())
+    }
+
+    /// Set the prefix of the names of the threads running the data callbacks of streams, which
+    /// are suffixed with `_in`, `_out` or `_duplex` (default is "cpal_alsa").
+    pub fn set_thread_name(&mut self, name: &str) {
+        self.options.thread.name = name.to_owned();
+    }
+}
+
+// Settings of a `Host` that apply to its devices and their streams.
+#[derive(Clone, Debug)]
+struct DeviceOptions {
+    mmap: bool,
+    plug: bool,
+    periods: u32,
+    period_size: Option<FrameCount>,
+    thread: ThreadOptions,
+}
+
+impl Default for DeviceOptions {
+    fn default() -> Self {
+        DeviceOptions {
+            mmap: false,
+            plug: true,
+            periods: 4,
+            period_size: None,
+            thread: ThreadOptions::default(),
+        }
     }
 }
 
@@ -44,15 +167,46 @@ impl HostTrait for Host {
     }
 
     fn devices(&self) -> Result<Self::Devices, DevicesError> {
-        Devices::new()
+        Devices::new(self.options.clone())
     }
 
     fn default_input_device(&self) -> Option<Self::Device> {
-        default_input_device()
+        default_input_device(self.options.clone())
     }
 
     fn default_output_device(&self) -> Option<Self::Device> {
-        default_output_device()
+        default_output_device(self.options.clone())
+    }
+
+    /// Besides the enumerated devices, any PCM that can be opened can be looked up by its name,
+    /// e.g. `ALSA:hw:CARD=PCH,DEV=0`.
+    fn device_by_id(&self, id: &DeviceId) -> Option<Self::Device> {
+        if id.host() != HostId::Alsa {
+            return None;
+        }
+        let pcm_id = upgrade_pcm_id(id.id());
+        if let Some(device) = self.devices().ok()?.find(|device| device.pcm_id == pcm_id) {
+            return Some(device);
+        }
+        if !pcm_exists(&pcm_id) {
+            return None;
+        }
+        Some(Device {
+            name: pcm_id.clone(),
+            pcm_id,
+            options: self.options.clone(),
+        })
+    }
+
+    /// Sound cards being added or removed are detected by watching `/dev/snd`, and reported as
+    /// the PCM devices of the card being added or removed. PCMs defined by the ALSA
+    /// configuration, e.g. `default`, are not reported, but `DefaultChanged` is reported when
+    /// the card of the `default` control device changes.
+    fn watch_devices<F>(&self, callback: F) -> Result<DeviceWatcher, WatchDevicesError>
+    where
+        F: FnMut(DeviceEvent) + Send + 'static,
+    {
+        hotplug::watch_devices(self.options.plug, callback)
     }
 }
 
@@ -65,6 +219,25 @@ impl DeviceTrait for Device {
         Device::name(self)
     }
 
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Ok(DeviceId::new(HostId::Alsa, &self.pcm_id))
+    }
+
+    fn description(&self) -> Result<DeviceDescription, DeviceNameError> {
+        Device::description(self)
+    }
+
+    /// Determined from the card or the ALSA configuration without opening the PCM where
+    /// possible. Busy PCMs support the direction they are busy in.
+    fn supports_input(&self) -> bool {
+        Device::supports_direction(self, alsa::Direction::Capture)
+    }
+
+    /// Determined like `supports_input`.
+    fn supports_output(&self) -> bool {
+        Device::supports_direction(self, alsa::Direction::Playback)
+    }
+
     fn supported_input_configs(
         &self,
     ) -> Result<Self::SupportedInputConfigs, SupportedStreamConfigsError> {
@@ -97,10 +270,29 @@ impl DeviceTrait for Device {
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
-        let stream_inner =
-            self.build_stream_inner(conf, sample_format, alsa::Direction::Capture)?;
+        // The `alsa` crate neither wraps `snd_pcm_readn`/`snd_pcm_writen` nor exposes the PCM
+        // handle to call them, so `Access::RWNonInterleaved` cannot be used and non-interleaved
+        // layouts are converted from the interleaved PCM data instead.
+        if conf.layout != SampleLayout::Interleaved {
+            return crate::planar::build_input_stream_planar_fallback(
+                self,
+                conf,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
+        let stream_inner = self.build_stream_inner(
+            conf,
+            sample_format,
+            alsa::Direction::Capture,
+            self.options.mmap,
+            true,
+        )?;
         let stream = Stream::new_input(
             Arc::new(stream_inner),
+            &self.options.thread,
             data_callback,
             error_callback,
             timeout,
@@ -120,35 +312,107 @@ impl DeviceTrait for Device {
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
-        let stream_inner =
-            self.build_stream_inner(conf, sample_format, alsa::Direction::Playback)?;
+        if conf.layout != SampleLayout::Interleaved {
+            return crate::planar::build_output_stream_planar_fallback(
+                self,
+                conf,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
+        let stream_inner = self.build_stream_inner(
+            conf,
+            sample_format,
+            alsa::Direction::Playback,
+            self.options.mmap,
+            true,
+        )?;
         let stream = Stream::new_output(
             Arc::new(stream_inner),
+            &self.options.thread,
             data_callback,
             error_callback,
             timeout,
         );
         Ok(stream)
     }
+
+    fn build_duplex_stream_raw<D, E>(
+        &self,
+        conf: &DuplexStreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<DuplexStream<Self::Stream>, BuildStreamError>
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        // The duplex worker always reads and writes intermediate buffers, as the ring buffers of
+        // the two devices wrap around at different points.
+        let input_inner = self.build_stream_inner(
+            &conf.input_config(),
+            sample_format,
+            alsa::Direction::Capture,
+            false,
+            false,
+        )?;
+        let output_inner = self.build_stream_inner(
+            &conf.output_config(),
+            sample_format,
+            alsa::Direction::Playback,
+            false,
+            false,
+        )?;
+        // Link the two PCMs, so that ALSA starts, stops and pauses them at the same moment and
+        // they stay sample-synchronous. Devices that cannot be linked, e.g. of different cards,
+        // are started one after the other.
+        let linked = input_inner.channel.link(&output_inner.channel).is_ok();
+        prefill_output(&output_inner, &mut Vec::new())?;
+        start_duplex(&input_inner, &output_inner)?;
+        let stream = Stream::new_duplex(
+            Arc::new(input_inner),
+            Arc::new(output_inner),
+            linked,
+            &self.options.thread,
+            data_callback,
+            error_callback,
+            timeout,
+        );
+        Ok(DuplexStream::native(stream))
+    }
 }
 
 struct TriggerSender(libc::c_int);
 
 struct TriggerReceiver(libc::c_int);
 
+// The messages sent to the worker of a stream via its trigger.
+const TRIGGER_DESTROY: u64 = 1;
+const TRIGGER_HOLD: u64 = 2;
+
 impl TriggerSender {
+    // Wake up the receiving worker to be destroyed.
     fn wakeup(&self) {
-        let buf = 1u64;
-        let ret = unsafe { libc::write(self.0, &buf as *const u64 as *const _, 8) };
+        self.send(TRIGGER_DESTROY);
+    }
+
+    fn send(&self, message: u64) {
+        let ret = unsafe { libc::write(self.0, &message as *const u64 as *const _, 8) };
         assert_eq!(ret, 8);
     }
 }
 
 impl TriggerReceiver {
-    fn clear_pipe(&self) {
+    // Read the next message from the pipe.
+    fn clear_pipe(&self) -> u64 {
         let mut out = 0u64;
         let ret = unsafe { libc::read(self.0, &mut out as *mut u64 as *mut _, 8) };
         assert_eq!(ret, 8);
+        out
     }
 }
 
@@ -176,69 +440,37 @@ impl Drop for TriggerReceiver {
     }
 }
 
-#[derive(Default)]
-struct DeviceHandles {
-    playback: Option<alsa::PCM>,
-    capture: Option<alsa::PCM>,
-}
-
-impl DeviceHandles {
-    /// Create `DeviceHandles` for `name` and try to open a handle for both
-    /// directions. Returns `Ok` if either direction is opened successfully.
-    fn open(pcm_id: &str) -> Result<Self, alsa::Error> {
-        let mut handles = Self::default();
-        let playback_err = handles.try_open(pcm_id, alsa::Direction::Playback).err();
-        let capture_err = handles.try_open(pcm_id, alsa::Direction::Capture).err();
-        if let Some(err) = capture_err.and(playback_err) {
-            Err(err)
-        } else {
-            Ok(handles)
-        }
-    }
-
-    /// Get a mutable reference to the `Option` for a specific `stream_type`.
-    /// If the `Option` is `None`, the `alsa::PCM` will be opened and placed in
-    /// the `Option` before returning. If `handle_mut()` returns `Ok` the contained
-    /// `Option` is guaranteed to be `Some(..)`.
-    fn try_open(
-        &mut self,
-        pcm_id: &str,
-        stream_type: alsa::Direction,
-    ) -> Result<&mut Option<alsa::PCM>, alsa::Error> {
-        let handle = match stream_type {
-            alsa::Direction::Playback => &mut self.playback,
-            alsa::Direction::Capture => &mut self.capture,
-        };
-
-        if handle.is_none() {
-            *handle = Some(alsa::pcm::PCM::new(pcm_id, stream_type, true)?);
-        }
-
-        Ok(handle)
-    }
-
-    /// Get a mutable reference to the `alsa::PCM` handle for a specific `stream_type`.
-    /// If the handle is not yet opened, it will be opened and stored in `self`.
-    fn get_mut(
-        &mut self,
-        pcm_id: &str,
-        stream_type: alsa::Direction,
-    ) -> Result<&mut alsa::PCM, alsa::Error> {
-        Ok(self.try_open(pcm_id, stream_type)?.as_mut().unwrap())
+// The id of a card PCM in the current form, given an id stored by an earlier version, which
+// addressed the first device of a card as e.g. `plughw:CARD=PCH` rather than
+// `plughw:CARD=PCH,DEV=0`.
+fn upgrade_pcm_id(pcm_id: &str) -> String {
+    let is_card_pcm = ["hw:CARD=", "plughw:CARD="]
+        .iter()
+        .any(|prefix| pcm_id.starts_with(prefix));
+    if is_card_pcm && !pcm_id.contains(',') {
+        format!("{},DEV=0", pcm_id)
+    } else {
+        pcm_id.to_owned()
     }
+}
 
-    /// Take ownership of the `alsa::PCM` handle for a specific `stream_type`.
-    /// If the handle is not yet opened, it will be opened and returned.
-    fn take(&mut self, name: &str, stream_type: alsa::Direction) -> Result<alsa::PCM, alsa::Error> {
-        Ok(self.try_open(name, stream_type)?.take().unwrap())
-    }
+// Whether a PCM exists, i.e. whether it can be opened in either direction or is busy.
+fn pcm_exists(pcm_id: &str) -> bool {
+    [alsa::Direction::Playback, alsa::Direction::Capture]
+        .into_iter()
+        .any(
+            |stream_type| match alsa::PCM::new(pcm_id, stream_type, true) {
+                Ok(_) => true,
+                Err(err) => err.errno() == libc::EBUSY,
+            },
+        )
 }
 
 #[derive(Clone)]
 pub struct Device {
     name: String,
     pcm_id: String,
-    handles: Arc<Mutex<DeviceHandles>>,
+    options: DeviceOptions,
 }
 
 impl Device {
@@ -247,22 +479,23 @@ impl Device {
         conf: &StreamConfig,
         sample_format: SampleFormat,
         stream_type: alsa::Direction,
+        mmap: bool,
+        auto_start: bool,
     ) -> Result<StreamInner, BuildStreamError> {
-        let handle_result = self
-            .handles
-            .lock()
-            .unwrap()
-            .take(&self.pcm_id, stream_type)
-            .map_err(|e| (e, e.errno()));
+        let handle_result =
+            alsa::PCM::new(&self.pcm_id, stream_type, true).map_err(|e| (e, e.errno()));
 
         let handle = match handle_result {
-            Err((_, libc::EBUSY)) => return Err(BuildStreamError::DeviceNotAvailable),
+            Err((_, libc::ENOENT)) => return Err(BuildStreamError::DeviceNotAvailable),
+            Err((_, libc::EBUSY)) => return Err(BuildStreamError::DeviceBusy),
             Err((_, libc::EINVAL)) => return Err(BuildStreamError::InvalidArgument),
             Err((e, _)) => return Err(e.into()),
             Ok(handle) => handle,
         };
-        let can_pause = set_hw_params_from_format(&handle, conf, sample_format)?;
-        let period_len = set_sw_params_from_format(&handle, conf, stream_type)?;
+        let (can_pause, packed, mmap) =
+            set_hw_params_from_format(&handle, conf, sample_format, mmap, &self.options)?;
+        let period_len = set_sw_params_from_format(&handle, conf, stream_type, auto_start)?;
+        let (buffer_frames, period_frames) = handle.get_params()?;
 
         handle.prepare()?;
 
@@ -276,23 +509,36 @@ impl Device {
         // Check to see if we can retrieve valid timestamps from the device.
         // Related: https://bugs.freedesktop.org/show_bug.cgi?id=88503
         let ts = handle.status()?.get_htstamp();
-        let creation_instant = match (ts.tv_sec, ts.tv_nsec) {
-            (0, 0) => Some(std::time::Instant::now()),
-            _ => None,
+        let timestamp_clock = match (ts.tv_sec, ts.tv_nsec) {
+            (0, 0) => None,
+            _ => match handle.sw_params_current()?.get_tstamp_type()? {
+                alsa::pcm::TstampType::Gettimeofday => Some(libc::CLOCK_REALTIME),
+                alsa::pcm::TstampType::Monotonic => Some(libc::CLOCK_MONOTONIC),
+                alsa::pcm::TstampType::MonotonicRaw => Some(libc::CLOCK_MONOTONIC_RAW),
+            },
         };
 
-        if let alsa::Direction::Capture = stream_type {
+        if auto_start && stream_type == alsa::Direction::Capture {
             handle.start()?;
         }
 
         let stream_inner = StreamInner {
             channel: handle,
+            stream_type,
             sample_format,
             num_descriptors,
             conf: conf.clone(),
             period_len,
+            period_frames: period_frames as usize,
+            buffer_frames: buffer_frames as usize,
             can_pause,
-            creation_instant,
+            packed,
+            mmap,
+            timestamp_clock,
+            auto_start,
+            hold: WorkerHold::default(),
+            position: PositionCounter::default(),
+            stats: StatsCollector::new(conf.sample_rate),
         };
 
         Ok(stream_inner)
@@ -303,37 +549,91 @@ impl Device {
         Ok(self.name.clone())
     }
 
+    fn description(&self) -> Result<DeviceDescription, DeviceNameError> {
+        let mut description = DeviceDescription {
+            name: self.name.clone(),
+            is_plugin: !self.pcm_id.starts_with("hw:"),
+            ..Default::default()
+        };
+
+        // The PCM reports the card and device it ends up at, even through plugins such as plughw.
+        // The PCM cannot be opened while it is busy, the description is then limited to what is
+        // known without it.
+        let info = [alsa::Direction::Playback, alsa::Direction::Capture]
+            .into_iter()
+            .find_map(|stream_type| alsa::PCM::new(&self.pcm_id, stream_type, true).ok())
+            .and_then(|handle| handle.info().ok());
+        if let Some(info) = info.filter(|info| info.get_card() >= 0) {
+            description.card_index = Some(info.get_card() as u32);
+            description.device_index = Some(info.get_device());
+            description.subdevice_index = Some(info.get_subdevice());
+        }
+
+        if description.is_plugin {
+            description.long_name = enumerate::hint_description(&self.pcm_id);
+        }
+        match description.card_index {
+            Some(card) => {
+                let ctl = alsa::Ctl::new(&format!("hw:{}", card), false)?;
+                let card_info = ctl.card_info()?;
+                if description.long_name.is_none() {
+                    description.long_name = Some(card_info.get_longname()?.to_owned());
+                }
+                description.driver = Some(card_info.get_driver()?.to_owned());
+                description.interface = enumerate::card_interface(card);
+            }
+            // PCMs of sound servers are not associated with any card.
+            None => description.interface = DeviceInterface::Virtual,
+        }
+        Ok(description)
+    }
+
+    fn supports_direction(&self, stream_t: alsa::Direction) -> bool {
+        if let Some(supported) = enumerate::card_pcm_supports(&self.pcm_id, stream_t) {
+            return supported;
+        }
+        if let Some(supported) = enumerate::hint_supports(&self.pcm_id, stream_t) {
+            return supported;
+        }
+        // PCMs only known by their id, e.g. ones defined by a string passed to `device_by_id`.
+        match alsa::PCM::new(&self.pcm_id, stream_t, true) {
+            Ok(_) => true,
+            Err(err) => err.errno() == libc::EBUSY,
+        }
+    }
+
     fn supported_configs(
         &self,
         stream_t: alsa::Direction,
     ) -> Result<VecIntoIter<SupportedStreamConfigRange>, SupportedStreamConfigsError> {
-        let mut guard = self.handles.lock().unwrap();
-        let handle_result = guard
-            .get_mut(&self.pcm_id, stream_t)
-            .map_err(|e| (e, e.errno()));
+        // The PCM is only open while its capabilities are queried, so that it is not held while
+        // it might be used by a stream or another application.
+        let handle_result =
+            alsa::PCM::new(&self.pcm_id, stream_t, true).map_err(|e| (e, e.errno()));
 
         let handle = match handle_result {
-            Err((_, libc::ENOENT)) | Err((_, libc::EBUSY)) => {
-                return Err(SupportedStreamConfigsError::DeviceNotAvailable)
-            }
+            Err((_, libc::ENOENT)) => return Err(SupportedStreamConfigsError::DeviceNotAvailable),
+            Err((_, libc::EBUSY)) => return Err(SupportedStreamConfigsError::DeviceBusy),
             Err((_, libc::EINVAL)) => return Err(SupportedStreamConfigsError::InvalidArgument),
             Err((e, _)) => return Err(e.into()),
             Ok(handle) => handle,
         };
 
-        let hw_params = alsa::pcm::HwParams::any(handle)?;
+        let hw_params = alsa::pcm::HwParams::any(&handle)?;
 
         // TODO: check endianness
-        const FORMATS: [(SampleFormat, alsa::pcm::Format); 8] = [
+        // 24-bit samples packed into 3 bytes are converted to and from `I24`/`U24` by the stream
+        // worker, so both layouts are reported as the same sample format.
+        const FORMATS: [(SampleFormat, alsa::pcm::Format); 12] = [
             (SampleFormat::I8, alsa::pcm::Format::S8),
             (SampleFormat::U8, alsa::pcm::Format::U8),
             (SampleFormat::I16, alsa::pcm::Format::S16LE),
             //SND_PCM_FORMAT_S16_BE,
             (SampleFormat::U16, alsa::pcm::Format::U16LE),
             //SND_PCM_FORMAT_U16_BE,
-            //SND_PCM_FORMAT_S24_LE,
+            (SampleFormat::I24, alsa::pcm::Format::S24LE),
             //SND_PCM_FORMAT_S24_BE,
-            //SND_PCM_FORMAT_U24_LE,
+            (SampleFormat::U24, alsa::pcm::Format::U24LE),
             //SND_PCM_FORMAT_U24_BE,
             (SampleFormat::I32, alsa::pcm::Format::S32LE),
             //SND_PCM_FORMAT_S32_BE,
@@ -351,9 +651,9 @@ impl Device {
             //SND_PCM_FORMAT_MPEG,
             //SND_PCM_FORMAT_GSM,
             //SND_PCM_FORMAT_SPECIAL,
-            //SND_PCM_FORMAT_S24_3LE,
+            (SampleFormat::I24, alsa::pcm::Format::S243LE),
             //SND_PCM_FORMAT_S24_3BE,
-            //SND_PCM_FORMAT_U24_3LE,
+            (SampleFormat::U24, alsa::pcm::Format::U243LE),
             //SND_PCM_FORMAT_U24_3BE,
             //SND_PCM_FORMAT_S20_3LE,
             //SND_PCM_FORMAT_S20_3BE,
@@ -367,7 +667,9 @@ impl Device {
 
         let mut supported_formats = Vec::new();
         for &(sample_format, alsa_format) in FORMATS.iter() {
-            if hw_params.test_format(alsa_format).is_ok() {
+            if hw_params.test_format(alsa_format).is_ok()
+                && !supported_formats.contains(&sample_format)
+            {
                 supported_formats.push(sample_format);
             }
         }
@@ -427,9 +729,9 @@ impl Device {
                 for &(min_rate, max_rate) in sample_rates.iter() {
                     output.push(SupportedStreamConfigRange {
                         channels,
-                        min_sample_rate: SampleRate(min_rate as u32),
-                        max_sample_rate: SampleRate(max_rate as u32),
-                        buffer_size: buffer_size_range.clone(),
+                        min_sample_rate: SampleRate(min_rate),
+                        max_sample_rate: SampleRate(max_rate),
+                        buffer_size: buffer_size_range,
                         sample_format,
                     });
                 }
@@ -462,6 +764,9 @@ impl Device {
                 Err(SupportedStreamConfigsError::DeviceNotAvailable) => {
                     return Err(DefaultStreamConfigError::DeviceNotAvailable);
                 }
+                Err(SupportedStreamConfigsError::DeviceBusy) => {
+                    return Err(DefaultStreamConfigError::DeviceBusy);
+                }
                 Err(SupportedStreamConfigsError::InvalidArgument) => {
                     // this happens sometimes when querying for input and output capabilities, but
                     // the device supports only one
@@ -508,6 +813,9 @@ struct StreamInner {
     // file descriptors that this `snd_pcm_t` uses.
     num_descriptors: usize,
 
+    // Whether this is a capture or playback stream.
+    stream_type: alsa::Direction,
+
     // Format of the samples.
     sample_format: SampleFormat,
 
@@ -517,25 +825,120 @@ struct StreamInner {
     // Minimum number of samples to put in the buffer.
     period_len: usize,
 
-    #[allow(dead_code)]
+    // The period and ring buffer sizes chosen by ALSA, in frames.
+    period_frames: usize,
+    buffer_frames: usize,
+
     // Whether or not the hardware supports pausing the stream.
     // TODO: We need an API to expose this. See #197, #284.
     can_pause: bool,
 
-    // In the case that the device does not return valid timestamps via `get_htstamp`, this field
-    // will be `Some` and will contain an `Instant` representing the moment the stream was created.
-    //
-    // If this field is `Some`, then the stream will use the duration since this instant as a
-    // source for timestamps.
+    // Whether 24-bit samples are packed into 3 bytes on the device. They are converted to and
+    // from the 4-byte representation of `I24`/`U24` when reading and writing.
+    packed: bool,
+
+    // Whether the ring buffer of the device is accessed directly via mmap, rather than by
+    // reading and writing an intermediate buffer.
+    mmap: bool,
+
+    // The clock of the timestamps returned by `get_htstamp`, which are mapped onto the epoch of
+    // `StreamInstant` by comparing them with the current time of that clock.
     //
-    // If this field is `None` then the elapsed duration between `get_trigger_htstamp` and
-    // `get_htstamp` is used.
-    creation_instant: Option<std::time::Instant>,
+    // If this field is `None`, the device does not return valid timestamps, and the current
+    // `StreamInstant` is used instead.
+    timestamp_clock: Option<libc::clockid_t>,
+
+    // Whether the device starts on its own, i.e. an input when it is built and an output once
+    // its worker has filled the buffer. The two halves of duplex streams are started together
+    // instead.
+    auto_start: bool,
+
+    // Holds the worker of the stream while `play_all` restarts it. For duplex streams, this is
+    // the one of the output.
+    hold: WorkerHold,
+
+    // The number of frames passed to or from the data callback.
+    position: PositionCounter,
+
+    // The timing statistics of the data callback and the xruns of the stream.
+    stats: StatsCollector,
 }
 
 // Assume that the ALSA library is built with thread safe option.
 unsafe impl Sync for StreamInner {}
 
+// Lets another thread operate on the PCMs of a stream while its worker waits, see `play_all`.
+#[derive(Default)]
+struct WorkerHold {
+    state: Mutex<HoldState>,
+    changed: Condvar,
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum HoldState {
+    #[default]
+    Released,
+    // The worker has been sent `TRIGGER_HOLD`, but has not received it yet.
+    Requested,
+    Held,
+}
+
+impl WorkerHold {
+    fn request(&self) {
+        *self.state.lock().unwrap() = HoldState::Requested;
+    }
+
+    fn wait_until_held(&self) {
+        let state = self.state.lock().unwrap();
+        drop(
+            self.changed
+                .wait_while(state, |state| *state == HoldState::Requested)
+                .unwrap(),
+        );
+    }
+
+    fn release(&self) {
+        *self.state.lock().unwrap() = HoldState::Released;
+        self.changed.notify_all();
+    }
+
+    // Called by the worker on receiving `TRIGGER_HOLD`, to wait until released.
+    fn park(&self) {
+        let mut state = self.state.lock().unwrap();
+        if *state != HoldState::Requested {
+            return;
+        }
+        *state = HoldState::Held;
+        self.changed.notify_all();
+        drop(
+            self.changed
+                .wait_while(state, |state| *state == HoldState::Held)
+                .unwrap(),
+        );
+    }
+}
+
+impl StreamInner {
+    // Record a late wakeup if less than half a period of the ring buffer was left to process
+    // before an xrun when the worker woke up with `avail_frames` frames available.
+    fn check_wakeup(&self, avail_frames: usize) {
+        if avail_frames + self.period_frames / 2 > self.buffer_frames {
+            self.stats.record_late_wakeup();
+        }
+    }
+
+    // The number of frames between the application and the device, as reported by `delay`.
+    fn latency(&self) -> Result<Duration, BackendSpecificError> {
+        let frames = match self.channel.delay() {
+            Ok(delay) => cmp::max(delay, 0) as usize,
+            // The stream is not in a state to report its delay, e.g. after an xrun. Assume that
+            // the buffer is full.
+            Err(_) => self.channel.get_params()?.0 as usize,
+        };
+        Ok(frames_to_duration(frames, self.conf.sample_rate))
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 enum StreamType {
     Input,
@@ -548,8 +951,16 @@ pub struct Stream {
     thread: Option<JoinHandle<()>>,
 
     /// Handle to the underlying stream for playback controls.
+    ///
+    /// For duplex streams this is the playback half.
     inner: Arc<StreamInner>,
 
+    /// Handle to the capture half of a duplex stream.
+    duplex_input: Option<Arc<StreamInner>>,
+
+    /// Whether the PCMs of the two halves of a duplex stream are linked.
+    duplex_linked: bool,
+
     /// Used to signal to stop processing.
     trigger: TriggerSender,
 }
@@ -596,7 +1007,13 @@ fn input_stream_worker(
                 continue;
             }
             PollDescriptorsFlow::XRun => {
-                if let Err(err) = stream.channel.prepare() {
+                error_callback(xrun_error(stream, alsa::Direction::Capture));
+                // Capture streams do not restart on their own once prepared.
+                if let Err(err) = stream
+                    .channel
+                    .prepare()
+                    .and_then(|_| stream.channel.start())
+                {
                     error_callback(err.into());
                 }
                 continue;
@@ -604,8 +1021,9 @@ fn input_stream_worker(
             PollDescriptorsFlow::Return => return,
             PollDescriptorsFlow::Ready {
                 status,
-                avail_frames: _,
+                avail_frames,
                 delay_frames,
+                underrun_frames: _,
                 stream_type,
             } => {
                 assert_eq!(
@@ -617,8 +1035,10 @@ fn input_stream_worker(
                     stream,
                     &mut ctxt.buffer,
                     status,
+                    avail_frames,
                     delay_frames,
                     data_callback,
+                    error_callback,
                 ) {
                     error_callback(err.into());
                 }
@@ -645,6 +1065,7 @@ fn output_stream_worker(
         match flow {
             PollDescriptorsFlow::Continue => continue,
             PollDescriptorsFlow::XRun => {
+                error_callback(xrun_error(stream, alsa::Direction::Playback));
                 if let Err(err) = stream.channel.prepare() {
                     error_callback(err.into());
                 }
@@ -655,6 +1076,7 @@ fn output_stream_worker(
                 status,
                 avail_frames,
                 delay_frames,
+                underrun_frames,
                 stream_type,
             } => {
                 assert_eq!(
@@ -662,6 +1084,13 @@ fn output_stream_worker(
                     StreamType::Output,
                     "expected output stream, but polling descriptors indicated input",
                 );
+                if underrun_frames > 0 {
+                    stream.position.mark_discontinuity();
+                    stream.stats.record_xrun();
+                    error_callback(StreamError::BufferUnderrun {
+                        frames_lost: Some(underrun_frames),
+                    });
+                }
                 if let Err(err) = process_output(
                     stream,
                     &mut ctxt.buffer,
@@ -678,6 +1107,68 @@ fn output_stream_worker(
     }
 }
 
+fn duplex_stream_worker(
+    rx: TriggerReceiver,
+    input: &StreamInner,
+    output: &StreamInner,
+    data_callback: &mut (dyn FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static),
+    error_callback: &mut (dyn FnMut(StreamError) + Send + 'static),
+    timeout: Option<Duration>,
+) {
+    let mut ctxt = StreamWorkerContext::new(&timeout);
+    let mut input_buffer = Vec::new();
+    loop {
+        let flow = poll_duplex_descriptors_and_prepare_buffers(
+            &rx,
+            input,
+            output,
+            &mut ctxt,
+            &mut input_buffer,
+        )
+        .unwrap_or_else(|err| {
+            error_callback(err.into());
+            DuplexPollDescriptorsFlow::Continue
+        });
+
+        match flow {
+            DuplexPollDescriptorsFlow::Continue => continue,
+            DuplexPollDescriptorsFlow::XRun(direction) => {
+                let stream = match direction {
+                    alsa::Direction::Capture => input,
+                    alsa::Direction::Playback => output,
+                };
+                error_callback(xrun_error(stream, direction));
+                if let Err(err) = restart_duplex(input, output, &mut ctxt.buffer) {
+                    error_callback(err.into());
+                }
+                continue;
+            }
+            DuplexPollDescriptorsFlow::Return => return,
+            DuplexPollDescriptorsFlow::Ready {
+                status,
+                frames,
+                input_delay_frames,
+                output_delay_frames,
+            } => {
+                if let Err(err) = process_duplex(
+                    input,
+                    output,
+                    &mut input_buffer,
+                    &mut ctxt.buffer,
+                    status,
+                    frames,
+                    input_delay_frames,
+                    output_delay_frames,
+                    data_callback,
+                    error_callback,
+                ) {
+                    error_callback(err.into());
+                }
+            }
+        }
+    }
+}
+
 enum PollDescriptorsFlow {
     Continue,
     Return,
@@ -686,6 +1177,9 @@ enum PollDescriptorsFlow {
         status: alsa::pcm::Status,
         avail_frames: usize,
         delay_frames: usize,
+        // The number of frames by which the hardware has overtaken the application pointer of
+        // an output stream that has not been stopped by the underrun (yet).
+        underrun_frames: usize,
     },
     XRun,
 }
@@ -732,9 +1226,12 @@ fn poll_descriptors_and_prepare_buffer(
     }
 
     if descriptors[0].revents != 0 {
-        // The stream has been requested to be destroyed.
-        rx.clear_pipe();
-        return Ok(PollDescriptorsFlow::Return);
+        if rx.clear_pipe() == TRIGGER_DESTROY {
+            // The stream has been requested to be destroyed.
+            return Ok(PollDescriptorsFlow::Return);
+        }
+        stream.hold.park();
+        return Ok(PollDescriptorsFlow::Continue);
     }
 
     let revents = stream.channel.revents(&descriptors[1..])?;
@@ -752,14 +1249,17 @@ fn poll_descriptors_and_prepare_buffer(
     };
 
     let status = stream.channel.status()?;
+    // More frames than fit into the buffer may be reported after an underrun. Callbacks never
+    // receive more than a buffer though, see `Stream::buffer_size`.
     let avail_frames = match stream.channel.avail() {
         Err(err) if err.errno() == libc::EPIPE => return Ok(PollDescriptorsFlow::XRun),
         res => res,
     }? as usize;
-    let delay_frames = match status.get_delay() {
-        // Buffer underrun. TODO: Notify the user.
-        d if d < 0 => 0,
-        d => d as usize,
+    let avail_frames = cmp::min(avail_frames, stream.buffer_frames);
+    let (delay_frames, underrun_frames) = match status.get_delay() {
+        // Buffer underrun.
+        d if d < 0 => (0, -d as usize),
+        d => (d as usize, 0),
     };
     let available_samples = avail_frames * stream.conf.channels as usize;
 
@@ -767,16 +1267,133 @@ fn poll_descriptors_and_prepare_buffer(
     if available_samples < stream.period_len {
         return Ok(PollDescriptorsFlow::Continue);
     }
+    stream.check_wakeup(avail_frames);
 
-    // Prepare the data buffer.
-    let buffer_size = stream.sample_format.sample_size() * available_samples;
-    buffer.resize(buffer_size, 0u8);
+    // Prepare the data buffer. Streams using mmap access hand out the ring buffer instead.
+    if !stream.mmap {
+        let buffer_size = stream.sample_format.sample_size() * available_samples;
+        buffer.resize(buffer_size, 0u8);
+    }
 
     Ok(PollDescriptorsFlow::Ready {
         stream_type,
         status,
         avail_frames,
         delay_frames,
+        underrun_frames,
+    })
+}
+
+enum DuplexPollDescriptorsFlow {
+    Continue,
+    Return,
+    Ready {
+        status: alsa::pcm::Status,
+        frames: usize,
+        input_delay_frames: usize,
+        output_delay_frames: usize,
+    },
+    XRun(alsa::Direction),
+}
+
+// Waits until both halves of a duplex stream have at least one period available.
+//
+// Only the descriptors of the half that is not yet ready are polled, so that a ready half does not
+// cause the worker to spin.
+fn poll_duplex_descriptors_and_prepare_buffers(
+    rx: &TriggerReceiver,
+    input: &StreamInner,
+    output: &StreamInner,
+    ctxt: &mut StreamWorkerContext,
+    input_buffer: &mut Vec<u8>,
+) -> Result<DuplexPollDescriptorsFlow, BackendSpecificError> {
+    let input_avail = match input.channel.avail() {
+        Err(err) if err.errno() == libc::EPIPE => {
+            return Ok(DuplexPollDescriptorsFlow::XRun(alsa::Direction::Capture))
+        }
+        res => res,
+    }? as usize;
+    let output_avail = match output.channel.avail() {
+        Err(err) if err.errno() == libc::EPIPE => {
+            return Ok(DuplexPollDescriptorsFlow::XRun(alsa::Direction::Playback))
+        }
+        res => res,
+    }? as usize;
+
+    let input_ready = input_avail * input.conf.channels as usize >= input.period_len;
+    let output_ready = output_avail * output.conf.channels as usize >= output.period_len;
+    let waiting = match (input_ready, output_ready) {
+        (false, _) => Some(input),
+        (true, false) => Some(output),
+        (true, true) => None,
+    };
+
+    if let Some(stream) = waiting {
+        let descriptors = &mut ctxt.descriptors;
+        descriptors.clear();
+
+        // Add the self-pipe for signaling termination.
+        descriptors.push(libc::pollfd {
+            fd: rx.0,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+
+        // Add ALSA polling fds.
+        descriptors.resize(
+            stream.num_descriptors + 1,
+            libc::pollfd {
+                fd: 0,
+                events: 0,
+                revents: 0,
+            },
+        );
+        let filled = stream.channel.fill(&mut descriptors[1..])?;
+        debug_assert_eq!(filled, stream.num_descriptors);
+
+        let res = alsa::poll::poll(descriptors, ctxt.poll_timeout)?;
+        if res == 0 {
+            let description = String::from("`alsa::poll()` spuriously returned");
+            return Err(BackendSpecificError { description });
+        }
+
+        if descriptors[0].revents != 0 {
+            if rx.clear_pipe() == TRIGGER_DESTROY {
+                // The stream has been requested to be destroyed.
+                return Ok(DuplexPollDescriptorsFlow::Return);
+            }
+            output.hold.park();
+            return Ok(DuplexPollDescriptorsFlow::Continue);
+        }
+
+        let revents = stream.channel.revents(&descriptors[1..])?;
+        if revents.contains(alsa::poll::Flags::ERR) {
+            let description = String::from("`alsa::poll()` returned POLLERR");
+            return Err(BackendSpecificError { description });
+        }
+        return Ok(DuplexPollDescriptorsFlow::Continue);
+    }
+
+    input.check_wakeup(cmp::min(input_avail, input.buffer_frames));
+    output.check_wakeup(cmp::min(output_avail, output.buffer_frames));
+    let frames = cmp::min(input_avail, output_avail)
+        .min(input.buffer_frames)
+        .min(output.buffer_frames);
+    let status = output.channel.status()?;
+    let output_delay_frames = cmp::max(status.get_delay(), 0) as usize;
+    let input_delay_frames = cmp::max(input.channel.status()?.get_delay(), 0) as usize;
+
+    // Prepare the data buffers.
+    let sample_size = output.sample_format.sample_size();
+    input_buffer.resize(frames * input.conf.channels as usize * sample_size, 0u8);
+    ctxt.buffer
+        .resize(frames * output.conf.channels as usize * sample_size, 0u8);
+
+    Ok(DuplexPollDescriptorsFlow::Ready {
+        status,
+        frames,
+        input_delay_frames,
+        output_delay_frames,
     })
 }
 
@@ -785,22 +1402,68 @@ fn process_input(
     stream: &StreamInner,
     buffer: &mut [u8],
     status: alsa::pcm::Status,
+    available_frames: usize,
     delay_frames: usize,
     data_callback: &mut (dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static),
+    error_callback: &mut dyn FnMut(StreamError),
 ) -> Result<(), BackendSpecificError> {
-    stream.channel.io_bytes().readi(buffer)?;
+    if stream.mmap {
+        let sample_format = stream.sample_format;
+        let callback = stream_timestamp(&status, stream.timestamp_clock)?;
+        mmap_frames(
+            stream,
+            available_frames,
+            alsa::Direction::Capture,
+            error_callback,
+            |area, offset| {
+                let len = area.len() / sample_format.sample_size();
+                let data =
+                    unsafe { Data::from_parts(area.as_mut_ptr() as *mut (), len, sample_format) };
+                // The frames at the start of the ring buffer were captured first.
+                let delay = delay_frames.saturating_sub(offset);
+                let capture = callback
+                    .sub(frames_to_duration(delay, stream.conf.sample_rate))
+                    .expect(
+                        "`capture` is earlier than representation supported by `StreamInstant`",
+                    );
+                let timestamp = crate::InputStreamTimestamp { callback, capture };
+                let frames = len / stream.conf.channels as usize;
+                let position = stream.position.advance(frames);
+                let info = crate::InputCallbackInfo {
+                    timestamp,
+                    position,
+                };
+                let start = stream.stats.start();
+                data_callback(&data, &info);
+                stream.stats.record_callback(start, frames);
+            },
+        )?;
+        return Ok(());
+    }
+    if !read_input(stream, buffer, error_callback)? {
+        stream.channel.prepare()?;
+        stream.channel.start()?;
+        return Ok(());
+    }
     let sample_format = stream.sample_format;
     let data = buffer.as_mut_ptr() as *mut ();
     let len = buffer.len() / sample_format.sample_size();
     let data = unsafe { Data::from_parts(data, len, sample_format) };
-    let callback = stream_timestamp(&status, stream.creation_instant)?;
+    let callback = stream_timestamp(&status, stream.timestamp_clock)?;
     let delay_duration = frames_to_duration(delay_frames, stream.conf.sample_rate);
     let capture = callback
         .sub(delay_duration)
         .expect("`capture` is earlier than representation supported by `StreamInstant`");
     let timestamp = crate::InputStreamTimestamp { callback, capture };
-    let info = crate::InputCallbackInfo { timestamp };
+    let frames = len / stream.conf.channels as usize;
+    let position = stream.position.advance(frames);
+    let info = crate::InputCallbackInfo {
+        timestamp,
+        position,
+    };
+    let start = stream.stats.start();
     data_callback(&data, &info);
+    stream.stats.record_callback(start, frames);
 
     Ok(())
 }
@@ -817,37 +1480,301 @@ fn process_output(
     data_callback: &mut (dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static),
     error_callback: &mut dyn FnMut(StreamError),
 ) -> Result<(), BackendSpecificError> {
+    if stream.mmap {
+        let sample_format = stream.sample_format;
+        let callback = stream_timestamp(&status, stream.timestamp_clock)?;
+        let written = mmap_frames(
+            stream,
+            available_frames,
+            alsa::Direction::Playback,
+            error_callback,
+            |area, offset| {
+                let len = area.len() / sample_format.sample_size();
+                let mut data =
+                    unsafe { Data::from_parts(area.as_mut_ptr() as *mut (), len, sample_format) };
+                let delay = frames_to_duration(delay_frames + offset, stream.conf.sample_rate);
+                let playback = callback
+                    .add(delay)
+                    .expect("`playback` occurs beyond representation supported by `StreamInstant`");
+                let timestamp = crate::OutputStreamTimestamp { callback, playback };
+                let frames = len / stream.conf.channels as usize;
+                let position = stream.position.advance(frames);
+                let info = crate::OutputCallbackInfo {
+                    timestamp,
+                    position,
+                };
+                let start = stream.stats.start();
+                data_callback(&mut data, &info);
+                stream.stats.record_callback(start, frames);
+            },
+        )?;
+        // Unlike writes, committing frames to the ring buffer never starts the stream.
+        if written && stream.channel.state() == alsa::pcm::State::Prepared {
+            stream.channel.start()?;
+        }
+        return Ok(());
+    }
     {
         // We're now sure that we're ready to write data.
         let sample_format = stream.sample_format;
         let data = buffer.as_mut_ptr() as *mut ();
         let len = buffer.len() / sample_format.sample_size();
         let mut data = unsafe { Data::from_parts(data, len, sample_format) };
-        let callback = stream_timestamp(&status, stream.creation_instant)?;
+        let callback = stream_timestamp(&status, stream.timestamp_clock)?;
         let delay_duration = frames_to_duration(delay_frames, stream.conf.sample_rate);
         let playback = callback
             .add(delay_duration)
             .expect("`playback` occurs beyond representation supported by `StreamInstant`");
         let timestamp = crate::OutputStreamTimestamp { callback, playback };
-        let info = crate::OutputCallbackInfo { timestamp };
+        let frames = len / stream.conf.channels as usize;
+        let position = stream.position.advance(frames);
+        let info = crate::OutputCallbackInfo {
+            timestamp,
+            position,
+        };
+        let start = stream.stats.start();
         data_callback(&mut data, &info);
+        stream.stats.record_callback(start, frames);
+    }
+    write_output(stream, buffer, available_frames, error_callback);
+    Ok(())
+}
+
+// Read input data and hand it to the user together with the output buffer, then write the output.
+#[allow(clippy::too_many_arguments)]
+fn process_duplex(
+    input: &StreamInner,
+    output: &StreamInner,
+    input_buffer: &mut [u8],
+    output_buffer: &mut Vec<u8>,
+    status: alsa::pcm::Status,
+    frames: usize,
+    input_delay_frames: usize,
+    output_delay_frames: usize,
+    data_callback: &mut (dyn FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static),
+    error_callback: &mut dyn FnMut(StreamError),
+) -> Result<(), BackendSpecificError> {
+    if !read_input(input, input_buffer, error_callback)? {
+        restart_duplex(input, output, output_buffer)?;
+        return Ok(());
+    }
+    {
+        let sample_format = output.sample_format;
+        let input_data = input_buffer.as_mut_ptr() as *mut ();
+        let input_len = input_buffer.len() / sample_format.sample_size();
+        let input_data = unsafe { Data::from_parts(input_data, input_len, sample_format) };
+        let output_data = output_buffer.as_mut_ptr() as *mut ();
+        let output_len = output_buffer.len() / sample_format.sample_size();
+        let mut output_data = unsafe { Data::from_parts(output_data, output_len, sample_format) };
+
+        let callback = stream_timestamp(&status, output.timestamp_clock)?;
+        let capture = callback
+            .sub(frames_to_duration(
+                input_delay_frames,
+                input.conf.sample_rate,
+            ))
+            .expect("`capture` is earlier than representation supported by `StreamInstant`");
+        let playback = callback
+            .add(frames_to_duration(
+                output_delay_frames,
+                output.conf.sample_rate,
+            ))
+            .expect("`playback` occurs beyond representation supported by `StreamInstant`");
+        let timestamp = crate::DuplexStreamTimestamp {
+            callback,
+            capture,
+            playback,
+        };
+        // Keep both counters in step, so that xruns of either half flag a discontinuity.
+        let mut position = output.position.advance(frames);
+        position.discontinuity |= input.position.advance(frames).discontinuity;
+        let info = crate::DuplexCallbackInfo {
+            timestamp,
+            position,
+        };
+        let start = output.stats.start();
+        data_callback(&input_data, &mut output_data, &info);
+        output.stats.record_callback(start, frames);
+    }
+    write_output(output, output_buffer, frames, error_callback);
+    // The output was prepared again after an underrun.
+    if output.channel.state() == alsa::pcm::State::Prepared {
+        restart_duplex(input, output, output_buffer)?;
+    }
+    Ok(())
+}
+
+// The number of frames an output holds when it starts, all but a period of the buffer, so that it
+// does not underrun while an input started with it captures the first period.
+fn prefill_frames(output: &StreamInner) -> usize {
+    output.buffer_frames - output.period_frames
+}
+
+// Write the frames a prepared output starts with, as silence.
+fn prefill_output(output: &StreamInner, buffer: &mut Vec<u8>) -> Result<(), alsa::Error> {
+    let sample_format = output.sample_format;
+    let frames = prefill_frames(output);
+    if output.mmap {
+        let frame_size = sample_format.sample_size() * output.conf.channels as usize;
+        let io = output.channel.io_bytes();
+        let mut done = 0;
+        while done < frames {
+            let written = io.mmap(frames - done, |area| {
+                let len = area.len() / sample_format.sample_size();
+                let area_ptr = area.as_mut_ptr() as *mut ();
+                let mut data = unsafe { Data::from_parts(area_ptr, len, sample_format) };
+                crate::duplex::fill_equilibrium(&mut data, 0);
+                area.len() / frame_size
+            })?;
+            if written == 0 {
+                break;
+            }
+            done += written;
+        }
+        return Ok(());
+    }
+    let len = frames * output.conf.channels as usize;
+    buffer.resize(len * sample_format.sample_size(), 0u8);
+    let mut data = unsafe { Data::from_parts(buffer.as_mut_ptr() as *mut (), len, sample_format) };
+    crate::duplex::fill_equilibrium(&mut data, 0);
+    let buffer = if output.packed {
+        pack_24(buffer);
+        &buffer[..buffer.len() / 4 * 3]
+    } else {
+        &buffer[..]
+    };
+    output.channel.io_bytes().writei(buffer)?;
+    Ok(())
+}
+
+// Start both halves of a prepared duplex stream. Linked halves are started together by starting
+// the input.
+fn start_duplex(input: &StreamInner, output: &StreamInner) -> Result<(), alsa::Error> {
+    input.channel.start()?;
+    if output.channel.state() == alsa::pcm::State::Prepared {
+        output.channel.start()?;
     }
+    Ok(())
+}
+
+// Restart both halves of a duplex stream after an xrun of either of them, so that they are in
+// sync again. The frames captured and written before are dropped.
+fn restart_duplex(
+    input: &StreamInner,
+    output: &StreamInner,
+    buffer: &mut Vec<u8>,
+) -> Result<(), alsa::Error> {
+    input.channel.drop()?;
+    output.channel.drop()?;
+    input.channel.prepare()?;
+    output.channel.prepare()?;
+    prefill_output(output, buffer)?;
+    start_duplex(input, output)
+}
+
+// Fill the buffer from the capture device.
+//
+// Returns `false` if the device overran, in which case the overrun has been reported and the
+// buffer holds no valid data. The caller restarts the device.
+fn read_input(
+    stream: &StreamInner,
+    buffer: &mut [u8],
+    error_callback: &mut dyn FnMut(StreamError),
+) -> Result<bool, BackendSpecificError> {
+    let device_len = if stream.packed {
+        buffer.len() / 4 * 3
+    } else {
+        buffer.len()
+    };
+    match stream.channel.io_bytes().readi(&mut buffer[..device_len]) {
+        Err(err) if err.errno() == libc::EPIPE => {
+            error_callback(xrun_error(stream, alsa::Direction::Capture));
+            return Ok(false);
+        }
+        Err(err) => return Err(err.into()),
+        Ok(_) => (),
+    }
+
+    let signed = stream.sample_format == SampleFormat::I24;
+    if stream.packed {
+        unpack_24(buffer, signed);
+    } else if signed || stream.sample_format == SampleFormat::U24 {
+        // Devices are free to put anything in the unused high byte.
+        for sample in buffer.chunks_exact_mut(4) {
+            let value = i32::from_ne_bytes(sample.try_into().unwrap());
+            sample.copy_from_slice(&normalize_24(value, signed).to_ne_bytes());
+        }
+    }
+    Ok(true)
+}
+
+// Hand `frames` frames of the ring buffer to `f` using mmap access, together with the number of
+// frames handed out before. The area handed out ends at the end of the ring buffer, so this may
+// take two rounds.
+//
+// Returns `false` if the device ran into an xrun, in which case the xrun has been reported and the
+// device has been prepared again.
+fn mmap_frames(
+    stream: &StreamInner,
+    frames: usize,
+    direction: alsa::Direction,
+    error_callback: &mut dyn FnMut(StreamError),
+    mut f: impl FnMut(&mut [u8], usize),
+) -> Result<bool, BackendSpecificError> {
+    let frame_size = stream.sample_format.sample_size() * stream.conf.channels as usize;
+    let io = stream.channel.io_bytes();
+    let mut done = 0;
+    while done < frames {
+        let result = io.mmap(frames - done, |area| {
+            f(area, done);
+            area.len() / frame_size
+        });
+        match result {
+            Err(err) if err.errno() == libc::EPIPE => {
+                error_callback(xrun_error(stream, direction));
+                stream.channel.prepare()?;
+                if let alsa::Direction::Capture = direction {
+                    // Capture streams do not restart on their own once prepared.
+                    stream.channel.start()?;
+                }
+                return Ok(false);
+            }
+            Err(err) => return Err(err.into()),
+            Ok(0) => break,
+            Ok(committed) => done += committed,
+        }
+    }
+    Ok(true)
+}
+
+// Write a buffer holding exactly `frames` frames to the playback device.
+fn write_output(
+    stream: &StreamInner,
+    buffer: &mut [u8],
+    frames: usize,
+    error_callback: &mut dyn FnMut(StreamError),
+) {
+    let buffer = if stream.packed {
+        pack_24(buffer);
+        &buffer[..buffer.len() / 4 * 3]
+    } else {
+        &buffer[..]
+    };
     loop {
         match stream.channel.io_bytes().writei(buffer) {
             Err(err) if err.errno() == libc::EPIPE => {
-                // buffer underrun
-                // TODO: Notify the user of this.
+                error_callback(xrun_error(stream, alsa::Direction::Playback));
                 let _ = stream.channel.try_recover(err, false);
             }
             Err(err) => {
                 error_callback(err.into());
                 continue;
             }
-            Ok(result) if result != available_frames => {
+            Ok(result) if result != frames => {
                 let description = format!(
                     "unexpected number of frames written: expected {}, \
                      result {} (this should never happen)",
-                    available_frames, result,
+                    frames, result,
                 );
                 error_callback(BackendSpecificError { description }.into());
                 continue;
@@ -857,45 +1784,106 @@ fn process_output(
             }
         }
     }
-    Ok(())
 }
 
-// Use the elapsed duration since the start of the stream.
+// The instant at which `status` was taken.
 //
-// This ensures positive values that are compatible with our `StreamInstant` representation.
+// The timestamp of the status is mapped onto the epoch of `StreamInstant` via the current time of
+// its clock, so that the instants of all streams are comparable.
 fn stream_timestamp(
     status: &alsa::pcm::Status,
-    creation_instant: Option<std::time::Instant>,
+    timestamp_clock: Option<libc::clockid_t>,
 ) -> Result<crate::StreamInstant, BackendSpecificError> {
-    match creation_instant {
-        None => {
-            let trigger_ts = status.get_trigger_htstamp();
-            let ts = status.get_htstamp();
-            let nanos = timespec_diff_nanos(ts, trigger_ts);
-            if nanos < 0 {
-                let description = format!(
-                    "get_htstamp `{}.{}` was earlier than get_trigger_htstamp `{}.{}`",
-                    ts.tv_sec, ts.tv_nsec, trigger_ts.tv_sec, trigger_ts.tv_nsec
-                );
-                return Err(BackendSpecificError { description });
-            }
-            Ok(crate::StreamInstant::from_nanos(nanos))
-        }
-        Some(creation) => {
-            let now = std::time::Instant::now();
-            let duration = now.duration_since(creation);
-            crate::StreamInstant::from_nanos_i128(duration.as_nanos() as i128).ok_or(
-                BackendSpecificError {
-                    description: "stream duration has exceeded `StreamInstant` representation"
-                        .to_string(),
-                },
-            )
+    let Some(clock) = timestamp_clock else {
+        return Ok(crate::StreamInstant::now());
+    };
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(clock, &mut now) } != 0 {
+        let description = format!(
+            "failed to read the timestamp clock: {}",
+            std::io::Error::last_os_error()
+        );
+        return Err(BackendSpecificError { description });
+    }
+    let ts = status.get_htstamp();
+    crate::StreamInstant::from_clock_nanos(
+        timespec_to_nanos(ts) as i128,
+        timespec_to_nanos(now) as i128,
+    )
+    .ok_or(BackendSpecificError {
+        description: "get_htstamp is out of range of the `StreamInstant` representation"
+            .to_string(),
+    })
+}
+
+// Convert the 3-byte samples at the start of `buffer` to the 4-byte representation of `I24` or
+// `U24`, filling the whole buffer.
+fn unpack_24(buffer: &mut [u8], signed: bool) {
+    // Work backwards so that no packed sample is overwritten before it has been read.
+    for i in (0..buffer.len() / 4).rev() {
+        let p = &buffer[i * 3..i * 3 + 3];
+        let bytes = if cfg!(target_endian = "big") {
+            [0, p[0], p[1], p[2]]
+        } else {
+            [p[0], p[1], p[2], 0]
+        };
+        let value = normalize_24(i32::from_ne_bytes(bytes), signed);
+        buffer[i * 4..i * 4 + 4].copy_from_slice(&value.to_ne_bytes());
+    }
+}
+
+// Convert the 4-byte `I24` or `U24` samples in `buffer` to 3-byte samples, stored in the first
+// three quarters of the buffer.
+fn pack_24(buffer: &mut [u8]) {
+    for i in 0..buffer.len() / 4 {
+        let s: [u8; 4] = buffer[i * 4..i * 4 + 4].try_into().unwrap();
+        let bytes = if cfg!(target_endian = "big") {
+            [s[1], s[2], s[3]]
+        } else {
+            [s[0], s[1], s[2]]
+        };
+        buffer[i * 3..i * 3 + 3].copy_from_slice(&bytes);
+    }
+}
+
+// Clear the unused high byte of a 24-bit sample, extending the sign of signed samples.
+fn normalize_24(value: i32, signed: bool) -> i32 {
+    if signed {
+        value << 8 >> 8
+    } else {
+        value & 0x00ff_ffff
+    }
+}
+
+// Build the error reporting an xrun of the given stream, flag the next buffer of the stream as
+// discontinuous and count the xrun.
+//
+// The stream stops when the xrun occurs, so the number of frames lost is estimated from the time
+// that passed since then. This must be called before the stream is prepared again.
+fn xrun_error(stream: &StreamInner, direction: alsa::Direction) -> StreamError {
+    stream.position.mark_discontinuity();
+    stream.stats.record_xrun();
+    let frames_lost = match stream.channel.status() {
+        Ok(status) if stream.timestamp_clock.is_some() => {
+            let nanos = timespec_diff_nanos(status.get_htstamp(), status.get_trigger_htstamp());
+            let rate = stream.conf.sample_rate.0 as i128;
+            Some((cmp::max(nanos, 0) as i128 * rate / 1_000_000_000) as usize)
         }
+        _ => None,
+    };
+    match direction {
+        alsa::Direction::Playback => StreamError::BufferUnderrun { frames_lost },
+        alsa::Direction::Capture => StreamError::BufferOverrun { frames_lost },
     }
 }
 
 // Adapted from `timestamp2ns` here:
 // https://fossies.org/linux/alsa-lib/test/audio_time.c
+// The fields of `timespec` are only 32 bits wide on some targets.
+#[allow(clippy::unnecessary_cast)]
 fn timespec_to_nanos(ts: libc::timespec) -> i64 {
     ts.tv_sec as i64 * 1_000_000_000 + ts.tv_nsec as i64
 }
@@ -906,17 +1894,10 @@ fn timespec_diff_nanos(a: libc::timespec, b: libc::timespec) -> i64 {
     timespec_to_nanos(a) - timespec_to_nanos(b)
 }
 
-// Convert the given duration in frames at the given sample rate to a `std::time::Duration`.
-fn frames_to_duration(frames: usize, rate: crate::SampleRate) -> std::time::Duration {
-    let secsf = frames as f64 / rate.0 as f64;
-    let secs = secsf as u64;
-    let nanos = ((secsf - secs as f64) * 1_000_000_000.0) as u32;
-    std::time::Duration::new(secs, nanos)
-}
-
 impl Stream {
     fn new_input<D, E>(
         inner: Arc<StreamInner>,
+        thread_options: &ThreadOptions,
         mut data_callback: D,
         mut error_callback: E,
         timeout: Option<Duration>,
@@ -928,9 +1909,11 @@ impl Stream {
         let (tx, rx) = trigger();
         // Clone the handle for passing into worker thread.
         let stream = inner.clone();
-        let thread = thread::Builder::new()
-            .name("cpal_alsa_in".to_owned())
+        let thread_options = thread_options.clone();
+        let thread = thread_options
+            .builder("in")
             .spawn(move || {
+                thread_options.apply(&mut |err| error_callback(err.into()));
                 input_stream_worker(
                     rx,
                     &stream,
@@ -943,12 +1926,15 @@ impl Stream {
         Stream {
             thread: Some(thread),
             inner,
+            duplex_input: None,
+            duplex_linked: false,
             trigger: tx,
         }
     }
 
     fn new_output<D, E>(
         inner: Arc<StreamInner>,
+        thread_options: &ThreadOptions,
         mut data_callback: D,
         mut error_callback: E,
         timeout: Option<Duration>,
@@ -960,9 +1946,11 @@ impl Stream {
         let (tx, rx) = trigger();
         // Clone the handle for passing into worker thread.
         let stream = inner.clone();
-        let thread = thread::Builder::new()
-            .name("cpal_alsa_out".to_owned())
+        let thread_options = thread_options.clone();
+        let thread = thread_options
+            .builder("out")
             .spawn(move || {
+                thread_options.apply(&mut |err| error_callback(err.into()));
                 output_stream_worker(
                     rx,
                     &stream,
@@ -975,6 +1963,51 @@ impl Stream {
         Stream {
             thread: Some(thread),
             inner,
+            duplex_input: None,
+            duplex_linked: false,
+            trigger: tx,
+        }
+    }
+}
+
+impl Stream {
+    fn new_duplex<D, E>(
+        input: Arc<StreamInner>,
+        output: Arc<StreamInner>,
+        linked: bool,
+        thread_options: &ThreadOptions,
+        mut data_callback: D,
+        mut error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Stream
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let (tx, rx) = trigger();
+        // Clone the handles for passing into worker thread.
+        let input_stream = input.clone();
+        let output_stream = output.clone();
+        let thread_options = thread_options.clone();
+        let thread = thread_options
+            .builder("duplex")
+            .spawn(move || {
+                thread_options.apply(&mut |err| error_callback(err.into()));
+                duplex_stream_worker(
+                    rx,
+                    &input_stream,
+                    &output_stream,
+                    &mut data_callback,
+                    &mut error_callback,
+                    timeout,
+                );
+            })
+            .unwrap();
+        Stream {
+            thread: Some(thread),
+            inner: output,
+            duplex_input: Some(input),
+            duplex_linked: linked,
             trigger: tx,
         }
     }
@@ -988,35 +2021,231 @@ impl Drop for Stream {
 }
 
 impl StreamTrait for Stream {
+    /// Streams run from when they are built, so this only resumes paused streams.
     fn play(&self) -> Result<(), PlayStreamError> {
+        if let Some(input) = &self.duplex_input {
+            input.channel.pause(false).ok();
+        }
         self.inner.channel.pause(false).ok();
         Ok(())
     }
     fn pause(&self) -> Result<(), PauseStreamError> {
         self.inner.channel.pause(true).ok();
+        if let Some(input) = &self.duplex_input {
+            input.channel.pause(true).ok();
+        }
+        Ok(())
+    }
+    fn latency(&self) -> Result<Duration, StreamLatencyError> {
+        let mut latency = self.inner.latency()?;
+        if let Some(input) = &self.duplex_input {
+            latency += input.latency()?;
+        }
+        Ok(latency)
+    }
+
+    /// The size of the ring buffer of the device. Callbacks receive all frames available in it,
+    /// so up to a full buffer when the stream thread wakes up late.
+    fn buffer_size(&self) -> Result<StreamBufferSize, StreamBufferSizeError> {
+        let max_callback_frames = match &self.duplex_input {
+            Some(input) => cmp::min(input.buffer_frames, self.inner.buffer_frames),
+            None => self.inner.buffer_frames,
+        };
+        // The period size and count of duplex streams are those of the output device.
+        Ok(StreamBufferSize {
+            buffer_frames: self.inner.buffer_frames as FrameCount,
+            period_frames: self.inner.period_frames as FrameCount,
+            max_callback_frames: max_callback_frames as FrameCount,
+        })
+    }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.inner.position.frames())
+    }
+
+    /// Late wakeups are counted when less than half a period of the ring buffer was left to
+    /// process before an xrun. Callbacks time the data callback only, not reading from or writing
+    /// to the device.
+    fn enable_stats(&self) -> Result<(), StreamStatsError> {
+        self.inner.stats.enable();
+        if let Some(input) = &self.duplex_input {
+            input.stats.enable();
+        }
         Ok(())
     }
+
+    fn stats(&self) -> Result<StreamStats, StreamStatsError> {
+        let mut stats = self.inner.stats.snapshot()?;
+        if let Some(input) = &self.duplex_input {
+            let input = input.stats.snapshot()?;
+            stats.xruns += input.xruns;
+            stats.late_wakeups += input.late_wakeups;
+        }
+        Ok(stats)
+    }
+
+    /// Streams that are paused are resumed together, other streams are restarted together, by
+    /// linking their PCMs. Restarting drops the frames captured and written so far, and outputs
+    /// start with all but a period of silence. This requires PCMs that support linking, e.g.
+    /// devices of the same card opened without the `dmix`/`dsnoop` plugins or a sound server. At
+    /// most one duplex stream can be linked with the others.
+    fn play_all(streams: &[&Self]) -> Result<bool, PlayStreamError> {
+        if streams.len() <= 1 {
+            return crate::group::play_each(streams);
+        }
+        if trigger_linked(streams, alsa::pcm::State::Paused, |pcm| pcm.pause(false))? {
+            return Ok(true);
+        }
+        Ok(with_workers_held(streams, || restart_streams(streams))?)
+    }
+
+    fn pause_all(streams: &[&Self]) -> Result<bool, PauseStreamError> {
+        // Like `pause`, this has no effect on devices that cannot pause.
+        let paused = streams.iter().all(|stream| stream.inner.can_pause)
+            && trigger_linked(streams, alsa::pcm::State::Running, |pcm| pcm.pause(true))?;
+        for stream in streams {
+            stream.pause()?;
+        }
+        Ok(paused || streams.len() <= 1)
+    }
+}
+
+// Run `f` while the workers of the given streams wait, so that it can operate on their PCMs.
+fn with_workers_held<T>(streams: &[&Stream], f: impl FnOnce() -> T) -> T {
+    for stream in streams {
+        stream.inner.hold.request();
+        stream.trigger.send(TRIGGER_HOLD);
+    }
+    for stream in streams {
+        stream.inner.hold.wait_until_held();
+    }
+    let result = f();
+    for stream in streams {
+        stream.inner.hold.release();
+    }
+    result
+}
+
+// Restart the given streams, whose workers are held, at the same moment if their PCMs can be
+// linked, and one after the other otherwise. Like when a duplex stream recovers from an xrun, the
+// frames captured and written before are dropped and outputs start with silence.
+//
+// Returns whether the streams were started together.
+fn restart_streams(streams: &[&Stream]) -> Result<bool, BackendSpecificError> {
+    let mut buffer = Vec::new();
+    for stream in streams {
+        for inner in stream.duplex_input.iter().chain([&stream.inner]) {
+            inner.channel.drop()?;
+            inner.channel.prepare()?;
+            inner.position.mark_discontinuity();
+        }
+        if stream.inner.stream_type == alsa::Direction::Playback {
+            // Keep the output from starting on its own once filled, before the others.
+            if stream.inner.auto_start {
+                set_auto_start(&stream.inner, false)?;
+            }
+            prefill_output(&stream.inner, &mut buffer)?;
+        }
+    }
+    let started = trigger_linked(streams, alsa::pcm::State::Prepared, |pcm| pcm.start())?;
+    for stream in streams {
+        match &stream.duplex_input {
+            Some(input) if !started => start_duplex(input, &stream.inner)?,
+            None if !started => stream.inner.channel.start()?,
+            _ => (),
+        }
+        if stream.inner.stream_type == alsa::Direction::Playback && stream.inner.auto_start {
+            set_auto_start(&stream.inner, true)?;
+        }
+    }
+    Ok(started)
+}
+
+// Link the PCMs of the given streams, which must all be in `state`, and run `trigger` on the
+// first one, which ALSA then applies to all of them at once. The PCMs are unlinked afterwards, so
+// that each stream recovers from xruns on its own.
+//
+// The two halves of a duplex stream are linked to each other already, which ALSA extends to the
+// PCMs linked to either of them. A duplex stream is therefore the one the others are linked to.
+//
+// Returns `false` without running `trigger` if the streams cannot be linked.
+fn trigger_linked(
+    streams: &[&Stream],
+    state: alsa::pcm::State,
+    trigger: impl FnOnce(&alsa::PCM) -> alsa::Result<()>,
+) -> Result<bool, BackendSpecificError> {
+    let mut streams = streams.to_vec();
+    streams.sort_by_key(|stream| stream.duplex_input.is_none());
+    let Some((first, others)) = streams.split_first() else {
+        return Ok(false);
+    };
+    let linkable = (first.duplex_input.is_none() || first.duplex_linked)
+        && others.iter().all(|stream| stream.duplex_input.is_none())
+        && streams
+            .iter()
+            .all(|stream| stream.inner.channel.state() == state);
+    if others.is_empty() || !linkable {
+        return Ok(false);
+    }
+    let linked = others
+        .iter()
+        .take_while(|other| first.inner.channel.link(&other.inner.channel).is_ok())
+        .count();
+    let result = if linked == others.len() {
+        trigger(&first.inner.channel).map(|()| true)
+    } else {
+        Ok(false)
+    };
+    for other in &others[..linked] {
+        other.inner.channel.unlink().ok();
+    }
+    Ok(result?)
+}
+
+// Let an output start on its own once its worker has filled all but a period of the buffer, or
+// keep it from starting until started explicitly.
+fn set_auto_start(output: &StreamInner, auto_start: bool) -> alsa::Result<()> {
+    let sw_params = output.channel.sw_params_current()?;
+    let start_threshold = if auto_start {
+        prefill_frames(output) as alsa::pcm::Frames
+    } else {
+        sw_params.get_boundary()?
+    };
+    sw_params.set_start_threshold(start_threshold)?;
+    output.channel.sw_params(&sw_params)
 }
 
 fn set_hw_params_from_format(
     pcm_handle: &alsa::pcm::PCM,
     config: &StreamConfig,
     sample_format: SampleFormat,
-) -> Result<bool, BackendSpecificError> {
+    mmap: bool,
+    options: &DeviceOptions,
+) -> Result<(bool, bool, bool), BuildStreamError> {
     let hw_params = alsa::pcm::HwParams::any(pcm_handle)?;
-    hw_params.set_access(alsa::pcm::Access::RWInterleaved)?;
+
+    // 24-bit samples are converted in an intermediate buffer, so they cannot be handed out
+    // straight from the ring buffer.
+    let mmap = mmap && !matches!(sample_format, SampleFormat::I24 | SampleFormat::U24);
+    let mmap = mmap
+        && hw_params
+            .set_access(alsa::pcm::Access::MMapInterleaved)
+            .is_ok();
+    if !mmap {
+        hw_params.set_access(alsa::pcm::Access::RWInterleaved)?;
+    }
 
     let sample_format = if cfg!(target_endian = "big") {
         match sample_format {
             SampleFormat::I8 => alsa::pcm::Format::S8,
             SampleFormat::I16 => alsa::pcm::Format::S16BE,
-            // SampleFormat::I24 => alsa::pcm::Format::S24BE,
+            SampleFormat::I24 => alsa::pcm::Format::S24BE,
             SampleFormat::I32 => alsa::pcm::Format::S32BE,
             // SampleFormat::I48 => alsa::pcm::Format::S48BE,
             // SampleFormat::I64 => alsa::pcm::Format::S64BE,
             SampleFormat::U8 => alsa::pcm::Format::U8,
             SampleFormat::U16 => alsa::pcm::Format::U16BE,
-            // SampleFormat::U24 => alsa::pcm::Format::U24BE,
+            SampleFormat::U24 => alsa::pcm::Format::U24BE,
             SampleFormat::U32 => alsa::pcm::Format::U32BE,
             // SampleFormat::U48 => alsa::pcm::Format::U48BE,
             // SampleFormat::U64 => alsa::pcm::Format::U64BE,
@@ -1028,20 +2257,21 @@ fn set_hw_params_from_format(
                         "Sample format '{}' is not supported by this backend",
                         sample_format
                     ),
-                })
+                }
+                .into())
             }
         }
     } else {
         match sample_format {
             SampleFormat::I8 => alsa::pcm::Format::S8,
             SampleFormat::I16 => alsa::pcm::Format::S16LE,
-            // SampleFormat::I24 => alsa::pcm::Format::S24LE,
+            SampleFormat::I24 => alsa::pcm::Format::S24LE,
             SampleFormat::I32 => alsa::pcm::Format::S32LE,
             // SampleFormat::I48 => alsa::pcm::Format::S48LE,
             // SampleFormat::I64 => alsa::pcm::Format::S64LE,
             SampleFormat::U8 => alsa::pcm::Format::U8,
             SampleFormat::U16 => alsa::pcm::Format::U16LE,
-            // SampleFormat::U24 => alsa::pcm::Format::U24LE,
+            SampleFormat::U24 => alsa::pcm::Format::U24LE,
             SampleFormat::U32 => alsa::pcm::Format::U32LE,
             // SampleFormat::U48 => alsa::pcm::Format::U48LE,
             // SampleFormat::U64 => alsa::pcm::Format::U64LE,
@@ -1053,37 +2283,81 @@ fn set_hw_params_from_format(
                         "Sample format '{}' is not supported by this backend",
                         sample_format
                     ),
-                })
+                }
+                .into())
             }
         }
     };
 
+    // Many devices only support 24-bit samples packed into 3 bytes, fall back to those if the
+    // 4-byte layout is not available.
+    let packed_format = match sample_format {
+        alsa::pcm::Format::S24LE => Some(alsa::pcm::Format::S243LE),
+        alsa::pcm::Format::S24BE => Some(alsa::pcm::Format::S243BE),
+        alsa::pcm::Format::U24LE => Some(alsa::pcm::Format::U243LE),
+        alsa::pcm::Format::U24BE => Some(alsa::pcm::Format::U243BE),
+        _ => None,
+    };
+    let (sample_format, packed) = match packed_format {
+        Some(packed_format)
+            if hw_params.test_format(sample_format).is_err()
+                && hw_params.test_format(packed_format).is_ok() =>
+        {
+            (packed_format, true)
+        }
+        _ => (sample_format, false),
+    };
+
     hw_params.set_format(sample_format)?;
     hw_params.set_rate(config.sample_rate.0, alsa::ValueOr::Nearest)?;
     hw_params.set_channels(config.channels as u32)?;
 
+    // Checked on a copy, so that the period count is only set if the buffer size is not fixed.
+    let periods = options.periods;
+    if periods == 0
+        || hw_params
+            .clone()
+            .set_periods(periods, alsa::ValueOr::Nearest)
+            .is_err()
+    {
+        return Err(BuildStreamError::StreamConfigNotSupported);
+    }
     match config.buffer_size {
         BufferSize::Fixed(v) => {
-            hw_params.set_period_size_near((v / 4) as alsa::pcm::Frames, alsa::ValueOr::Nearest)?;
+            let period_size = options.period_size.unwrap_or(v / periods);
+            hw_params
+                .set_period_size_near(period_size as alsa::pcm::Frames, alsa::ValueOr::Nearest)?;
             hw_params.set_buffer_size(v as alsa::pcm::Frames)?;
         }
         BufferSize::Default => {
-            // These values together represent a moderate latency and wakeup interval.
-            // Without them, we are at the mercy of the device
-            hw_params.set_period_time_near(25_000, alsa::ValueOr::Nearest)?;
-            hw_params.set_buffer_time_near(100_000, alsa::ValueOr::Nearest)?;
+            match options.period_size {
+                Some(period_size) => {
+                    hw_params.set_period_size_near(
+                        period_size as alsa::pcm::Frames,
+                        alsa::ValueOr::Nearest,
+                    )?;
+                }
+                // A moderate latency and wakeup interval. Without it, we are at the mercy of the
+                // device.
+                None => {
+                    hw_params.set_period_time_near(25_000, alsa::ValueOr::Nearest)?;
+                }
+            }
+            let period_size = hw_params.get_period_size()?;
+            hw_params.set_buffer_size_near(period_size * periods as alsa::pcm::Frames)?;
         }
     }
 
     pcm_handle.hw_params(&hw_params)?;
 
-    Ok(hw_params.can_pause())
+    Ok((hw_params.can_pause(), packed, mmap))
 }
 
 fn set_sw_params_from_format(
     pcm_handle: &alsa::pcm::PCM,
     config: &StreamConfig,
     stream_type: alsa::Direction,
+    auto_start: bool,
 ) -> Result<usize, BackendSpecificError> {
     let sw_params = pcm_handle.sw_params_current()?;
 
@@ -1096,16 +2370,13 @@ fn set_sw_params_from_format(
         }
         sw_params.set_avail_min(period as alsa::pcm::Frames)?;
 
+        // Outputs start on their own once all but a period of the buffer is filled. Inputs are
+        // started when built, and the two halves of duplex streams together, explicitly.
         let start_threshold = match stream_type {
-            alsa::Direction::Playback => buffer - period,
-
-            // For capture streams, the start threshold is irrelevant and ignored,
-            // because build_stream_inner() starts the stream before process_input()
-            // reads from it. Set it anyway I guess, since it's better than leaving
-            // it at an unspecified default value.
-            alsa::Direction::Capture => 1,
+            alsa::Direction::Playback if auto_start => (buffer - period).try_into().unwrap(),
+            _ => sw_params.get_boundary()?,
         };
-        sw_params.set_start_threshold(start_threshold.try_into().unwrap())?;
+        sw_params.set_start_threshold(start_threshold)?;
 
         period as usize * config.channels as usize
     };
@@ -1139,6 +2410,13 @@ impl From<alsa::Error> for BuildStreamError {
     }
 }
 
+impl From<alsa::Error> for DeviceNameError {
+    fn from(err: alsa::Error) -> Self {
+        let err: BackendSpecificError = err.into();
+        err.into()
+    }
+}
+
 impl From<alsa::Error> for SupportedStreamConfigsError {
     fn from(err: alsa::Error) -> Self {
         let err: BackendSpecificError = err.into();
@@ -1166,3 +2444,73 @@ impl From<alsa::Error> for StreamError {
         err.into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_24, pack_24, unpack_24, upgrade_pcm_id};
+
+    // Packed little-endian 24-bit samples, as in `S24_3LE` and `U24_3LE`.
+    const PACKED: [u8; 9] = [0x01, 0x02, 0x03, 0xff, 0xff, 0xff, 0x00, 0x00, 0x80];
+
+    #[test]
+    fn test_normalize_24() {
+        assert_eq!(normalize_24(0x00ff_ffff, true), -1);
+        assert_eq!(normalize_24(0x0080_0000, true), -0x80_0000);
+        assert_eq!(normalize_24(0x007f_ffff, true), 0x7f_ffff);
+        assert_eq!(normalize_24(-1, false), 0xff_ffff);
+        assert_eq!(normalize_24(0x1280_0000, false), 0x80_0000);
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn test_unpack_24() {
+        let mut buffer = [0; 12];
+        buffer[..9].copy_from_slice(&PACKED);
+        unpack_24(&mut buffer, true);
+        assert_eq!(
+            buffer,
+            [0x01, 0x02, 0x03, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x80, 0xff]
+        );
+
+        let mut buffer = [0; 12];
+        buffer[..9].copy_from_slice(&PACKED);
+        unpack_24(&mut buffer, false);
+        assert_eq!(
+            buffer,
+            [0x01, 0x02, 0x03, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x80, 0x00]
+        );
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn test_pack_24() {
+        let mut buffer = [0; 12];
+        for (i, value) in [0x03_0201, -1, -0x80_0000].iter().enumerate() {
+            buffer[i * 4..i * 4 + 4].copy_from_slice(&i32::to_ne_bytes(*value));
+        }
+        pack_24(&mut buffer);
+        assert_eq!(buffer[..9], PACKED);
+
+        // Unsigned samples pack to the same bytes.
+        for (i, value) in [0x03_0201, 0xff_ffff, 0x80_0000].iter().enumerate() {
+            buffer[i * 4..i * 4 + 4].copy_from_slice(&i32::to_ne_bytes(*value));
+        }
+        pack_24(&mut buffer);
+        assert_eq!(buffer[..9], PACKED);
+    }
+
+    #[test]
+    fn test_upgrade_pcm_id() {
+        assert_eq!(upgrade_pcm_id("plughw:CARD=PCH"), "plughw:CARD=PCH,DEV=0");
+        assert_eq!(upgrade_pcm_id("hw:CARD=U192k"), "hw:CARD=U192k,DEV=0");
+        for pcm_id in [
+            "plughw:CARD=PCH,DEV=3",
+            "hw:CARD=PCH,DEV=0,SUBDEV=1",
+            "default",
+            "dmix:CARD=PCH",
+            "pulse",
+        ] {
+            assert_eq!(upgrade_pcm_id(pcm_id), pcm_id);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Naming, scheduling and CPU affinity of the threads running the stream callbacks.
+
+use crate::BackendSpecificError;
+use std::io;
+use std::thread;
+
+/// The scheduling policy of the threads running the data callbacks of ALSA streams.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ThreadScheduling {
+    /// The regular time-sharing policy of the system.
+    #[default]
+    Normal,
+    /// `SCHED_FIFO` with the given priority, usually within `1..=99`.
+    Fifo(i32),
+    /// `SCHED_RR` with the given priority, usually within `1..=99`.
+    RoundRobin(i32),
+}
+
+impl ThreadScheduling {
+    // The policy and priority to pass to `pthread_setschedparam`, or `None` for `Normal`.
+    fn policy(self) -> Option<(libc::c_int, i32)> {
+        match self {
+            ThreadScheduling::Normal => None,
+            ThreadScheduling::Fifo(priority) => Some((libc::SCHED_FIFO, priority)),
+            ThreadScheduling::RoundRobin(priority) => Some((libc::SCHED_RR, priority)),
+        }
+    }
+}
+
+// Check that the priority of `scheduling` is within the range supported by its policy.
+pub(super) fn check_scheduling(scheduling: ThreadScheduling) -> Result<(), BackendSpecificError> {
+    let Some((policy, priority)) = scheduling.policy() else {
+        return Ok(());
+    };
+    let (min, max) = unsafe {
+        (
+            libc::sched_get_priority_min(policy),
+            libc::sched_get_priority_max(policy),
+        )
+    };
+    if min < 0 || max < 0 {
+        return Err(BackendSpecificError {
+            description: format!(
+                "failed to query the priority range of the scheduling policy: {}",
+                io::Error::last_os_error()
+            ),
+        });
+    }
+    if !(min..=max).contains(&priority) {
+        return Err(BackendSpecificError {
+            description: format!(
+                "the priority {} is outside of the supported range {}..={}",
+                priority, min, max
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Check that every CPU index can be added to a CPU set.
+#[cfg(target_os = "linux")]
+pub(super) fn check_affinity(cpus: &[usize]) -> Result<(), BackendSpecificError> {
+    let max = libc::CPU_SETSIZE as usize;
+    match cpus.iter().find(|&&cpu| cpu >= max) {
+        Some(cpu) => Err(BackendSpecificError {
+            description: format!("the CPU index {} is not below {}", cpu, max),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn check_affinity(cpus: &[usize]) -> Result<(), BackendSpecificError> {
+    if cpus.is_empty() {
+        return Ok(());
+    }
+    Err(BackendSpecificError {
+        description: "setting the CPU affinity of stream threads is only supported on Linux"
+            .to_string(),
+    })
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct ThreadOptions {
+    pub name: String,
+    pub scheduling: ThreadScheduling,
+    // The CPUs the threads may run on. All CPUs if empty.
+    pub affinity: Vec<usize>,
+}
+
+impl Default for ThreadOptions {
+    fn default() -> Self {
+        ThreadOptions {
+            name: "cpal_alsa".to_owned(),
+            scheduling: ThreadScheduling::Normal,
+            affinity: Vec::new(),
+        }
+    }
+}
+
+impl ThreadOptions {
+    // A builder for a thread whose name ends in `suffix`.
+    pub fn builder(&self, suffix: &str) -> thread::Builder {
+        thread::Builder::new().name(format!("{}_{}", self.name, suffix))
+    }
+
+    // Apply the CPU affinity and scheduling policy to the calling thread.
+    //
+    // These require privileges or platform support that may be missing, so failures are only
+    // reported and the thread keeps running with its current settings.
+    pub fn apply(&self, report: &mut dyn FnMut(BackendSpecificError)) {
+        if !self.affinity.is_empty() {
+            if let Err(err) = set_affinity(&self.affinity) {
+                report(err);
+            }
+        }
+        if let Err(err) = set_scheduling(self.scheduling) {
+            report(err);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(cpus: &[usize]) -> Result<(), BackendSpecificError> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(BackendSpecificError {
+                description: format!(
+                    "failed to set the CPU affinity of the stream thread: {}",
+                    io::Error::last_os_error()
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_cpus: &[usize]) -> Result<(), BackendSpecificError> {
+    Err(BackendSpecificError {
+        description: "setting the CPU affinity of stream threads is only supported on Linux"
+            .to_string(),
+    })
+}
+
+fn set_scheduling(scheduling: ThreadScheduling) -> Result<(), BackendSpecificError> {
+    let Some((policy, priority)) = scheduling.policy() else {
+        return Ok(());
+    };
+    let res = unsafe {
+        let mut param: libc::sched_param = std::mem::zeroed();
+        param.sched_priority = priority;
+        libc::pthread_setschedparam(libc::pthread_self(), policy, &param)
+    };
+    if res != 0 {
+        return Err(BackendSpecificError {
+            description: format!(
+                "failed to enable real-time scheduling of the stream thread, \
+                 running at normal priority instead: {}",
+                io::Error::from_raw_os_error(res)
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_affinity, check_scheduling, ThreadScheduling};
+
+    #[test]
+    fn test_check_scheduling() {
+        assert!(check_scheduling(ThreadScheduling::Normal).is_ok());
+        assert!(check_scheduling(ThreadScheduling::Fifo(1)).is_ok());
+        assert!(check_scheduling(ThreadScheduling::RoundRobin(1)).is_ok());
+        assert!(check_scheduling(ThreadScheduling::Fifo(-1)).is_err());
+        assert!(check_scheduling(ThreadScheduling::RoundRobin(i32::MAX)).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_check_affinity() {
+        assert!(check_affinity(&[]).is_ok());
+        assert!(check_affinity(&[0, 1]).is_ok());
+        assert!(check_affinity(&[0, libc::CPU_SETSIZE as usize]).is_err());
+        assert!(check_affinity(&[usize::MAX]).is_err());
+    }
+}
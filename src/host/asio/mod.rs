@@ -4,7 +4,8 @@ use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
     BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError, DevicesError,
     InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleFormat,
-    StreamConfig, StreamError, SupportedStreamConfig, SupportedStreamConfigsError,
+    SampleLayout, StreamConfig, StreamError, StreamPositionError, SupportedStreamConfig,
+    SupportedStreamConfigsError,
 };
 
 pub use self::device::{Device, Devices, SupportedInputConfigs, SupportedOutputConfigs};
@@ -94,6 +95,16 @@ impl DeviceTrait for Device {
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_input_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         Device::build_input_stream_raw(
             self,
             config,
@@ -116,6 +127,16 @@ impl DeviceTrait for Device {
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_output_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         Device::build_output_stream_raw(
             self,
             config,
@@ -135,4 +156,8 @@ impl StreamTrait for Stream {
     fn pause(&self) -> Result<(), PauseStreamError> {
         Stream::pause(self)
     }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Stream::position(self)
+    }
 }
@@ -3,9 +3,11 @@ extern crate num_traits;
 
 use self::num_traits::PrimInt;
 use super::Device;
+use crate::position::PositionCounter;
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, Data, InputCallbackInfo,
-    OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleFormat, StreamConfig, StreamError,
+    frames_to_duration, BackendSpecificError, BufferSize, BuildStreamError, Data,
+    InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleFormat,
+    StreamConfig, StreamError, StreamPositionError,
 };
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -18,6 +20,8 @@ pub struct Stream {
     #[allow(dead_code)]
     asio_streams: Arc<Mutex<sys::AsioStreams>>,
     callback_id: sys::CallbackId,
+    // The number of frames passed to or from the data callback.
+    position: Arc<PositionCounter>,
 }
 
 impl Stream {
@@ -30,6 +34,10 @@ impl Stream {
         self.playing.store(false, Ordering::SeqCst);
         Ok(())
     }
+
+    pub fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.position.frames())
+    }
 }
 
 impl Device {
@@ -65,6 +73,9 @@ impl Device {
         let stream_playing = Arc::new(AtomicBool::new(false));
         let playing = Arc::clone(&stream_playing);
         let asio_streams = self.asio_streams.clone();
+        let position = Arc::new(PositionCounter::default());
+        let stream_position = position.clone();
+        let mut next_sample_position = None;
 
         // Set the input callback.
         // This is most performance critical part of the ASIO bindings.
@@ -81,6 +92,12 @@ impl Device {
                 Some(ref asio_stream) => asio_stream,
                 None => return,
             };
+            track_sample_position(
+                callback_info,
+                asio_stream.buffer_size as usize,
+                &mut next_sample_position,
+                &position,
+            );
 
             /// 1. Write from the ASIO buffer to the interleaved CPAL buffer.
             /// 2. Deliver the CPAL buffer to the user callback.
@@ -89,6 +106,7 @@ impl Device {
                 interleaved: &mut [u8],
                 asio_stream: &sys::AsioStream,
                 asio_info: &sys::CallbackInfo,
+                position: &PositionCounter,
                 sample_rate: crate::SampleRate,
                 format: SampleFormat,
                 from_endianness: F,
@@ -119,7 +137,10 @@ impl Device {
                     .sub(delay)
                     .expect("`capture` occurs before origin of alsa `StreamInstant`");
                 let timestamp = crate::InputStreamTimestamp { callback, capture };
-                let info = InputCallbackInfo { timestamp };
+                let info = InputCallbackInfo {
+                    timestamp,
+                    position: position.advance(n_frames),
+                };
                 data_callback(&data, &info);
             }
 
@@ -130,6 +151,7 @@ impl Device {
                         &mut interleaved,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::I16,
                         from_le,
@@ -141,6 +163,7 @@ impl Device {
                         &mut interleaved,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::I16,
                         from_be,
@@ -153,6 +176,7 @@ impl Device {
                         &mut interleaved,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::F32,
                         from_le,
@@ -164,6 +188,7 @@ impl Device {
                         &mut interleaved,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::F32,
                         from_be,
@@ -176,6 +201,7 @@ impl Device {
                         &mut interleaved,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::I32,
                         from_le,
@@ -187,6 +213,7 @@ impl Device {
                         &mut interleaved,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::I32,
                         from_be,
@@ -199,6 +226,7 @@ impl Device {
                         &mut interleaved,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::F64,
                         from_le,
@@ -210,6 +238,7 @@ impl Device {
                         &mut interleaved,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::F64,
                         from_be,
@@ -235,6 +264,7 @@ impl Device {
             driver,
             asio_streams,
             callback_id,
+            position: stream_position,
         })
     }
 
@@ -271,6 +301,9 @@ impl Device {
         let stream_playing = Arc::new(AtomicBool::new(false));
         let playing = Arc::clone(&stream_playing);
         let asio_streams = self.asio_streams.clone();
+        let position = Arc::new(PositionCounter::default());
+        let stream_position = position.clone();
+        let mut next_sample_position = None;
 
         let config = config.clone();
         let callback_id = self.driver.add_callback(move |callback_info| unsafe {
@@ -285,6 +318,12 @@ impl Device {
                 Some(ref mut asio_stream) => asio_stream,
                 None => return,
             };
+            track_sample_position(
+                callback_info,
+                asio_stream.buffer_size as usize,
+                &mut next_sample_position,
+                &position,
+            );
 
             // Silence the ASIO buffer that is about to be used.
             //
@@ -307,6 +346,7 @@ impl Device {
                 silence_asio_buffer: bool,
                 asio_stream: &mut sys::AsioStream,
                 asio_info: &sys::CallbackInfo,
+                position: &PositionCounter,
                 sample_rate: crate::SampleRate,
                 format: SampleFormat,
                 mix_samples: F,
@@ -327,7 +367,10 @@ impl Device {
                     .add(delay)
                     .expect("`playback` occurs beyond representation supported by `StreamInstant`");
                 let timestamp = crate::OutputStreamTimestamp { callback, playback };
-                let info = OutputCallbackInfo { timestamp };
+                let info = OutputCallbackInfo {
+                    timestamp,
+                    position: position.advance(n_frames),
+                };
                 data_callback(&mut data, &info);
 
                 // 2. Silence ASIO channels if necessary.
@@ -359,6 +402,7 @@ impl Device {
                         silence,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::I16,
                         |old_sample, new_sample| {
@@ -373,6 +417,7 @@ impl Device {
                         silence,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::I16,
                         |old_sample, new_sample| {
@@ -387,6 +432,7 @@ impl Device {
                         silence,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::F32,
                         |old_sample, new_sample| {
@@ -404,6 +450,7 @@ impl Device {
                         silence,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::F32,
                         |old_sample, new_sample| {
@@ -421,6 +468,7 @@ impl Device {
                         silence,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::I32,
                         |old_sample, new_sample| {
@@ -435,6 +483,7 @@ impl Device {
                         silence,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::I32,
                         |old_sample, new_sample| {
@@ -450,6 +499,7 @@ impl Device {
                         silence,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::F64,
                         |old_sample, new_sample| {
@@ -467,6 +517,7 @@ impl Device {
                         silence,
                         asio_stream,
                         callback_info,
+                        &position,
                         config.sample_rate,
                         SampleFormat::F64,
                         |old_sample, new_sample| {
@@ -496,6 +547,7 @@ impl Device {
             driver,
             asio_streams,
             callback_id,
+            position: stream_position,
         })
     }
 
@@ -607,22 +659,37 @@ fn asio_ns_to_double(val: sys::bindings::asio_import::ASIOTimeStamp) -> f64 {
     val.lo as f64 + val.hi as f64 * two_raised_to_32
 }
 
+/// Flag the buffer of a callback as discontinuous if its sample position skips frames, e.g. after
+/// an overload of the driver or while the stream was paused. `next_sample_position` holds the
+/// sample position expected for the callback, and is updated to the one expected for the next
+/// callback.
+fn track_sample_position(
+    callback_info: &sys::CallbackInfo,
+    n_frames: usize,
+    next_sample_position: &mut Option<i64>,
+    position: &PositionCounter,
+) {
+    let samples = callback_info.sample_position;
+    let sample_position = ((samples.hi as i64) << 32) | samples.lo as i64;
+    if next_sample_position.is_some_and(|expected| expected != sample_position) {
+        position.mark_discontinuity();
+    }
+    *next_sample_position = Some(sample_position + n_frames as i64);
+}
+
 /// Asio retrieves system time via `timeGetTime` which returns the time in milliseconds.
 fn system_time_to_stream_instant(
     system_time: sys::bindings::asio_import::ASIOTimeStamp,
 ) -> crate::StreamInstant {
-    let systime_ns = asio_ns_to_double(system_time);
-    let secs = systime_ns as i64 / 1_000_000_000;
-    let nanos = (systime_ns as i64 - secs * 1_000_000_000) as u32;
-    crate::StreamInstant::new(secs, nanos)
-}
-
-/// Convert the given duration in frames at the given sample rate to a `std::time::Duration`.
-fn frames_to_duration(frames: usize, rate: crate::SampleRate) -> std::time::Duration {
-    let secsf = frames as f64 / rate.0 as f64;
-    let secs = secsf as u64;
-    let nanos = ((secsf - secs as f64) * 1_000_000_000.0) as u32;
-    std::time::Duration::new(secs, nanos)
+    let systime_ns = asio_ns_to_double(system_time) as i128;
+    // Map the time onto the epoch of `StreamInstant` via the current time of the same clock. The
+    // millisecond counter wraps around every 49.7 days, so only the difference is used.
+    let now_ms = unsafe { windows::Win32::Media::timeGetTime() };
+    let age_ms = now_ms.wrapping_sub((systime_ns / 1_000_000) as u32);
+    let now_ns = systime_ns - systime_ns % 1_000_000 + age_ms as i128 * 1_000_000;
+    crate::StreamInstant::from_clock_nanos(systime_ns, now_ns)
+        // Only out of range for a bogus timestamp, which must not panic the audio thread.
+        .unwrap_or_else(crate::StreamInstant::now)
 }
 
 /// Check whether or not the desired config is supported by the stream.
@@ -638,6 +705,7 @@ fn check_config(
         channels,
         sample_rate,
         buffer_size: _,
+        layout: _,
     } = config;
     // Try and set the sample rate to what the user selected.
     let sample_rate = sample_rate.0.into();
@@ -11,6 +11,7 @@ extern crate core_foundation_sys;
 extern crate coreaudio;
 
 use std::cell::RefCell;
+use std::sync::Arc;
 
 use self::coreaudio::audio_unit::render_callback::data;
 use self::coreaudio::audio_unit::{render_callback, AudioUnit, Element, Scope};
@@ -19,14 +20,16 @@ use self::coreaudio::sys::{
     AudioStreamBasicDescription,
 };
 
-use super::{asbd_from_config, frames_to_duration, host_time_to_stream_instant};
+use super::{asbd_from_config, host_time_to_stream_instant, track_sample_time};
+use crate::position::PositionCounter;
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
-    DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError, SupportedBufferSize,
-    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError,
+    frames_to_duration, BackendSpecificError, BufferSize, BuildStreamError, Data,
+    DefaultStreamConfigError, DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo,
+    PauseStreamError, PlayStreamError, SampleFormat, SampleLayout, SampleRate, StreamConfig,
+    StreamError, StreamPositionError, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 
 use self::enumerate::{
@@ -175,12 +178,22 @@ impl DeviceTrait for Device {
         sample_format: SampleFormat,
         mut data_callback: D,
         mut error_callback: E,
-        _timeout: Option<Duration>,
+        timeout: Option<Duration>,
     ) -> Result<Self::Stream, BuildStreamError>
     where
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_input_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         // The scope and element for working with a device's input stream.
         let scope = Scope::Output;
         let element = Element::Input;
@@ -206,6 +219,9 @@ impl DeviceTrait for Device {
         // fed to the audio buffer.
         let bytes_per_channel = sample_format.sample_size();
         let sample_rate = config.sample_rate;
+        let position = Arc::new(PositionCounter::default());
+        let callback_position = position.clone();
+        let mut next_sample_time = None;
         type Args = render_callback::Args<data::Raw>;
         audio_unit.set_input_callback(move |args: Args| unsafe {
             let ptr = (*args.data.data).mBuffers.as_ptr() as *const AudioBuffer;
@@ -238,17 +254,29 @@ impl DeviceTrait for Device {
                 .expect("`capture` occurs before origin of alsa `StreamInstant`");
             let timestamp = crate::InputStreamTimestamp { callback, capture };
 
-            let info = InputCallbackInfo { timestamp };
+            track_sample_time(
+                &args.time_stamp,
+                buffer_frames,
+                &mut next_sample_time,
+                &callback_position,
+            );
+            let info = InputCallbackInfo {
+                timestamp,
+                position: callback_position.advance(buffer_frames),
+            };
             data_callback(&data, &info);
             Ok(())
         })?;
 
         audio_unit.start()?;
 
-        Ok(Stream::new(StreamInner {
-            playing: true,
-            audio_unit,
-        }))
+        Ok(Stream::new(
+            StreamInner {
+                playing: true,
+                audio_unit,
+            },
+            position,
+        ))
     }
 
     /// Create an output stream.
@@ -258,12 +286,22 @@ impl DeviceTrait for Device {
         sample_format: SampleFormat,
         mut data_callback: D,
         mut error_callback: E,
-        _timeout: Option<Duration>,
+        timeout: Option<Duration>,
     ) -> Result<Self::Stream, BuildStreamError>
     where
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_output_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         match config.buffer_size {
             BufferSize::Fixed(_) => {
                 return Err(BuildStreamError::StreamConfigNotSupported);
@@ -285,6 +323,9 @@ impl DeviceTrait for Device {
         // fed to the audio buffer.
         let bytes_per_channel = sample_format.sample_size();
         let sample_rate = config.sample_rate;
+        let position = Arc::new(PositionCounter::default());
+        let callback_position = position.clone();
+        let mut next_sample_time = None;
         type Args = render_callback::Args<data::Raw>;
         audio_unit.set_render_callback(move |args: Args| unsafe {
             // If `run()` is currently running, then a callback will be available from this list.
@@ -315,28 +356,43 @@ impl DeviceTrait for Device {
                 .expect("`playback` occurs beyond representation supported by `StreamInstant`");
             let timestamp = crate::OutputStreamTimestamp { callback, playback };
 
-            let info = OutputCallbackInfo { timestamp };
+            track_sample_time(
+                &args.time_stamp,
+                buffer_frames,
+                &mut next_sample_time,
+                &callback_position,
+            );
+            let info = OutputCallbackInfo {
+                timestamp,
+                position: callback_position.advance(buffer_frames),
+            };
             data_callback(&mut data, &info);
             Ok(())
         })?;
 
         audio_unit.start()?;
 
-        Ok(Stream::new(StreamInner {
-            playing: true,
-            audio_unit,
-        }))
+        Ok(Stream::new(
+            StreamInner {
+                playing: true,
+                audio_unit,
+            },
+            position,
+        ))
     }
 }
 
 pub struct Stream {
     inner: RefCell<StreamInner>,
+    // The number of frames passed to or from the data callback.
+    position: Arc<PositionCounter>,
 }
 
 impl Stream {
-    fn new(inner: StreamInner) -> Self {
+    fn new(inner: StreamInner, position: Arc<PositionCounter>) -> Self {
         Self {
             inner: RefCell::new(inner),
+            position,
         }
     }
 }
@@ -370,6 +426,10 @@ impl StreamTrait for Stream {
         }
         Ok(())
     }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.position.frames())
+    }
 }
 
 struct StreamInner {
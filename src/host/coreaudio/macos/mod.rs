@@ -1,7 +1,7 @@
 extern crate core_foundation_sys;
 extern crate coreaudio;
 
-use super::{asbd_from_config, check_os_status, frames_to_duration, host_time_to_stream_instant};
+use super::{asbd_from_config, check_os_status, host_time_to_stream_instant, track_sample_time};
 
 use self::core_foundation_sys::string::{CFStringGetCString, CFStringGetCStringPtr, CFStringRef};
 use self::coreaudio::audio_unit::render_callback::{self, data};
@@ -20,13 +20,14 @@ use self::coreaudio::sys::{
     AudioObjectPropertyScope, AudioObjectSetPropertyData, AudioStreamBasicDescription,
     AudioValueRange, OSStatus,
 };
+use crate::position::PositionCounter;
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
+    frames_to_duration, BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
     DefaultStreamConfigError, DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo,
-    PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    PauseStreamError, PlayStreamError, SampleFormat, SampleLayout, SampleRate, StreamConfig,
+    StreamError, StreamPositionError, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use std::ffi::CStr;
 use std::fmt;
@@ -121,6 +122,16 @@ impl DeviceTrait for Device {
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_input_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         Device::build_input_stream_raw(
             self,
             config,
@@ -143,6 +154,16 @@ impl DeviceTrait for Device {
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_output_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         Device::build_output_stream_raw(
             self,
             config,
@@ -595,6 +616,9 @@ impl Device {
         // fed to the audio buffer.
         let bytes_per_channel = sample_format.sample_size();
         let sample_rate = config.sample_rate;
+        let position = Arc::new(PositionCounter::default());
+        let callback_position = position.clone();
+        let mut next_sample_time = None;
         type Args = render_callback::Args<data::Raw>;
         audio_unit.set_input_callback(move |args: Args| unsafe {
             let ptr = (*args.data.data).mBuffers.as_ptr();
@@ -627,17 +651,29 @@ impl Device {
                 .expect("`capture` occurs before origin of alsa `StreamInstant`");
             let timestamp = crate::InputStreamTimestamp { callback, capture };
 
-            let info = InputCallbackInfo { timestamp };
+            track_sample_time(
+                &args.time_stamp,
+                buffer_frames,
+                &mut next_sample_time,
+                &callback_position,
+            );
+            let info = InputCallbackInfo {
+                timestamp,
+                position: callback_position.advance(buffer_frames),
+            };
             data_callback(&data, &info);
             Ok(())
         })?;
 
-        let stream = Stream::new(StreamInner {
-            playing: true,
-            _disconnect_listener: None,
-            audio_unit,
-            device_id: self.audio_device_id,
-        });
+        let stream = Stream::new(
+            StreamInner {
+                playing: true,
+                _disconnect_listener: None,
+                audio_unit,
+                device_id: self.audio_device_id,
+            },
+            position,
+        );
 
         // If we didn't request the default device, stop the stream if the
         // device disconnects.
@@ -702,6 +738,9 @@ impl Device {
         // fed to the audio buffer.
         let bytes_per_channel = sample_format.sample_size();
         let sample_rate = config.sample_rate;
+        let position = Arc::new(PositionCounter::default());
+        let callback_position = position.clone();
+        let mut next_sample_time = None;
         type Args = render_callback::Args<data::Raw>;
         audio_unit.set_render_callback(move |args: Args| unsafe {
             // If `run()` is currently running, then a callback will be available from this list.
@@ -732,17 +771,29 @@ impl Device {
                 .expect("`playback` occurs beyond representation supported by `StreamInstant`");
             let timestamp = crate::OutputStreamTimestamp { callback, playback };
 
-            let info = OutputCallbackInfo { timestamp };
+            track_sample_time(
+                &args.time_stamp,
+                buffer_frames,
+                &mut next_sample_time,
+                &callback_position,
+            );
+            let info = OutputCallbackInfo {
+                timestamp,
+                position: callback_position.advance(buffer_frames),
+            };
             data_callback(&mut data, &info);
             Ok(())
         })?;
 
-        let stream = Stream::new(StreamInner {
-            playing: true,
-            _disconnect_listener: None,
-            audio_unit,
-            device_id: self.audio_device_id,
-        });
+        let stream = Stream::new(
+            StreamInner {
+                playing: true,
+                _disconnect_listener: None,
+                audio_unit,
+                device_id: self.audio_device_id,
+            },
+            position,
+        );
 
         // If we didn't request the default device, stop the stream if the
         // device disconnects.
@@ -912,12 +963,15 @@ fn set_sample_rate(
 #[derive(Clone)]
 pub struct Stream {
     inner: Arc<Mutex<StreamInner>>,
+    // The number of frames passed to or from the data callback.
+    position: Arc<PositionCounter>,
 }
 
 impl Stream {
-    fn new(inner: StreamInner) -> Self {
+    fn new(inner: StreamInner, position: Arc<PositionCounter>) -> Self {
         Self {
             inner: Arc::new(Mutex::new(inner)),
+            position,
         }
     }
 }
@@ -934,6 +988,10 @@ impl StreamTrait for Stream {
 
         stream.pause()
     }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.position.frames())
+    }
 }
 
 fn get_io_buffer_frame_size_range(
@@ -2,9 +2,10 @@ extern crate coreaudio;
 
 use self::coreaudio::sys::{
     kAudioFormatFlagIsFloat, kAudioFormatFlagIsPacked, kAudioFormatLinearPCM,
-    AudioStreamBasicDescription, OSStatus,
+    kAudioTimeStampSampleTimeValid, AudioStreamBasicDescription, AudioTimeStamp, OSStatus,
 };
 
+use crate::position::PositionCounter;
 use crate::DefaultStreamConfigError;
 use crate::{BuildStreamError, SupportedStreamConfigsError};
 
@@ -74,18 +75,33 @@ fn host_time_to_stream_instant(
     let mut info: mach2::mach_time::mach_timebase_info = Default::default();
     let res = unsafe { mach2::mach_time::mach_timebase_info(&mut info) };
     check_os_status(res)?;
-    let nanos = m_host_time * info.numer as u64 / info.denom as u64;
-    let secs = nanos / 1_000_000_000;
-    let subsec_nanos = nanos - secs * 1_000_000_000;
-    Ok(crate::StreamInstant::new(secs as i64, subsec_nanos as u32))
+    let now = unsafe { mach2::mach_time::mach_absolute_time() };
+    let to_nanos = |host_time: u64| host_time as i128 * info.numer as i128 / info.denom as i128;
+    crate::StreamInstant::from_clock_nanos(to_nanos(m_host_time), to_nanos(now)).ok_or(
+        BackendSpecificError {
+            description: "host time out of range of `StreamInstant` representation".to_string(),
+        },
+    )
 }
 
-// Convert the given duration in frames at the given sample rate to a `std::time::Duration`.
-fn frames_to_duration(frames: usize, rate: crate::SampleRate) -> std::time::Duration {
-    let secsf = frames as f64 / rate.0 as f64;
-    let secs = secsf as u64;
-    let nanos = ((secsf - secs as f64) * 1_000_000_000.0) as u32;
-    std::time::Duration::new(secs, nanos)
+// Flag the buffer of a callback as discontinuous if its sample time skips frames, e.g. after an
+// overload of the IO thread or while the stream was paused. `next_sample_time` holds the sample
+// time expected for the callback, and is updated to the one expected for the next callback.
+fn track_sample_time(
+    time_stamp: &AudioTimeStamp,
+    frames: usize,
+    next_sample_time: &mut Option<f64>,
+    position: &PositionCounter,
+) {
+    if time_stamp.mFlags & kAudioTimeStampSampleTimeValid == 0 {
+        return;
+    }
+    if let Some(expected) = *next_sample_time {
+        if (time_stamp.mSampleTime - expected).abs() >= 1.0 {
+            position.mark_discontinuity();
+        }
+    }
+    *next_sample_time = Some(time_stamp.mSampleTime + frames as f64);
 }
 
 // TODO need stronger error identification
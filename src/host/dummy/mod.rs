@@ -0,0 +1,820 @@
+//! A host of virtual devices that do not produce or consume any sound.
+//!
+//! The streams of its devices run their callbacks on a timer thread, or are advanced manually via
+//! a [`ManualClock`], which makes the host useful to test audio code on machines without sound
+//! cards.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::duplex::fill_equilibrium;
+use crate::platform::HostId;
+use crate::position::PositionCounter;
+use crate::stats::StatsCollector;
+use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
+use crate::{
+    frames_to_duration, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
+    DeviceDescription, DeviceId, DeviceIdError, DeviceInterface, DeviceNameError, DevicesError,
+    FrameCount, InputCallbackInfo, InputStreamTimestamp, OutputCallbackInfo, OutputStreamTimestamp,
+    PauseStreamError, PlayStreamError, SampleFormat, SampleLayout, SampleRate, StreamBufferSize,
+    StreamBufferSizeError, StreamConfig, StreamError, StreamInstant, StreamLatencyError,
+    StreamPositionError, StreamStats, StreamStatsError, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
+};
+
+pub type SupportedInputConfigs = std::vec::IntoIter<SupportedStreamConfigRange>;
+pub type SupportedOutputConfigs = std::vec::IntoIter<SupportedStreamConfigRange>;
+pub type Devices = std::vec::IntoIter<Device>;
+
+// The number of frames per callback of streams with `BufferSize::Default`.
+const DEFAULT_BUFFER_SIZE: u32 = 512;
+
+/// The Dummy Host type
+#[derive(Debug)]
+pub struct Host {
+    /// The virtual devices of this host. The first devices supporting input and output are the
+    /// default ones.
+    devices: Vec<Device>,
+}
+
+impl Host {
+    /// Create a host with a single virtual device named "Dummy", supporting mono and stereo
+    /// input and output of `f32` and `i16` samples at any sample rate.
+    pub fn new() -> Result<Self, crate::HostUnavailable> {
+        let configs: Vec<_> = [2, 1]
+            .into_iter()
+            .flat_map(|channels| {
+                [SampleFormat::F32, SampleFormat::I16]
+                    .into_iter()
+                    .map(move |sample_format| SupportedStreamConfigRange {
+                        channels,
+                        min_sample_rate: SampleRate(8_000),
+                        max_sample_rate: SampleRate(192_000),
+                        buffer_size: SupportedBufferSize::Range { min: 16, max: 8192 },
+                        sample_format,
+                    })
+            })
+            .collect();
+        let device = Device::new("Dummy")
+            .with_input_configs(configs.clone())
+            .with_output_configs(configs);
+        Ok(Host {
+            devices: vec![device],
+        })
+    }
+
+    /// Create a host without any devices.
+    pub fn empty() -> Self {
+        Host { devices: vec![] }
+    }
+
+    /// Add a virtual device to the host.
+    pub fn add_device(&mut self, device: Device) {
+        self.devices.push(device);
+    }
+}
+
+impl HostTrait for Host {
+    type Devices = Devices;
+    type Device = Device;
+
+    fn is_available() -> bool {
+        true
+    }
+
+    fn devices(&self) -> Result<Self::Devices, DevicesError> {
+        Ok(self.devices.clone().into_iter())
+    }
+
+    fn default_input_device(&self) -> Option<Device> {
+        self.devices
+            .iter()
+            .find(|device| !device.input_configs.is_empty())
+            .cloned()
+    }
+
+    fn default_output_device(&self) -> Option<Device> {
+        self.devices
+            .iter()
+            .find(|device| !device.output_configs.is_empty())
+            .cloned()
+    }
+}
+
+/// A virtual device of the dummy host.
+///
+/// Input streams receive silence, and everything written to output streams is discarded.
+#[derive(Clone, Debug)]
+pub struct Device {
+    name: String,
+    input_configs: Vec<SupportedStreamConfigRange>,
+    output_configs: Vec<SupportedStreamConfigRange>,
+    clock: Option<ManualClock>,
+}
+
+impl Device {
+    /// Create a device with the given name, not supporting any configs.
+    pub fn new(name: &str) -> Self {
+        Device {
+            name: name.to_owned(),
+            input_configs: vec![],
+            output_configs: vec![],
+            clock: None,
+        }
+    }
+
+    /// Set the configs supported by input streams of the device.
+    pub fn with_input_configs(mut self, configs: Vec<SupportedStreamConfigRange>) -> Self {
+        self.input_configs = configs;
+        self
+    }
+
+    /// Set the configs supported by output streams of the device.
+    pub fn with_output_configs(mut self, configs: Vec<SupportedStreamConfigRange>) -> Self {
+        self.output_configs = configs;
+        self
+    }
+
+    /// Drive the streams of the device by the given clock instead of a timer thread.
+    pub fn with_clock(mut self, clock: ManualClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    fn default_config(
+        configs: &[SupportedStreamConfigRange],
+    ) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
+        let config = *configs
+            .iter()
+            .max_by(|a, b| a.cmp_default_heuristics(b))
+            .ok_or(DefaultStreamConfigError::StreamTypeNotSupported)?;
+        const HZ_44100: SampleRate = SampleRate(44_100);
+        Ok(config
+            .try_with_sample_rate(HZ_44100)
+            .unwrap_or_else(|| config.with_max_sample_rate()))
+    }
+
+    fn build_stream(
+        &self,
+        configs: &[SupportedStreamConfigRange],
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        callback: Callback,
+        error_callback: ErrorCallback,
+    ) -> Result<Stream, BuildStreamError> {
+        let supported = configs.iter().any(|range| {
+            range.channels == config.channels
+                && range.sample_format == sample_format
+                && range.min_sample_rate <= config.sample_rate
+                && config.sample_rate <= range.max_sample_rate
+                && match (&range.buffer_size, &config.buffer_size) {
+                    (SupportedBufferSize::Range { min, max }, BufferSize::Fixed(size)) => {
+                        min <= size && size <= max
+                    }
+                    _ => true,
+                }
+        });
+        if !supported {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+
+        let period = match config.buffer_size {
+            BufferSize::Fixed(size) => size,
+            BufferSize::Default => DEFAULT_BUFFER_SIZE,
+        };
+        let channels = config.channels as usize;
+        // Streams driven by the timer thread hold one period in the virtual device.
+        let latency = match self.clock {
+            Some(_) => Duration::ZERO,
+            None => frames_to_duration(period as usize, config.sample_rate),
+        };
+        let shared = Arc::new(Shared {
+            playing: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            idle: Mutex::new(()),
+            wakeup: Condvar::new(),
+            period: period as usize,
+            latency,
+            state: Mutex::new(StreamState {
+                callback,
+                error_callback,
+                buffer: vec![0; period as usize * channels * sample_format.sample_size()],
+                channels,
+                sample_format,
+                sample_rate: config.sample_rate,
+                frames_skipped: 0,
+            }),
+            position: PositionCounter::default(),
+            stats: StatsCollector::new(config.sample_rate),
+        });
+
+        let thread = match self.clock {
+            Some(ref clock) => {
+                clock.streams.lock().unwrap().push(Arc::downgrade(&shared));
+                None
+            }
+            None => {
+                let shared = shared.clone();
+                let thread = thread::Builder::new()
+                    .name("cpal_dummy".to_owned())
+                    .spawn(move || timer_worker(&shared))
+                    .unwrap();
+                Some(thread)
+            }
+        };
+        Ok(Stream { shared, thread })
+    }
+}
+
+impl DeviceTrait for Device {
+    type SupportedInputConfigs = SupportedInputConfigs;
+    type SupportedOutputConfigs = SupportedOutputConfigs;
+    type Stream = Stream;
+
+    #[inline]
+    fn name(&self) -> Result<String, DeviceNameError> {
+        Ok(self.name.clone())
+    }
+
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Ok(DeviceId::new(HostId::Dummy, &self.name))
+    }
+
+    fn description(&self) -> Result<DeviceDescription, DeviceNameError> {
+        Ok(DeviceDescription {
+            name: self.name.clone(),
+            interface: DeviceInterface::Virtual,
+            ..Default::default()
+        })
+    }
+
+    #[inline]
+    fn supported_input_configs(
+        &self,
+    ) -> Result<SupportedInputConfigs, SupportedStreamConfigsError> {
+        Ok(self.input_configs.clone().into_iter())
+    }
+
+    #[inline]
+    fn supported_output_configs(
+        &self,
+    ) -> Result<SupportedOutputConfigs, SupportedStreamConfigsError> {
+        Ok(self.output_configs.clone().into_iter())
+    }
+
+    #[inline]
+    fn default_input_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
+        Device::default_config(&self.input_configs)
+    }
+
+    #[inline]
+    fn default_output_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
+        Device::default_config(&self.output_configs)
+    }
+
+    fn build_input_stream_raw<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_input_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
+        self.build_stream(
+            &self.input_configs,
+            config,
+            sample_format,
+            Callback::Input(Box::new(data_callback)),
+            Box::new(error_callback),
+        )
+    }
+
+    /// Create an output stream.
+    fn build_output_stream_raw<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_output_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
+        self.build_stream(
+            &self.output_configs,
+            config,
+            sample_format,
+            Callback::Output(Box::new(data_callback)),
+            Box::new(error_callback),
+        )
+    }
+}
+
+/// A clock advancing the streams of dummy devices on demand, for deterministic tests.
+///
+/// Streams of devices created via [`Device::with_clock`] only run their callbacks when the clock
+/// is advanced, on the thread advancing it. Their timestamps are derived from the number of
+/// frames the stream processed or skipped, starting at zero, and input and output are reported
+/// without latency.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    streams: Arc<Mutex<Vec<Weak<Shared>>>>,
+}
+
+impl ManualClock {
+    /// Create a clock that is not driving any streams yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advance all playing streams by `frames` frames, running each of their data callbacks once,
+    /// or several times if `frames` exceeds the buffer size of the stream.
+    pub fn advance(&self, frames: usize) {
+        for shared in self.streams() {
+            let mut remaining = frames;
+            while remaining > 0 && shared.playing.load(Ordering::SeqCst) {
+                let frames = remaining.min(shared.period);
+                let mut state = shared.state.lock().unwrap();
+                let callback = state.position_instant(shared.position.frames());
+                state.process(frames, callback, &shared);
+                remaining -= frames;
+            }
+        }
+    }
+
+    /// Skip `frames` frames of all playing streams without running their data callbacks, as if
+    /// they were lost to an xrun. Every stream reports the lost frames to its error callback and
+    /// flags its next buffer as following a discontinuity. The skipped frames count towards the
+    /// timestamps of later callbacks, but not towards the position of the stream.
+    pub fn skip(&self, frames: usize) {
+        for shared in self.streams() {
+            if frames > 0 && shared.playing.load(Ordering::SeqCst) {
+                shared.state.lock().unwrap().skip(frames, &shared);
+            }
+        }
+    }
+
+    // The streams driven by this clock. Collected first, so that callbacks may build or drop
+    // streams of this clock.
+    fn streams(&self) -> Vec<Arc<Shared>> {
+        let mut streams = self.streams.lock().unwrap();
+        streams.retain(|stream| stream.strong_count() > 0);
+        streams.iter().filter_map(Weak::upgrade).collect()
+    }
+}
+
+type InputDataCallback = Box<dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static>;
+type OutputDataCallback = Box<dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static>;
+type ErrorCallback = Box<dyn FnMut(StreamError) + Send + 'static>;
+
+enum Callback {
+    Input(InputDataCallback),
+    Output(OutputDataCallback),
+}
+
+struct StreamState {
+    callback: Callback,
+    error_callback: ErrorCallback,
+    buffer: Vec<u8>,
+    channels: usize,
+    sample_format: SampleFormat,
+    sample_rate: SampleRate,
+    // The number of frames skipped via `ManualClock::skip`.
+    frames_skipped: u64,
+}
+
+impl StreamState {
+    // The instant of the frame at the given position, counted from the start of the stream
+    // including the frames skipped.
+    fn position_instant(&self, position: u64) -> StreamInstant {
+        let frames = (position + self.frames_skipped) as i128;
+        let nanos = frames * 1_000_000_000 / self.sample_rate.0 as i128;
+        StreamInstant::from_nanos_i128(nanos).expect("stream position out of range")
+    }
+
+    // Drop `frames` frames as if they were lost to an xrun.
+    fn skip(&mut self, frames: usize, shared: &Shared) {
+        self.frames_skipped += frames as u64;
+        shared.position.mark_discontinuity();
+        shared.stats.record_xrun();
+        let frames_lost = Some(frames);
+        let err = match self.callback {
+            Callback::Input(_) => StreamError::BufferOverrun { frames_lost },
+            Callback::Output(_) => StreamError::BufferUnderrun { frames_lost },
+        };
+        (self.error_callback)(err);
+    }
+
+    // Run the data callback for `frames` frames, with the given callback instant.
+    fn process(&mut self, frames: usize, callback: StreamInstant, shared: &Shared) {
+        let position = shared.position.advance(frames);
+        let sample_format = self.sample_format;
+        let len = frames * self.channels;
+        // The buffer holds a full period, which callbacks never exceed.
+        let mut data =
+            unsafe { Data::from_parts(self.buffer.as_mut_ptr() as *mut (), len, sample_format) };
+        let start = shared.stats.start();
+        match self.callback {
+            Callback::Input(ref mut data_callback) => {
+                fill_equilibrium(&mut data, 0);
+                let timestamp = InputStreamTimestamp {
+                    callback,
+                    capture: callback.sub(shared.latency).unwrap_or(callback),
+                };
+                let info = InputCallbackInfo {
+                    timestamp,
+                    position,
+                };
+                data_callback(&data, &info);
+            }
+            Callback::Output(ref mut data_callback) => {
+                let timestamp = OutputStreamTimestamp {
+                    callback,
+                    playback: callback.add(shared.latency).unwrap_or(callback),
+                };
+                let info = OutputCallbackInfo {
+                    timestamp,
+                    position,
+                };
+                data_callback(&mut data, &info);
+            }
+        }
+        shared.stats.record_callback(start, frames);
+    }
+}
+
+struct Shared {
+    playing: AtomicBool,
+    stopped: AtomicBool,
+    // Only locked by the timer thread while it waits for `wakeup`, never while it runs the data
+    // callback, so that the callback may play or pause the stream.
+    idle: Mutex<()>,
+    wakeup: Condvar,
+    // The maximum number of frames per callback.
+    period: usize,
+    // The time between a frame being captured or played and it being passed to the callback.
+    latency: Duration,
+    state: Mutex<StreamState>,
+    // The number of frames processed so far.
+    position: PositionCounter,
+    stats: StatsCollector,
+}
+
+impl Shared {
+    // Wake up the timer thread to check whether the stream is still playing and not dropped.
+    fn notify(&self) {
+        // Taking the lock ensures the timer thread is either waiting or has yet to check the
+        // flags, so the notification is not lost.
+        let _idle = self.idle.lock().unwrap();
+        self.wakeup.notify_all();
+    }
+}
+
+impl std::fmt::Debug for Shared {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Shared").finish_non_exhaustive()
+    }
+}
+
+// Run the data callback every `period` frames in real time while the stream is playing.
+fn timer_worker(shared: &Shared) {
+    let period = shared.period;
+    let mut next = Instant::now();
+    while !shared.stopped.load(Ordering::SeqCst) {
+        if !shared.playing.load(Ordering::SeqCst) {
+            // Wait for the stream to be played or dropped.
+            let idle = shared.idle.lock().unwrap();
+            let _idle = shared
+                .wakeup
+                .wait_while(idle, |_| {
+                    !shared.playing.load(Ordering::SeqCst) && !shared.stopped.load(Ordering::SeqCst)
+                })
+                .unwrap();
+            next = Instant::now();
+            continue;
+        }
+
+        let now = Instant::now();
+        if now < next {
+            // Wait for the next period, unless the stream is dropped.
+            let idle = shared.idle.lock().unwrap();
+            let _idle = shared
+                .wakeup
+                .wait_timeout_while(idle, next - now, |_| !shared.stopped.load(Ordering::SeqCst))
+                .unwrap();
+            continue;
+        }
+
+        let mut state = shared.state.lock().unwrap();
+        let period_duration = Duration::from_secs_f64(period as f64 / state.sample_rate.0 as f64);
+        if now - next > period_duration / 2 {
+            shared.stats.record_late_wakeup();
+        }
+        state.process(period, StreamInstant::from_instant(now), shared);
+        next += period_duration;
+    }
+}
+
+/// A stream of a dummy device.
+#[derive(Debug)]
+pub struct Stream {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StreamTrait for Stream {
+    fn play(&self) -> Result<(), PlayStreamError> {
+        self.shared.playing.store(true, Ordering::SeqCst);
+        self.shared.notify();
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<(), PauseStreamError> {
+        self.shared.playing.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// The buffer size of the stream config, or 512 frames by default. Streams driven by a
+    /// [`ManualClock`] receive as many frames per callback as the clock is advanced by instead,
+    /// up to the buffer size.
+    fn buffer_size(&self) -> Result<StreamBufferSize, StreamBufferSizeError> {
+        let period = self.shared.period as FrameCount;
+        Ok(StreamBufferSize {
+            buffer_frames: period,
+            period_frames: period,
+            max_callback_frames: period,
+        })
+    }
+
+    /// Streams driven by the timer thread have the latency of one buffer, as a frame is captured
+    /// or played one buffer after the callback it is passed to. Streams driven by a
+    /// [`ManualClock`] have no latency.
+    fn latency(&self) -> Result<Duration, StreamLatencyError> {
+        Ok(self.shared.latency)
+    }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.shared.position.frames())
+    }
+
+    /// Streams driven by the timer thread count late wakeups when the timer fires more than half
+    /// a period late, but never run into xruns, as the timer catches up instead. Streams driven
+    /// by a [`ManualClock`] only collect the durations of their callbacks.
+    fn enable_stats(&self) -> Result<(), StreamStatsError> {
+        self.shared.stats.enable();
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<StreamStats, StreamStatsError> {
+        self.shared.stats.snapshot()
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        self.shared.notify();
+        if let Some(thread) = self.thread.take() {
+            // The stream may be dropped from its own data callback, in which case the timer
+            // thread stops once the callback returns.
+            if thread.thread().id() != thread::current().id() {
+                thread.join().unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Device, ManualClock, Stream};
+    use crate::traits::{DeviceTrait, StreamTrait};
+    use crate::{
+        BufferSize, SampleFormat, SampleLayout, SampleRate, StreamConfig, StreamInstant,
+        SupportedBufferSize, SupportedStreamConfigRange,
+    };
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::time::Duration;
+
+    const CONFIG: StreamConfig = StreamConfig {
+        channels: 2,
+        sample_rate: SampleRate(1000),
+        buffer_size: BufferSize::Fixed(10),
+        layout: SampleLayout::Interleaved,
+    };
+
+    fn device() -> Device {
+        let range = SupportedStreamConfigRange::new(
+            2,
+            SampleRate(1000),
+            SampleRate(1000),
+            SupportedBufferSize::Unknown,
+            SampleFormat::F32,
+        );
+        Device::new("test")
+            .with_input_configs(vec![range])
+            .with_output_configs(vec![range])
+    }
+
+    #[test]
+    fn test_manual_clock_input() {
+        let clock = ManualClock::new();
+        let device = device().with_clock(clock.clone());
+        let calls = Arc::new(Mutex::new(vec![]));
+        let stream = {
+            let calls = calls.clone();
+            device
+                .build_input_stream(
+                    &CONFIG,
+                    move |data: &[f32], info| {
+                        assert!(data.iter().all(|&sample| sample == 0.0));
+                        let timestamp = info.timestamp();
+                        assert_eq!(timestamp.callback, timestamp.capture);
+                        calls.lock().unwrap().push((
+                            data.len(),
+                            info.position().frames,
+                            timestamp.callback,
+                        ));
+                    },
+                    |err| panic!("{err}"),
+                    None,
+                )
+                .unwrap()
+        };
+
+        // Nothing happens until the stream is played.
+        clock.advance(5);
+        assert!(calls.lock().unwrap().is_empty());
+        assert_eq!(stream.position().unwrap(), 0);
+
+        stream.enable_stats().unwrap();
+        stream.play().unwrap();
+        clock.advance(5);
+        clock.advance(5);
+        stream.pause().unwrap();
+        clock.advance(5);
+        stream.play().unwrap();
+        clock.advance(5);
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                (10, 0, StreamInstant::new(0, 0)),
+                (10, 5, StreamInstant::new(0, 5_000_000)),
+                (10, 10, StreamInstant::new(0, 10_000_000)),
+            ]
+        );
+        assert_eq!(stream.position().unwrap(), 15);
+        assert_eq!(stream.stats().unwrap().callbacks, 3);
+    }
+
+    #[test]
+    fn test_manual_clock_output() {
+        let clock = ManualClock::new();
+        let device = device().with_clock(clock.clone());
+        let calls = Arc::new(Mutex::new(vec![]));
+        let stream = {
+            let calls = calls.clone();
+            device
+                .build_output_stream(
+                    &CONFIG,
+                    move |data: &mut [f32], info| {
+                        data.fill(1.0);
+                        let timestamp = info.timestamp();
+                        assert_eq!(timestamp.callback, timestamp.playback);
+                        calls
+                            .lock()
+                            .unwrap()
+                            .push((info.position().frames, timestamp.playback));
+                    },
+                    |err| panic!("{err}"),
+                    None,
+                )
+                .unwrap()
+        };
+
+        stream.play().unwrap();
+        for _ in 0..3 {
+            clock.advance(4);
+        }
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                (0, StreamInstant::new(0, 0)),
+                (4, StreamInstant::new(0, 4_000_000)),
+                (8, StreamInstant::new(0, 8_000_000)),
+            ]
+        );
+        assert_eq!(stream.position().unwrap(), 12);
+
+        // Advancing by more than the buffer size splits the frames across callbacks.
+        calls.lock().unwrap().clear();
+        clock.advance(25);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                (12, StreamInstant::new(0, 12_000_000)),
+                (22, StreamInstant::new(0, 22_000_000)),
+                (32, StreamInstant::new(0, 32_000_000)),
+            ]
+        );
+        let buffer_size = stream.buffer_size().unwrap();
+        assert_eq!(buffer_size.max_callback_frames, 10);
+        assert_eq!(buffer_size.period_frames, 10);
+
+        // Streams dropped are no longer advanced.
+        drop(stream);
+        clock.advance(4);
+        assert_eq!(calls.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_latency() {
+        let (tx, rx) = mpsc::channel();
+        let stream = device()
+            .build_output_stream(
+                &CONFIG,
+                move |_: &mut [f32], info| {
+                    let timestamp = info.timestamp();
+                    let _ = tx.send(timestamp.playback.duration_since(&timestamp.callback));
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        // The buffer of 10 frames at 1 kHz takes 10 ms to play.
+        let latency = stream.latency().unwrap();
+        assert_eq!(latency, Duration::from_millis(10));
+        stream.play().unwrap();
+        let playback = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(playback, Some(latency));
+
+        let clock = ManualClock::new();
+        let stream = device()
+            .with_clock(clock)
+            .build_input_stream(&CONFIG, |_: &[f32], _| {}, |err| panic!("{err}"), None)
+            .unwrap();
+        assert_eq!(stream.latency().unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_play_from_callback() {
+        let stream_cell = Arc::new(OnceLock::<Stream>::new());
+        let (tx, rx) = mpsc::channel();
+        let stream = {
+            let stream_cell = Arc::downgrade(&stream_cell);
+            device()
+                .build_output_stream(
+                    &CONFIG,
+                    move |_: &mut [f32], info| {
+                        // Playing or pausing the stream from its callback must not deadlock.
+                        if let Some(stream_cell) = stream_cell.upgrade() {
+                            if let Some(stream) = stream_cell.get() {
+                                stream.play().unwrap();
+                            }
+                        }
+                        let _ = tx.send(info.position().frames);
+                    },
+                    |err| panic!("{err}"),
+                    None,
+                )
+                .unwrap()
+        };
+        stream_cell.set(stream).unwrap();
+        stream_cell.get().unwrap().play().unwrap();
+
+        for expected in [0, 10, 20] {
+            let frames = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(frames, expected);
+        }
+        drop(stream_cell);
+    }
+}
@@ -1,16 +1,19 @@
 use js_sys::Float32Array;
+use std::sync::Arc;
 use std::time::Duration;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::{spawn_local, JsFuture};
 use web_sys::AudioContext;
 
+use crate::position::PositionCounter;
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
-    BufferSize, BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError, DevicesError,
-    InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleFormat,
-    SampleRate, StreamConfig, StreamError, SupportedBufferSize, SupportedStreamConfig,
-    SupportedStreamConfigRange, SupportedStreamConfigsError,
+    frames_to_duration, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
+    DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
+    PlayStreamError, SampleFormat, SampleLayout, SampleRate, StreamConfig, StreamError,
+    StreamPositionError, SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+    SupportedStreamConfigsError,
 };
 
 // The emscripten backend currently works by instantiating an `AudioContext` object per `Stream`.
@@ -32,6 +35,10 @@ pub struct Device;
 pub struct Stream {
     // A reference to an `AudioContext` object.
     audio_ctxt: AudioContext,
+    // The number of frames requested from the data callback.
+    position: Arc<PositionCounter>,
+    // The offset of the context time onto the epoch of `StreamInstant`s.
+    epoch_offset: f64,
 }
 
 // Index within the `streams` array of the events loop.
@@ -184,13 +191,23 @@ impl DeviceTrait for Device {
         config: &StreamConfig,
         sample_format: SampleFormat,
         data_callback: D,
-        _error_callback: E,
-        _timeout: Option<Duration>,
+        error_callback: E,
+        timeout: Option<Duration>,
     ) -> Result<Self::Stream, BuildStreamError>
     where
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_output_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         if !valid_config(config, sample_format) {
             return Err(BuildStreamError::StreamConfigNotSupported);
         }
@@ -208,7 +225,12 @@ impl DeviceTrait for Device {
 
         // Create the stream.
         let audio_ctxt = AudioContext::new().expect("webaudio is not present on this system");
-        let stream = Stream { audio_ctxt };
+        let epoch_offset = crate::StreamInstant::clock_offset_secs(audio_ctxt.current_time());
+        let stream = Stream {
+            audio_ctxt,
+            position: Arc::new(PositionCounter::default()),
+            epoch_offset,
+        };
 
         // Use `set_timeout` to invoke a Rust callback repeatedly.
         //
@@ -259,6 +281,10 @@ impl StreamTrait for Stream {
         });
         Ok(())
     }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.position.frames())
+    }
 }
 
 fn audio_callback_fn<D>(
@@ -280,17 +306,20 @@ where
             let data = temporary_buffer.as_mut_ptr() as *mut ();
             let mut data = unsafe { Data::from_parts(data, len, sample_format) };
             let now_secs: f64 = audio_ctxt.current_time();
-            let callback = crate::StreamInstant::from_secs_f64(now_secs);
+            let callback = crate::StreamInstant::from_secs_f64(now_secs + stream.epoch_offset);
             // TODO: Use proper latency instead. Currently, unsupported on most browsers though, so
             // we estimate based on buffer size instead. Probably should use this, but it's only
             // supported by firefox (2020-04-28).
             // let latency_secs: f64 = audio_ctxt.outputLatency.try_into().unwrap();
-            let buffer_duration = frames_to_duration(len, sample_rate as usize);
+            let buffer_duration = frames_to_duration(len, SampleRate(sample_rate));
             let playback = callback
                 .add(buffer_duration)
                 .expect("`playback` occurs beyond representation supported by `StreamInstant`");
             let timestamp = crate::OutputStreamTimestamp { callback, playback };
-            let info = OutputCallbackInfo { timestamp };
+            let info = OutputCallbackInfo {
+                timestamp,
+                position: stream.position.advance(buffer_size_frames as usize),
+            };
             data_callback(&mut data, &info);
         }
 
@@ -412,11 +441,3 @@ fn valid_config(conf: &StreamConfig, sample_format: SampleFormat) -> bool {
         && conf.sample_rate >= MIN_SAMPLE_RATE
         && sample_format == SUPPORTED_SAMPLE_FORMAT
 }
-
-// Convert the given duration in frames at the given sample rate to a `std::time::Duration`.
-fn frames_to_duration(frames: usize, rate: usize) -> std::time::Duration {
-    let secsf = frames as f64 / rate as f64;
-    let secs = secsf as u64;
-    let nanos = ((secsf - secs as f64) * 1_000_000_000.0) as u32;
-    std::time::Duration::new(secs, nanos)
-}
@@ -1,9 +1,11 @@
+use crate::platform::HostId;
 use crate::traits::DeviceTrait;
 use crate::{
-    BackendSpecificError, BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError,
-    InputCallbackInfo, OutputCallbackInfo, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    BackendSpecificError, BuildStreamError, Data, DefaultStreamConfigError, DeviceDescription,
+    DeviceId, DeviceIdError, DeviceInterface, DeviceNameError, DuplexCallbackInfo, DuplexStream,
+    DuplexStreamConfig, InputCallbackInfo, OutputCallbackInfo, SampleFormat, SampleRate,
+    StreamConfig, StreamError, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use std::hash::{Hash, Hasher};
 use std::time::Duration;
@@ -18,7 +20,8 @@ const DEFAULT_NUM_CHANNELS: u16 = 2;
 const DEFAULT_SUPPORTED_CHANNELS: [u16; 10] = [1, 2, 4, 6, 8, 16, 24, 32, 48, 64];
 
 /// If a device is for input or output.
-/// Until we have duplex stream support JACK clients and CPAL devices for JACK will be either input or output.
+/// Duplex streams can be built from either type of device, in which case the JACK client gets both
+/// input and output ports.
 #[derive(Clone, Debug)]
 pub enum DeviceType {
     InputDevice,
@@ -32,6 +35,8 @@ pub struct Device {
     device_type: DeviceType,
     start_server_automatically: bool,
     connect_ports_automatically: bool,
+    // The name of the sound card behind the system ports the device connects to.
+    system_name: Option<String>,
 }
 
 impl Device {
@@ -49,9 +54,10 @@ impl Device {
         // This is a hack due to the fact that the Client must be moved to create the AsyncClient.
         match super::get_client(&name, client_options) {
             Ok(client) => Ok(Device {
+                system_name: system_port_name(&client, &device_type),
                 // The name given to the client by JACK, could potentially be different from the name supplied e.g.if there is a name collision
                 name: client.name().to_string(),
-                sample_rate: SampleRate(client.sample_rate() as u32),
+                sample_rate: SampleRate(client.sample_rate()),
                 buffer_size: SupportedBufferSize::Range {
                     min: client.buffer_size(),
                     max: client.buffer_size(),
@@ -95,7 +101,7 @@ impl Device {
     pub fn default_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
         let channels = DEFAULT_NUM_CHANNELS;
         let sample_rate = self.sample_rate;
-        let buffer_size = self.buffer_size.clone();
+        let buffer_size = self.buffer_size;
         // The sample format for JACK audio ports is always "32-bit float mono audio" in the current implementation.
         // Custom formats are allowed within JACK, but this is of niche interest.
         // The format can be found programmatically by calling jack::PortSpec::port_type() on a created port.
@@ -121,13 +127,24 @@ impl Device {
                 channels,
                 min_sample_rate: f.sample_rate,
                 max_sample_rate: f.sample_rate,
-                buffer_size: f.buffer_size.clone(),
+                buffer_size: f.buffer_size,
                 sample_format: f.sample_format,
             });
         }
         supported_configs
     }
 
+    fn build_description(&self) -> DeviceDescription {
+        DeviceDescription {
+            name: self.name.clone(),
+            long_name: self.system_name.clone(),
+            driver: Some("JACK".to_owned()),
+            interface: DeviceInterface::Virtual,
+            is_plugin: true,
+            ..Default::default()
+        }
+    }
+
     pub fn is_input(&self) -> bool {
         matches!(self.device_type, DeviceType::InputDevice)
     }
@@ -137,6 +154,27 @@ impl Device {
     }
 }
 
+// The name of the sound card behind the system ports a device connects to, taken from the aliases
+// of the first port, e.g. "HDA-Intel-PCH" for the alias "alsa_pcm:HDA-Intel-PCH/playback_1".
+fn system_port_name(client: &jack::Client, device_type: &DeviceType) -> Option<String> {
+    let pattern = match device_type {
+        DeviceType::InputDevice => "system:capture_.*",
+        DeviceType::OutputDevice => "system:playback_.*",
+    };
+    let ports = client.ports(Some(pattern), None, jack::PortFlags::empty());
+    let alias = client
+        .port_by_name(ports.first()?)?
+        .aliases()
+        .ok()?
+        .into_iter()
+        .next()?;
+    let name = alias
+        .split_once(':')
+        .map_or(alias.as_str(), |(_, name)| name);
+    let name = name.rsplit_once('/').map_or(name, |(name, _)| name);
+    Some(name.to_owned())
+}
+
 impl DeviceTrait for Device {
     type SupportedInputConfigs = SupportedInputConfigs;
     type SupportedOutputConfigs = SupportedOutputConfigs;
@@ -146,6 +184,17 @@ impl DeviceTrait for Device {
         Ok(self.name.clone())
     }
 
+    /// Streams connect to the system ports of the server, so the description is based on the
+    /// aliases of these ports, which usually name the sound card.
+    fn description(&self) -> Result<DeviceDescription, DeviceNameError> {
+        Ok(self.build_description())
+    }
+
+    /// The name of the JACK client, which is unique within the server.
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Ok(DeviceId::new(HostId::Jack, &self.name))
+    }
+
     fn supported_input_configs(
         &self,
     ) -> Result<Self::SupportedInputConfigs, SupportedStreamConfigsError> {
@@ -202,7 +251,13 @@ impl DeviceTrait for Device {
                 })
             }
         };
-        let mut stream = Stream::new_input(client, conf.channels, data_callback, error_callback);
+        let mut stream = Stream::new_input(
+            client,
+            conf.channels,
+            conf.layout,
+            data_callback,
+            error_callback,
+        );
 
         if self.connect_ports_automatically {
             stream.connect_to_system_inputs();
@@ -242,7 +297,13 @@ impl DeviceTrait for Device {
                 })
             }
         };
-        let mut stream = Stream::new_output(client, conf.channels, data_callback, error_callback);
+        let mut stream = Stream::new_output(
+            client,
+            conf.channels,
+            conf.layout,
+            data_callback,
+            error_callback,
+        );
 
         if self.connect_ports_automatically {
             stream.connect_to_system_outputs();
@@ -250,6 +311,45 @@ impl DeviceTrait for Device {
 
         Ok(stream)
     }
+
+    fn build_duplex_stream_raw<D, E>(
+        &self,
+        conf: &DuplexStreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+        _timeout: Option<Duration>,
+    ) -> Result<DuplexStream<Self::Stream>, BuildStreamError>
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        if conf.sample_rate != self.sample_rate || sample_format != JACK_SAMPLE_FORMAT {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+
+        // The settings should be fine, create a Client
+        let client_options = super::get_client_options(self.start_server_automatically);
+        let client = super::get_client(&self.name, client_options).map_err(|e| {
+            BuildStreamError::BackendSpecific {
+                err: BackendSpecificError { description: e },
+            }
+        })?;
+        let mut stream = Stream::new_duplex(
+            client,
+            conf.input_channels,
+            conf.output_channels,
+            data_callback,
+            error_callback,
+        );
+
+        if self.connect_ports_automatically {
+            stream.connect_to_system_inputs();
+            stream.connect_to_system_outputs();
+        }
+
+        Ok(DuplexStream::native(stream))
+    }
 }
 
 impl PartialEq for Device {
@@ -1,7 +1,13 @@
 extern crate jack;
 
-use crate::traits::HostTrait;
-use crate::{DevicesError, SampleFormat, SupportedStreamConfigRange};
+use crate::traits::{DeviceTrait, HostTrait};
+use crate::{
+    BackendSpecificError, DeviceEvent, DeviceId, DeviceWatcher, DevicesError, SampleFormat,
+    SupportedStreamConfigRange, WatchDevicesError,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 
 mod device;
 pub use self::device::Device;
@@ -18,8 +24,8 @@ pub type Devices = std::vec::IntoIter<Device>;
 #[derive(Debug)]
 pub struct Host {
     /// The name that the client will have in JACK.
-    /// Until we have duplex streams two clients will be created adding "out" or "in" to the name
-    /// since names have to be unique.
+    /// Separate input and output devices are created, adding "out" or "in" to the name since
+    /// names have to be unique. Duplex streams can be built from either of them.
     name: String,
     /// If ports are to be connected to the system (soundcard) ports automatically (default is true).
     connect_ports_automatically: bool,
@@ -129,6 +135,161 @@ impl HostTrait for Host {
         }
         None
     }
+
+    /// Reports the input and output devices of the host as added or removed when the first
+    /// system capture or playback port is registered with the server, or the last one leaves
+    /// it, e.g. as the backend of the server is reconfigured. `DefaultChanged` is reported when
+    /// the number of system ports of an available device changes, as the devices then refer to
+    /// different hardware. Ports are watched using a separate client named after the host with
+    /// "_watch" appended.
+    fn watch_devices<F>(&self, callback: F) -> Result<DeviceWatcher, WatchDevicesError>
+    where
+        F: FnMut(DeviceEvent) + Send + 'static,
+    {
+        let client_options = get_client_options(self.start_server_automatically);
+        let client = get_client(&format!("{}_watch", self.name), client_options)
+            .map_err(|description| BackendSpecificError { description })?;
+        let device_id = |is_input: bool| {
+            self.devices_created
+                .iter()
+                .find(|device| device.is_input() == is_input)
+                .and_then(|device| device.id().ok())
+        };
+        let capture = SystemPorts::new(&client, "system:capture_.*", device_id(true));
+        let playback = SystemPorts::new(&client, "system:playback_.*", device_id(false));
+        let (tx, rx) = mpsc::channel();
+        let notification_handler = WatchNotificationHandler {
+            messages: Mutex::new(tx.clone()),
+        };
+        let async_client = client
+            .activate_async(notification_handler, ())
+            .map_err(|e| BackendSpecificError {
+                description: format!("Failed to activate JACK client: {:?}", e),
+            })?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let worker_stopped = stopped.clone();
+        let thread = thread::Builder::new()
+            .name("cpal_jack_watch".to_owned())
+            .spawn(move || {
+                let mut worker = WatchWorker {
+                    callback,
+                    capture,
+                    playback,
+                };
+                worker.run(&async_client, &rx, &worker_stopped);
+                // Deactivating the client waits for the notification thread, which only sends
+                // messages, so this does not block on the callback.
+                let _ = async_client.deactivate();
+            })
+            .unwrap();
+        Ok(DeviceWatcher::new(move || {
+            stopped.store(true, Ordering::Relaxed);
+            let _ = tx.send(WatchMessage::Stop);
+            // The watcher may be dropped by the callback, in which case the worker exits once the
+            // callback returns.
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
+        }))
+    }
+}
+
+enum WatchMessage {
+    // A port has been registered or unregistered.
+    PortsChanged,
+    // The watcher has been dropped.
+    Stop,
+}
+
+// The system ports backing one of the devices of the host.
+struct SystemPorts {
+    pattern: &'static str,
+    device_id: Option<DeviceId>,
+    count: usize,
+}
+
+impl SystemPorts {
+    fn new(client: &jack::Client, pattern: &'static str, device_id: Option<DeviceId>) -> Self {
+        let mut ports = SystemPorts {
+            pattern,
+            device_id,
+            count: 0,
+        };
+        ports.count = ports.registered(client);
+        ports
+    }
+
+    fn registered(&self, client: &jack::Client) -> usize {
+        client
+            .ports(Some(self.pattern), None, jack::PortFlags::empty())
+            .len()
+    }
+
+    // The event for the device if the ports have changed.
+    fn update(&mut self, client: &jack::Client) -> Option<DeviceEvent> {
+        let count = self.registered(client);
+        let previous = std::mem::replace(&mut self.count, count);
+        let device_id = self.device_id.clone()?;
+        match (previous, count) {
+            (0, 0) => None,
+            (0, _) => Some(DeviceEvent::Added(device_id)),
+            (_, 0) => Some(DeviceEvent::Removed(device_id)),
+            (previous, count) if previous != count => Some(DeviceEvent::DefaultChanged),
+            _ => None,
+        }
+    }
+}
+
+// Queries the ports of the server on behalf of the notification handler, as JACK does not allow
+// calling the server from notification callbacks, and calls the user's callback.
+struct WatchWorker<F> {
+    callback: F,
+    capture: SystemPorts,
+    playback: SystemPorts,
+}
+
+impl<F> WatchWorker<F>
+where
+    F: FnMut(DeviceEvent),
+{
+    fn run<N, P>(
+        &mut self,
+        client: &jack::AsyncClient<N, P>,
+        messages: &mpsc::Receiver<WatchMessage>,
+        stopped: &AtomicBool,
+    ) {
+        while let Ok(WatchMessage::PortsChanged) = messages.recv() {
+            let client = client.as_client();
+            let events = [self.capture.update(client), self.playback.update(client)];
+            // Both devices change at once if the server is reconfigured.
+            let default_changed = events.contains(&Some(DeviceEvent::DefaultChanged));
+            let events = events
+                .into_iter()
+                .flatten()
+                .filter(|event| *event != DeviceEvent::DefaultChanged)
+                .chain(default_changed.then_some(DeviceEvent::DefaultChanged));
+            for event in events {
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                (self.callback)(event);
+            }
+        }
+    }
+}
+
+struct WatchNotificationHandler {
+    messages: Mutex<mpsc::Sender<WatchMessage>>,
+}
+
+impl jack::NotificationHandler for WatchNotificationHandler {
+    fn port_registration(&mut self, _: &jack::Client, _: jack::PortId, _: bool) {
+        let _ = self
+            .messages
+            .lock()
+            .unwrap()
+            .send(WatchMessage::PortsChanged);
+    }
 }
 
 fn get_client_options(start_server_automatically: bool) -> jack::ClientOptions {
@@ -1,11 +1,16 @@
+use crate::planar::ChannelPointers;
+use crate::position::PositionCounter;
+use crate::stats::StatsCollector;
 use crate::traits::StreamTrait;
 use crate::ChannelCount;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use crate::{
-    BackendSpecificError, Data, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleRate, StreamError,
+    frames_to_duration, BackendSpecificError, Data, DuplexCallbackInfo, FrameCount,
+    InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleLayout,
+    SampleRate, StreamBufferSize, StreamBufferSizeError, StreamError, StreamLatencyError,
+    StreamPositionError, StreamStats, StreamStatsError,
 };
 
 use super::JACK_SAMPLE_FORMAT;
@@ -15,6 +20,8 @@ type ErrorCallbackPtr = Arc<Mutex<dyn FnMut(StreamError) + Send + 'static>>;
 pub struct Stream {
     // TODO: It might be faster to send a message when playing/pausing than to check this every iteration
     playing: Arc<AtomicBool>,
+    position: Arc<PositionCounter>,
+    stats: Arc<StatsCollector>,
     async_client: jack::AsyncClient<JackNotificationHandler, LocalProcessHandler>,
     // Port names are stored in order to connect them to other ports in jack automatically
     input_port_names: Vec<String>,
@@ -26,130 +33,115 @@ impl Stream {
     pub fn new_input<D, E>(
         client: jack::Client,
         channels: ChannelCount,
+        layout: SampleLayout,
         data_callback: D,
-        mut error_callback: E,
+        error_callback: E,
     ) -> Stream
     where
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
-        let mut ports = vec![];
-        let mut port_names: Vec<String> = vec![];
-        // Create ports
-        for i in 0..channels {
-            let port_try = client.register_port(&format!("in_{}", i), jack::AudioIn::default());
-            match port_try {
-                Ok(port) => {
-                    // Get the port name in order to later connect it automatically
-                    if let Ok(port_name) = port.name() {
-                        port_names.push(port_name);
-                    }
-                    // Store the port into a Vec to move to the ProcessHandler
-                    ports.push(port);
-                }
-                Err(e) => {
-                    // If port creation failed, send the error back via the error_callback
-                    error_callback(
-                        BackendSpecificError {
-                            description: e.to_string(),
-                        }
-                        .into(),
-                    );
-                }
-            }
-        }
-
-        let playing = Arc::new(AtomicBool::new(true));
-
-        let error_callback_ptr = Arc::new(Mutex::new(error_callback)) as ErrorCallbackPtr;
-
-        let input_process_handler = LocalProcessHandler::new(
-            vec![],
-            ports,
-            SampleRate(client.sample_rate() as u32),
-            client.buffer_size() as usize,
-            Some(Box::new(data_callback)),
-            None,
-            playing.clone(),
-            Arc::clone(&error_callback_ptr),
-        );
-
-        let notification_handler = JackNotificationHandler::new(error_callback_ptr);
-
-        let async_client = client
-            .activate_async(notification_handler, input_process_handler)
-            .unwrap();
-
-        Stream {
-            playing,
-            async_client,
-            input_port_names: port_names,
-            output_port_names: vec![],
-        }
+        let data_callback = DataCallback::Input(Box::new(data_callback));
+        Stream::new(client, channels, 0, layout, data_callback, error_callback)
     }
 
     pub fn new_output<D, E>(
         client: jack::Client,
         channels: ChannelCount,
+        layout: SampleLayout,
         data_callback: D,
-        mut error_callback: E,
+        error_callback: E,
     ) -> Stream
     where
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
-        let mut ports = vec![];
-        let mut port_names: Vec<String> = vec![];
-        // Create ports
-        for i in 0..channels {
-            let port_try = client.register_port(&format!("out_{}", i), jack::AudioOut::default());
-            match port_try {
-                Ok(port) => {
-                    // Get the port name in order to later connect it automatically
-                    if let Ok(port_name) = port.name() {
-                        port_names.push(port_name);
-                    }
-                    // Store the port into a Vec to move to the ProcessHandler
-                    ports.push(port);
-                }
-                Err(e) => {
-                    // If port creation failed, send the error back via the error_callback
-                    error_callback(
-                        BackendSpecificError {
-                            description: e.to_string(),
-                        }
-                        .into(),
-                    );
-                }
-            }
-        }
+        let data_callback = DataCallback::Output(Box::new(data_callback));
+        Stream::new(client, 0, channels, layout, data_callback, error_callback)
+    }
+
+    pub fn new_duplex<D, E>(
+        client: jack::Client,
+        input_channels: ChannelCount,
+        output_channels: ChannelCount,
+        data_callback: D,
+        error_callback: E,
+    ) -> Stream
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let data_callback = DataCallback::Duplex(Box::new(data_callback));
+        Stream::new(
+            client,
+            input_channels,
+            output_channels,
+            SampleLayout::Interleaved,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn new<E>(
+        client: jack::Client,
+        input_channels: ChannelCount,
+        output_channels: ChannelCount,
+        layout: SampleLayout,
+        data_callback: DataCallback,
+        mut error_callback: E,
+    ) -> Stream
+    where
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let (in_ports, input_port_names) = register_ports(
+            &client,
+            "in",
+            input_channels,
+            jack::AudioIn::default,
+            &mut error_callback,
+        );
+        let (out_ports, output_port_names) = register_ports(
+            &client,
+            "out",
+            output_channels,
+            jack::AudioOut::default,
+            &mut error_callback,
+        );
 
         let playing = Arc::new(AtomicBool::new(true));
+        let position = Arc::new(PositionCounter::default());
+        let stats = Arc::new(StatsCollector::new(SampleRate(client.sample_rate())));
+        let xrun_occurred = Arc::new(AtomicBool::new(false));
 
         let error_callback_ptr = Arc::new(Mutex::new(error_callback)) as ErrorCallbackPtr;
 
-        let output_process_handler = LocalProcessHandler::new(
-            ports,
-            vec![],
-            SampleRate(client.sample_rate() as u32),
+        let process_handler = LocalProcessHandler::new(
+            out_ports,
+            in_ports,
+            SampleRate(client.sample_rate()),
             client.buffer_size() as usize,
-            None,
-            Some(Box::new(data_callback)),
+            layout,
+            data_callback,
             playing.clone(),
+            position.clone(),
+            stats.clone(),
+            xrun_occurred.clone(),
             Arc::clone(&error_callback_ptr),
         );
 
-        let notification_handler = JackNotificationHandler::new(error_callback_ptr);
+        let notification_handler = JackNotificationHandler::new(error_callback_ptr, xrun_occurred);
 
         let async_client = client
-            .activate_async(notification_handler, output_process_handler)
+            .activate_async(notification_handler, process_handler)
             .unwrap();
 
         Stream {
             playing,
+            position,
+            stats,
             async_client,
-            input_port_names: vec![],
-            output_port_names: port_names,
+            input_port_names,
+            output_port_names,
         }
     }
 
@@ -216,10 +208,107 @@ impl StreamTrait for Stream {
         self.playing.store(false, Ordering::SeqCst);
         Ok(())
     }
+
+    fn latency(&self) -> Result<std::time::Duration, StreamLatencyError> {
+        let client = self.async_client.as_client();
+        // JACK propagates the latency of the longest path through the connections of a port to
+        // the port itself, so only our own ports have to be queried.
+        let port_latency = |names: &[String], mode: jack::LatencyType| {
+            names
+                .iter()
+                .filter_map(|name| client.port_by_name(name))
+                .map(|port| port.get_latency_range(mode).1)
+                .max()
+                .unwrap_or(0)
+        };
+        let frames = port_latency(&self.input_port_names, jack::LatencyType::Capture)
+            + port_latency(&self.output_port_names, jack::LatencyType::Playback);
+        let sample_rate = SampleRate(client.sample_rate());
+        Ok(frames_to_duration(frames as usize, sample_rate))
+    }
+
+    /// The current buffer size of the JACK server, which every process callback receives in full.
+    /// The server may change it while the stream runs, which is reported to the error callback.
+    fn buffer_size(&self) -> Result<StreamBufferSize, StreamBufferSizeError> {
+        let frames = self.async_client.as_client().buffer_size() as FrameCount;
+        Ok(StreamBufferSize {
+            buffer_frames: frames,
+            period_frames: frames,
+            max_callback_frames: frames,
+        })
+    }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.position.frames())
+    }
+
+    /// Callbacks are timed including the copies between the port buffers and the buffers passed
+    /// to the data callback. Late wakeups are counted when the process callback starts more than
+    /// half a period after the start of the cycle, e.g. because clients earlier in the graph took
+    /// long.
+    fn enable_stats(&self) -> Result<(), StreamStatsError> {
+        self.stats.enable();
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<StreamStats, StreamStatsError> {
+        self.stats.snapshot()
+    }
 }
 
 type InputDataCallback = Box<dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static>;
 type OutputDataCallback = Box<dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static>;
+type DuplexDataCallback = Box<dyn FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static>;
+
+/// The data callback of a stream, which also determines how the port buffers are passed to it.
+enum DataCallback {
+    Input(InputDataCallback),
+    Output(OutputDataCallback),
+    Duplex(DuplexDataCallback),
+}
+
+/// Register `channels` ports named `{prefix}_{index}` on the client.
+///
+/// Returns the ports together with their full names, which are used to connect them automatically.
+/// Ports that fail to register are reported via the error callback and skipped.
+fn register_ports<PS, F>(
+    client: &jack::Client,
+    prefix: &str,
+    channels: ChannelCount,
+    port_spec: F,
+    error_callback: &mut dyn FnMut(StreamError),
+) -> (Vec<jack::Port<PS>>, Vec<String>)
+where
+    PS: jack::PortSpec,
+    F: Fn() -> PS,
+{
+    let mut ports = vec![];
+    let mut port_names: Vec<String> = vec![];
+    // Create ports
+    for i in 0..channels {
+        let port_try = client.register_port(&format!("{}_{}", prefix, i), port_spec());
+        match port_try {
+            Ok(port) => {
+                // Get the port name in order to later connect it automatically
+                if let Ok(port_name) = port.name() {
+                    port_names.push(port_name);
+                }
+                // Store the port into a Vec to move to the ProcessHandler
+                ports.push(port);
+            }
+            Err(e) => {
+                // If port creation failed, send the error back via the error_callback
+                error_callback(
+                    BackendSpecificError {
+                        description: e.to_string(),
+                    }
+                    .into(),
+                );
+            }
+        }
+    }
+    (ports, port_names)
+}
 
 struct LocalProcessHandler {
     /// No new ports are allowed to be created after the creation of the LocalProcessHandler as that would invalidate the buffer sizes
@@ -228,48 +317,166 @@ struct LocalProcessHandler {
 
     sample_rate: SampleRate,
     buffer_size: usize,
-    input_data_callback: Option<InputDataCallback>,
-    output_data_callback: Option<OutputDataCallback>,
+    /// The layout of the buffers passed to the data callback of an input or output stream.
+    layout: SampleLayout,
+    data_callback: DataCallback,
 
     // JACK audio samples are 32-bit float (unless you do some custom dark magic)
     temp_input_buffer: Vec<f32>,
     temp_output_buffer: Vec<f32>,
+    /// The port buffers handed to the data callback of an input or output stream of the split
+    /// layout.
+    port_buffers: ChannelPointers,
     playing: Arc<AtomicBool>,
-    creation_timestamp: std::time::Instant,
-    /// This should not be called on `process`, only on `buffer_size` because it can block.
+    /// The number of frames passed to or from the data callback, shared with the stream.
+    position: Arc<PositionCounter>,
+    /// The timing statistics of the process callback, shared with the stream.
+    stats: Arc<StatsCollector>,
+    /// Set by the notification handler when JACK reports an xrun.
+    xrun_occurred: Arc<AtomicBool>,
+    /// Whether an xrun has not been reported to the error callback yet.
+    xrun_pending: bool,
+    /// The frames skipped by the xruns that have not been reported yet.
+    frames_lost: usize,
+    /// The frame time at which the next cycle starts if no cycles are skipped.
+    next_frame_time: Option<jack::Frames>,
+    /// This must only be locked without blocking on `process`, as the notification thread may
+    /// hold it.
     error_callback_ptr: ErrorCallbackPtr,
 }
 
 impl LocalProcessHandler {
-    #[allow(too_many_arguments)]
+    #[allow(clippy::too_many_arguments)]
     fn new(
         out_ports: Vec<jack::Port<jack::AudioOut>>,
         in_ports: Vec<jack::Port<jack::AudioIn>>,
         sample_rate: SampleRate,
         buffer_size: usize,
-        input_data_callback: Option<InputDataCallback>,
-        output_data_callback: Option<OutputDataCallback>,
+        layout: SampleLayout,
+        data_callback: DataCallback,
         playing: Arc<AtomicBool>,
+        position: Arc<PositionCounter>,
+        stats: Arc<StatsCollector>,
+        xrun_occurred: Arc<AtomicBool>,
         error_callback_ptr: ErrorCallbackPtr,
     ) -> Self {
         // These may be reallocated in the `buffer_size` callback.
         let temp_input_buffer = vec![0.0; in_ports.len() * buffer_size];
         let temp_output_buffer = vec![0.0; out_ports.len() * buffer_size];
+        // Only streams of a single direction use the split layout.
+        let port_buffers = ChannelPointers::new(in_ports.len().max(out_ports.len()));
 
         LocalProcessHandler {
             out_ports,
             in_ports,
             sample_rate,
             buffer_size,
-            input_data_callback,
-            output_data_callback,
+            layout,
+            data_callback,
             temp_input_buffer,
             temp_output_buffer,
+            port_buffers,
             playing,
-            creation_timestamp: std::time::Instant::now(),
+            position,
+            stats,
+            xrun_occurred,
+            xrun_pending: false,
+            frames_lost: 0,
+            next_frame_time: None,
             error_callback_ptr,
         }
     }
+
+    // Flag the next buffer as discontinuous and report an xrun if JACK notified us of one or if
+    // cycles have been skipped since the last call to `process`, counting the skipped frames as
+    // lost.
+    fn track_xrun(&mut self, process_scope: &jack::ProcessScope) {
+        let frame_time = process_scope.last_frame_time();
+        let frames_lost = match self.next_frame_time {
+            Some(expected) if frame_time > expected => (frame_time - expected) as usize,
+            _ => 0,
+        };
+        self.next_frame_time = Some(frame_time.wrapping_add(process_scope.n_frames()));
+
+        if self.xrun_occurred.swap(false, Ordering::SeqCst) || frames_lost > 0 {
+            self.position.mark_discontinuity();
+            self.stats.record_xrun();
+            self.xrun_pending = true;
+            self.frames_lost += frames_lost;
+        }
+        if self.xrun_pending {
+            self.report_xrun();
+        }
+    }
+
+    // Report the pending xrun as a single error, an underrun for streams with outputs and an
+    // overrun for input streams. If the notification thread is using the error callback, the
+    // xrun is reported in the next cycle instead of blocking the process thread.
+    fn report_xrun(&mut self) {
+        let Ok(mut error_callback) = self.error_callback_ptr.try_lock() else {
+            return;
+        };
+        let frames_lost = (self.frames_lost > 0).then_some(self.frames_lost);
+        if self.out_ports.is_empty() {
+            error_callback(StreamError::BufferOverrun { frames_lost });
+        } else {
+            error_callback(StreamError::BufferUnderrun { frames_lost });
+        }
+        self.xrun_pending = false;
+        self.frames_lost = 0;
+    }
+}
+
+// Copy `frames` frames of the input ports into `buffer`, arranged according to `layout`. Streams of
+// the split layout use the port buffers directly instead.
+fn read_ports(
+    ports: &[jack::Port<jack::AudioIn>],
+    process_scope: &jack::ProcessScope,
+    buffer: &mut [f32],
+    frames: usize,
+    layout: SampleLayout,
+) {
+    let channels = ports.len();
+    for (ch_ix, port) in ports.iter().enumerate() {
+        let input_channel = &port.as_slice(process_scope)[..frames];
+        match layout {
+            SampleLayout::Interleaved => {
+                for (i, sample) in input_channel.iter().enumerate() {
+                    buffer[ch_ix + i * channels] = *sample;
+                }
+            }
+            SampleLayout::Planar => {
+                buffer[ch_ix * frames..(ch_ix + 1) * frames].copy_from_slice(input_channel);
+            }
+            SampleLayout::Split => unreachable!("split data is not copied"),
+        }
+    }
+}
+
+// Copy `frames` frames of `buffer`, arranged according to `layout`, into the output ports. Streams
+// of the split layout use the port buffers directly instead.
+fn write_ports(
+    ports: &mut [jack::Port<jack::AudioOut>],
+    process_scope: &jack::ProcessScope,
+    buffer: &[f32],
+    frames: usize,
+    layout: SampleLayout,
+) {
+    let channels = ports.len();
+    for (ch_ix, port) in ports.iter_mut().enumerate() {
+        let output_channel = &mut port.as_mut_slice(process_scope)[..frames];
+        match layout {
+            SampleLayout::Interleaved => {
+                for (i, sample) in output_channel.iter_mut().enumerate() {
+                    *sample = buffer[ch_ix + i * channels];
+                }
+            }
+            SampleLayout::Planar => {
+                output_channel.copy_from_slice(&buffer[ch_ix * frames..(ch_ix + 1) * frames]);
+            }
+            SampleLayout::Split => unreachable!("split data is not copied"),
+        }
+    }
 }
 
 fn temp_buffer_to_data(temp_input_buffer: &mut [f32], total_buffer_size: usize) -> Data {
@@ -280,28 +487,32 @@ fn temp_buffer_to_data(temp_input_buffer: &mut [f32], total_buffer_size: usize)
 }
 
 impl jack::ProcessHandler for LocalProcessHandler {
-    fn process(&mut self, _: &jack::Client, process_scope: &jack::ProcessScope) -> jack::Control {
+    fn process(
+        &mut self,
+        client: &jack::Client,
+        process_scope: &jack::ProcessScope,
+    ) -> jack::Control {
         if !self.playing.load(Ordering::SeqCst) {
+            self.next_frame_time = None;
             return jack::Control::Continue;
         }
 
+        self.track_xrun(process_scope);
+
         // This should be equal to self.buffer_size, but the implementation will
         // work even if it is less. Will panic in `temp_buffer_to_data` if greater.
         let current_frame_count = process_scope.n_frames() as usize;
 
         // Get timestamp data
         let cycle_times = process_scope.cycle_times();
-        let current_start_usecs = match cycle_times {
-            Ok(times) => times.current_usecs,
+        let start_cycle_instant = match cycle_times {
+            Ok(times) => micros_to_stream_instant(times.current_usecs, client.time()),
             Err(_) => {
                 // jack was unable to get the current time information
-                // Fall back to using Instants
-                let now = std::time::Instant::now();
-                let duration = now.duration_since(self.creation_timestamp);
-                duration.as_micros() as u64
+                // Fall back to using the current instant
+                crate::StreamInstant::now()
             }
         };
-        let start_cycle_instant = micros_to_stream_instant(current_start_usecs);
         let start_callback_instant = start_cycle_instant
             .add(frames_to_duration(
                 process_scope.frames_since_cycle_start() as usize,
@@ -309,68 +520,135 @@ impl jack::ProcessHandler for LocalProcessHandler {
             ))
             .expect("`playback` occurs beyond representation supported by `StreamInstant`");
 
-        if let Some(input_callback) = &mut self.input_data_callback {
-            // Let's get the data from the input ports and run the callback
+        // Create timestamps
+        let frames_since_cycle_start = process_scope.frames_since_cycle_start() as usize;
+        let duration_since_cycle_start =
+            frames_to_duration(frames_since_cycle_start, self.sample_rate);
+        let callback = start_callback_instant
+            .add(duration_since_cycle_start)
+            .expect("`playback` occurs beyond representation supported by `StreamInstant`");
+        let capture = start_callback_instant;
+        let buffer_duration = frames_to_duration(current_frame_count, self.sample_rate);
+        let playback = start_cycle_instant
+            .add(buffer_duration)
+            .expect("`playback` occurs beyond representation supported by `StreamInstant`");
 
-            let num_in_channels = self.in_ports.len();
+        let position = self.position.advance(current_frame_count);
+        if frames_since_cycle_start > current_frame_count / 2 {
+            self.stats.record_late_wakeup();
+        }
 
-            // Read the data from the input ports into the temporary buffer
-            // Go through every channel and store its data in the temporary input buffer
-            for ch_ix in 0..num_in_channels {
-                let input_channel = &self.in_ports[ch_ix].as_slice(process_scope);
-                for i in 0..current_frame_count {
-                    self.temp_input_buffer[ch_ix + i * num_in_channels] = input_channel[i];
-                }
+        let num_in_channels = self.in_ports.len();
+        let num_out_channels = self.out_ports.len();
+
+        let start = self.stats.start();
+        match &mut self.data_callback {
+            DataCallback::Input(input_callback) => {
+                let data = if self.layout == SampleLayout::Split {
+                    // Hand out the port buffers without copying them
+                    for (ch_ix, port) in self.in_ports.iter().enumerate() {
+                        let input_channel = port.as_slice(process_scope).as_ptr();
+                        self.port_buffers.set(ch_ix, input_channel as *mut ());
+                    }
+                    unsafe {
+                        self.port_buffers
+                            .data(current_frame_count, JACK_SAMPLE_FORMAT)
+                    }
+                } else {
+                    // Read the data from the input ports into the temporary buffer
+                    read_ports(
+                        &self.in_ports,
+                        process_scope,
+                        &mut self.temp_input_buffer,
+                        current_frame_count,
+                        self.layout,
+                    );
+                    // Create a slice of exactly current_frame_count frames
+                    temp_buffer_to_data(
+                        &mut self.temp_input_buffer,
+                        current_frame_count * num_in_channels,
+                    )
+                };
+                let timestamp = crate::InputStreamTimestamp { callback, capture };
+                let info = crate::InputCallbackInfo {
+                    timestamp,
+                    position,
+                };
+                input_callback(&data, &info);
             }
-            // Create a slice of exactly current_frame_count frames
-            let data = temp_buffer_to_data(
-                &mut self.temp_input_buffer,
-                current_frame_count * num_in_channels,
-            );
-            // Create timestamp
-            let frames_since_cycle_start = process_scope.frames_since_cycle_start() as usize;
-            let duration_since_cycle_start =
-                frames_to_duration(frames_since_cycle_start, self.sample_rate);
-            let callback = start_callback_instant
-                .add(duration_since_cycle_start)
-                .expect("`playback` occurs beyond representation supported by `StreamInstant`");
-            let capture = start_callback_instant;
-            let timestamp = crate::InputStreamTimestamp { callback, capture };
-            let info = crate::InputCallbackInfo { timestamp };
-            input_callback(&data, &info);
-        }
 
-        if let Some(output_callback) = &mut self.output_data_callback {
-            let num_out_channels = self.out_ports.len();
-
-            // Create a slice of exactly current_frame_count frames
-            let mut data = temp_buffer_to_data(
-                &mut self.temp_output_buffer,
-                current_frame_count * num_out_channels,
-            );
-            // Create timestamp
-            let frames_since_cycle_start = process_scope.frames_since_cycle_start() as usize;
-            let duration_since_cycle_start =
-                frames_to_duration(frames_since_cycle_start, self.sample_rate);
-            let callback = start_callback_instant
-                .add(duration_since_cycle_start)
-                .expect("`playback` occurs beyond representation supported by `StreamInstant`");
-            let buffer_duration = frames_to_duration(current_frame_count, self.sample_rate);
-            let playback = start_cycle_instant
-                .add(buffer_duration)
-                .expect("`playback` occurs beyond representation supported by `StreamInstant`");
-            let timestamp = crate::OutputStreamTimestamp { callback, playback };
-            let info = crate::OutputCallbackInfo { timestamp };
-            output_callback(&mut data, &info);
-
-            // Deinterlace
-            for ch_ix in 0..num_out_channels {
-                let output_channel = &mut self.out_ports[ch_ix].as_mut_slice(process_scope);
-                for i in 0..current_frame_count {
-                    output_channel[i] = self.temp_output_buffer[ch_ix + i * num_out_channels];
+            DataCallback::Output(output_callback) => {
+                let timestamp = crate::OutputStreamTimestamp { callback, playback };
+                let info = crate::OutputCallbackInfo {
+                    timestamp,
+                    position,
+                };
+                if self.layout == SampleLayout::Split {
+                    // Hand out the port buffers without copying them
+                    for (ch_ix, port) in self.out_ports.iter_mut().enumerate() {
+                        let output_channel = port.as_mut_slice(process_scope).as_mut_ptr();
+                        self.port_buffers.set(ch_ix, output_channel as *mut ());
+                    }
+                    let mut data = unsafe {
+                        self.port_buffers
+                            .data(current_frame_count, JACK_SAMPLE_FORMAT)
+                    };
+                    output_callback(&mut data, &info);
+                } else {
+                    // Create a slice of exactly current_frame_count frames
+                    let mut data = temp_buffer_to_data(
+                        &mut self.temp_output_buffer,
+                        current_frame_count * num_out_channels,
+                    );
+                    output_callback(&mut data, &info);
+
+                    write_ports(
+                        &mut self.out_ports,
+                        process_scope,
+                        &self.temp_output_buffer,
+                        current_frame_count,
+                        self.layout,
+                    );
                 }
             }
+
+            DataCallback::Duplex(duplex_callback) => {
+                read_ports(
+                    &self.in_ports,
+                    process_scope,
+                    &mut self.temp_input_buffer,
+                    current_frame_count,
+                    SampleLayout::Interleaved,
+                );
+                let input_data = temp_buffer_to_data(
+                    &mut self.temp_input_buffer,
+                    current_frame_count * num_in_channels,
+                );
+                let mut output_data = temp_buffer_to_data(
+                    &mut self.temp_output_buffer,
+                    current_frame_count * num_out_channels,
+                );
+                let timestamp = crate::DuplexStreamTimestamp {
+                    callback,
+                    capture,
+                    playback,
+                };
+                let info = crate::DuplexCallbackInfo {
+                    timestamp,
+                    position,
+                };
+                duplex_callback(&input_data, &mut output_data, &info);
+
+                write_ports(
+                    &mut self.out_ports,
+                    process_scope,
+                    &self.temp_output_buffer,
+                    current_frame_count,
+                    SampleLayout::Interleaved,
+                );
+            }
         }
+        self.stats.record_callback(start, current_frame_count);
 
         // Continue as normal
         jack::Control::Continue
@@ -397,19 +675,12 @@ impl jack::ProcessHandler for LocalProcessHandler {
     }
 }
 
-fn micros_to_stream_instant(micros: u64) -> crate::StreamInstant {
-    let nanos = micros * 1000;
-    let secs = micros / 1_000_000;
-    let subsec_nanos = nanos - secs * 1_000_000_000;
-    crate::StreamInstant::new(secs as i64, subsec_nanos as u32)
-}
-
-// Convert the given duration in frames at the given sample rate to a `std::time::Duration`.
-fn frames_to_duration(frames: usize, rate: crate::SampleRate) -> std::time::Duration {
-    let secsf = frames as f64 / rate.0 as f64;
-    let secs = secsf as u64;
-    let nanos = ((secsf - secs as f64) * 1_000_000_000.0) as u32;
-    std::time::Duration::new(secs, nanos)
+// Map a time of the JACK clock onto the epoch of `StreamInstant`, given the current time of the
+// JACK clock.
+fn micros_to_stream_instant(micros: u64, now_micros: u64) -> crate::StreamInstant {
+    crate::StreamInstant::from_clock_nanos(micros as i128 * 1000, now_micros as i128 * 1000)
+        // Only out of range for a bogus timestamp, which must not panic the audio thread.
+        .unwrap_or_else(crate::StreamInstant::now)
 }
 
 /// Receives notifications from the JACK server. It is unclear if this may be run concurrent with itself under JACK2 specs
@@ -417,13 +688,15 @@ fn frames_to_duration(frames: usize, rate: crate::SampleRate) -> std::time::Dura
 struct JackNotificationHandler {
     error_callback_ptr: ErrorCallbackPtr,
     init_sample_rate_flag: Arc<AtomicBool>,
+    xrun_occurred: Arc<AtomicBool>,
 }
 
 impl JackNotificationHandler {
-    pub fn new(error_callback_ptr: ErrorCallbackPtr) -> Self {
+    pub fn new(error_callback_ptr: ErrorCallbackPtr, xrun_occurred: Arc<AtomicBool>) -> Self {
         JackNotificationHandler {
             error_callback_ptr,
             init_sample_rate_flag: Arc::new(AtomicBool::new(false)),
+            xrun_occurred,
         }
     }
 
@@ -458,7 +731,8 @@ impl jack::NotificationHandler for JackNotificationHandler {
     }
 
     fn xrun(&mut self, _: &jack::Client) -> jack::Control {
-        self.send_error(String::from("xrun (buffer over or under run)"));
+        // The process thread reports the xrun once it knows how many frames were skipped.
+        self.xrun_occurred.store(true, Ordering::SeqCst);
         jack::Control::Continue
     }
 }
@@ -9,6 +9,22 @@ pub(crate) mod alsa;
 pub(crate) mod asio;
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 pub(crate) mod coreaudio;
+#[cfg(any(
+    feature = "dummy",
+    not(any(
+        windows,
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "emscripten",
+        target_os = "android",
+        all(target_arch = "wasm32", feature = "wasm-bindgen"),
+    ))
+))]
+pub(crate) mod dummy;
 #[cfg(target_os = "emscripten")]
 pub(crate) mod emscripten;
 #[cfg(all(
@@ -21,7 +37,6 @@ pub(crate) mod emscripten;
     feature = "jack"
 ))]
 pub(crate) mod jack;
-pub(crate) mod null;
 #[cfg(target_os = "android")]
 pub(crate) mod oboe;
 #[cfg(windows)]
@@ -1,6 +1,3 @@
-use std::convert::TryInto;
-use std::time::Duration;
-
 extern crate oboe;
 
 use crate::{
@@ -8,22 +5,22 @@ use crate::{
     StreamInstant,
 };
 
-pub fn to_stream_instant(duration: Duration) -> StreamInstant {
-    StreamInstant::new(
-        duration.as_secs().try_into().unwrap(),
-        duration.subsec_nanos(),
-    )
-}
-
 pub fn stream_instant<T: oboe::AudioStreamSafe + ?Sized>(stream: &mut T) -> StreamInstant {
-    const CLOCK_MONOTONIC: i32 = 1;
-    let ts = stream
-        .get_timestamp(CLOCK_MONOTONIC)
-        .unwrap_or(oboe::FrameTimestamp {
-            position: 0,
-            timestamp: 0,
-        });
-    to_stream_instant(Duration::from_nanos(ts.timestamp as u64))
+    match stream.get_timestamp(libc::CLOCK_MONOTONIC) {
+        Ok(ts) => {
+            // Map the timestamp onto the epoch of `StreamInstant` via the current time of the
+            // same clock.
+            let mut now = libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+            let now_nanos = now.tv_sec as i128 * 1_000_000_000 + now.tv_nsec as i128;
+            StreamInstant::from_clock_nanos(ts.timestamp as i128, now_nanos)
+                .unwrap_or_else(StreamInstant::now)
+        }
+        Err(_) => StreamInstant::now(),
+    }
 }
 
 impl From<oboe::Error> for StreamError {
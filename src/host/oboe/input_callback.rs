@@ -1,21 +1,24 @@
 use std::marker::PhantomData;
-use std::time::Instant;
+use std::sync::Arc;
 
 extern crate oboe;
 
-use super::convert::{stream_instant, to_stream_instant};
+use super::convert::stream_instant;
+use crate::position::PositionCounter;
 use crate::{Data, InputCallbackInfo, InputStreamTimestamp, SizedSample, StreamError};
 
 pub struct CpalInputCallback<I, C> {
     data_cb: Box<dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static>,
     error_cb: Box<dyn FnMut(StreamError) + Send + 'static>,
-    created: Instant,
+    position: Arc<PositionCounter>,
+    // The number of xruns reported by the stream so far.
+    xruns: i32,
     phantom_channel: PhantomData<C>,
     phantom_input: PhantomData<I>,
 }
 
 impl<I, C> CpalInputCallback<I, C> {
-    pub fn new<D, E>(data_cb: D, error_cb: E) -> Self
+    pub fn new<D, E>(data_cb: D, error_cb: E, position: Arc<PositionCounter>) -> Self
     where
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
@@ -23,21 +26,31 @@ impl<I, C> CpalInputCallback<I, C> {
         Self {
             data_cb: Box::new(data_cb),
             error_cb: Box::new(error_cb),
-            created: Instant::now(),
+            position,
+            xruns: 0,
             phantom_channel: PhantomData,
             phantom_input: PhantomData,
         }
     }
 
     fn make_callback_info(
-        &self,
+        &mut self,
         audio_stream: &mut dyn oboe::AudioInputStreamSafe,
+        frames: usize,
     ) -> InputCallbackInfo {
+        // Flag the buffer following new xruns of the stream.
+        if let Ok(xruns) = audio_stream.get_xrun_count() {
+            if xruns > self.xruns {
+                self.position.mark_discontinuity();
+            }
+            self.xruns = xruns;
+        }
         InputCallbackInfo {
             timestamp: InputStreamTimestamp {
-                callback: to_stream_instant(self.created.elapsed()),
+                callback: crate::StreamInstant::now(),
                 capture: stream_instant(audio_stream),
             },
+            position: self.position.advance(frames),
         }
     }
 }
@@ -69,7 +82,7 @@ where
         audio_stream: &mut dyn oboe::AudioInputStreamSafe,
         audio_data: &[<<Self as oboe::AudioInputCallback>::FrameType as oboe::IsFrameType>::Type],
     ) -> oboe::DataCallbackResult {
-        let cb_info = self.make_callback_info(audio_stream);
+        let cb_info = self.make_callback_info(audio_stream, audio_data.len());
         let channel_count = if C::CHANNEL_COUNT == oboe::ChannelCount::Mono {
             1
         } else {
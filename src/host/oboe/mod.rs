@@ -1,18 +1,20 @@
 use std::cell::RefCell;
 use std::cmp;
 use std::convert::TryInto;
+use std::sync::Arc;
 use std::time::Duration;
 use std::vec::IntoIter as VecIntoIter;
 
 extern crate oboe;
 
+use crate::position::PositionCounter;
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
     BackendSpecificError, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
     DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleFormat, SampleRate, SizedSample, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    PlayStreamError, SampleFormat, SampleLayout, SampleRate, SizedSample, StreamConfig,
+    StreamError, StreamPositionError, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 
 mod android_media;
@@ -39,9 +41,10 @@ const SAMPLE_RATES: [i32; 13] = [
 pub struct Host;
 #[derive(Clone)]
 pub struct Device(Option<oboe::AudioDeviceInfo>);
+// The streams hold the number of frames passed to or from the data callback.
 pub enum Stream {
-    Input(Box<RefCell<dyn AudioInputStream>>),
-    Output(Box<RefCell<dyn AudioOutputStream>>),
+    Input(Box<RefCell<dyn AudioInputStream>>, Arc<PositionCounter>),
+    Output(Box<RefCell<dyn AudioOutputStream>>, Arc<PositionCounter>),
 }
 pub type SupportedInputConfigs = VecIntoIter<SupportedStreamConfigRange>;
 pub type SupportedOutputConfigs = VecIntoIter<SupportedStreamConfigRange>;
@@ -234,13 +237,15 @@ where
     E: FnMut(StreamError) + Send + 'static,
 {
     let builder = configure_for_device(builder, device, config);
+    let position = Arc::new(PositionCounter::default());
     let stream = builder
         .set_callback(CpalInputCallback::<T, C>::new(
             data_callback,
             error_callback,
+            position.clone(),
         ))
         .open_stream()?;
-    Ok(Stream::Input(Box::new(RefCell::new(stream))))
+    Ok(Stream::Input(Box::new(RefCell::new(stream)), position))
 }
 
 fn build_output_stream<D, E, C, T>(
@@ -258,13 +263,15 @@ where
     E: FnMut(StreamError) + Send + 'static,
 {
     let builder = configure_for_device(builder, device, config);
+    let position = Arc::new(PositionCounter::default());
     let stream = builder
         .set_callback(CpalOutputCallback::<T, C>::new(
             data_callback,
             error_callback,
+            position.clone(),
         ))
         .open_stream()?;
-    Ok(Stream::Output(Box::new(RefCell::new(stream))))
+    Ok(Stream::Output(Box::new(RefCell::new(stream)), position))
 }
 
 impl DeviceTrait for Device {
@@ -327,12 +334,22 @@ impl DeviceTrait for Device {
         sample_format: SampleFormat,
         data_callback: D,
         error_callback: E,
-        _timeout: Option<Duration>,
+        timeout: Option<Duration>,
     ) -> Result<Self::Stream, BuildStreamError>
     where
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_input_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         match sample_format {
             SampleFormat::I16 => {
                 let builder = oboe::AudioStreamBuilder::default()
@@ -401,12 +418,22 @@ impl DeviceTrait for Device {
         sample_format: SampleFormat,
         data_callback: D,
         error_callback: E,
-        _timeout: Option<Duration>,
+        timeout: Option<Duration>,
     ) -> Result<Self::Stream, BuildStreamError>
     where
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_output_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         match sample_format {
             SampleFormat::I16 => {
                 let builder = oboe::AudioStreamBuilder::default()
@@ -473,11 +500,11 @@ impl DeviceTrait for Device {
 impl StreamTrait for Stream {
     fn play(&self) -> Result<(), PlayStreamError> {
         match self {
-            Self::Input(stream) => stream
+            Self::Input(stream, _) => stream
                 .borrow_mut()
                 .request_start()
                 .map_err(PlayStreamError::from),
-            Self::Output(stream) => stream
+            Self::Output(stream, _) => stream
                 .borrow_mut()
                 .request_start()
                 .map_err(PlayStreamError::from),
@@ -486,14 +513,19 @@ impl StreamTrait for Stream {
 
     fn pause(&self) -> Result<(), PauseStreamError> {
         match self {
-            Self::Input(_) => Err(BackendSpecificError {
+            Self::Input(..) => Err(BackendSpecificError {
                 description: "Pause called on the input stream.".to_owned(),
             }
             .into()),
-            Self::Output(stream) => stream
+            Self::Output(stream, _) => stream
                 .borrow_mut()
                 .request_pause()
                 .map_err(PauseStreamError::from),
         }
     }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        let (Self::Input(_, position) | Self::Output(_, position)) = self;
+        Ok(position.frames())
+    }
 }
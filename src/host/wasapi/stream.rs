@@ -1,17 +1,20 @@
 use super::windows_err_to_cpal_err;
+use crate::position::PositionCounter;
 use crate::traits::StreamTrait;
 use crate::{
-    BackendSpecificError, Data, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleFormat, StreamError,
+    frames_to_duration, BackendSpecificError, Data, InputCallbackInfo, OutputCallbackInfo,
+    PauseStreamError, PlayStreamError, SampleFormat, StreamError, StreamPositionError,
 };
 use std::mem;
 use std::ptr;
 use std::sync::mpsc::{channel, Receiver, SendError, Sender};
+use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 use windows::Win32::Foundation;
 use windows::Win32::Foundation::HANDLE;
 use windows::Win32::Foundation::WAIT_OBJECT_0;
 use windows::Win32::Media::Audio;
+use windows::Win32::System::Performance;
 use windows::Win32::System::SystemServices;
 use windows::Win32::System::Threading;
 
@@ -30,6 +33,9 @@ pub struct Stream {
     // This event is signalled after a new entry is added to `commands`, so that the `run()`
     // method can be notified.
     pending_scheduled_event: Foundation::HANDLE,
+
+    // The number of frames passed to or from the data callback, shared with the `StreamInner`.
+    position: Arc<PositionCounter>,
 }
 
 struct RunContext {
@@ -77,6 +83,8 @@ pub struct StreamInner {
     pub config: crate::StreamConfig,
     // The sample format with which the stream was created.
     pub sample_format: SampleFormat,
+    // The number of frames passed to or from the data callback.
+    pub position: Arc<PositionCounter>,
 }
 
 impl Stream {
@@ -94,6 +102,7 @@ impl Stream {
         }
         .expect("cpal: could not create input stream event");
         let (tx, rx) = channel();
+        let position = stream_inner.position.clone();
 
         let run_context = RunContext {
             handles: vec![pending_scheduled_event, stream_inner.event],
@@ -110,6 +119,7 @@ impl Stream {
             thread: Some(thread),
             commands: tx,
             pending_scheduled_event,
+            position,
         }
     }
 
@@ -127,6 +137,7 @@ impl Stream {
         }
         .expect("cpal: could not create output stream event");
         let (tx, rx) = channel();
+        let position = stream_inner.position.clone();
 
         let run_context = RunContext {
             handles: vec![pending_scheduled_event, stream_inner.event],
@@ -143,6 +154,7 @@ impl Stream {
             thread: Some(thread),
             commands: tx,
             pending_scheduled_event,
+            position,
         }
     }
 
@@ -179,6 +191,10 @@ impl StreamTrait for Stream {
             .map_err(|_| crate::error::PauseStreamError::DeviceNotAvailable)?;
         Ok(())
     }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.position.frames())
+    }
 }
 
 impl Drop for StreamInner {
@@ -424,7 +440,13 @@ fn process_input(
                     return ControlFlow::Break;
                 }
             };
-            let info = InputCallbackInfo { timestamp };
+            if flags.assume_init() & Audio::AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY.0 as u32 != 0 {
+                stream.position.mark_discontinuity();
+            }
+            let info = InputCallbackInfo {
+                timestamp,
+                position: stream.position.advance(frames_available as usize),
+            };
             data_callback(&data, &info);
 
             // Release the buffer.
@@ -479,7 +501,15 @@ fn process_output(
                 return ControlFlow::Break;
             }
         };
-        let info = OutputCallbackInfo { timestamp };
+        // WASAPI does not report underruns of render clients, but the buffer only runs empty
+        // while playing if the device played silence in the meantime.
+        if frames_available == stream.max_frames_in_buffer && stream.position.frames() > 0 {
+            stream.position.mark_discontinuity();
+        }
+        let info = OutputCallbackInfo {
+            timestamp,
+            position: stream.position.advance(frames_available as usize),
+        };
         data_callback(&mut data, &info);
 
         if let Err(err) = render_client.ReleaseBuffer(frames_available, 0) {
@@ -491,14 +521,6 @@ fn process_output(
     ControlFlow::Continue
 }
 
-/// Convert the given duration in frames at the given sample rate to a `std::time::Duration`.
-fn frames_to_duration(frames: u32, rate: crate::SampleRate) -> std::time::Duration {
-    let secsf = frames as f64 / rate.0 as f64;
-    let secs = secsf as u64;
-    let nanos = ((secsf - secs as f64) * 1_000_000_000.0) as u32;
-    std::time::Duration::new(secs, nanos)
-}
-
 /// Use the stream's `IAudioClock` to produce the current stream instant.
 ///
 /// Uses the QPC position produced via the `GetPosition` method.
@@ -511,11 +533,24 @@ fn stream_instant(stream: &StreamInner) -> Result<crate::StreamInstant, StreamEr
             .GetPosition(&mut position, Some(&mut qpc_position))
             .map_err(windows_err_to_cpal_err::<StreamError>)?;
     };
+    Ok(qpc_position_to_stream_instant(qpc_position))
+}
+
+/// Map a `qpc_position` onto the epoch of `StreamInstant` via the current performance counter.
+fn qpc_position_to_stream_instant(qpc_position: u64) -> crate::StreamInstant {
+    let mut counter = 0;
+    let mut frequency = 0;
+    unsafe {
+        // These cannot fail on Windows XP and later.
+        let _ = Performance::QueryPerformanceCounter(&mut counter);
+        let _ = Performance::QueryPerformanceFrequency(&mut frequency);
+    }
+    let now_nanos = counter as i128 * 1_000_000_000 / frequency.max(1) as i128;
     // The `qpc_position` is in 100 nanosecond units. Convert it to nanoseconds.
     let qpc_nanos = qpc_position as i128 * 100;
-    let instant = crate::StreamInstant::from_nanos_i128(qpc_nanos)
-        .expect("performance counter out of range of `StreamInstant` representation");
-    Ok(instant)
+    crate::StreamInstant::from_clock_nanos(qpc_nanos, now_nanos)
+        // Only out of range for a bogus timestamp, which must not panic the audio thread.
+        .unwrap_or_else(crate::StreamInstant::now)
 }
 
 /// Produce the input stream timestamp.
@@ -527,10 +562,7 @@ fn input_timestamp(
     stream: &StreamInner,
     buffer_qpc_position: u64,
 ) -> Result<crate::InputStreamTimestamp, StreamError> {
-    // The `qpc_position` is in 100 nanosecond units. Convert it to nanoseconds.
-    let qpc_nanos = buffer_qpc_position as i128 * 100;
-    let capture = crate::StreamInstant::from_nanos_i128(qpc_nanos)
-        .expect("performance counter out of range of `StreamInstant` representation");
+    let capture = qpc_position_to_stream_instant(buffer_qpc_position);
     let callback = stream_instant(stream)?;
     Ok(crate::InputStreamTimestamp { capture, callback })
 }
@@ -551,7 +583,7 @@ fn output_timestamp(
     sample_rate: crate::SampleRate,
 ) -> Result<crate::OutputStreamTimestamp, StreamError> {
     let callback = stream_instant(stream)?;
-    let buffer_duration = frames_to_duration(frames_available, sample_rate);
+    let buffer_duration = frames_to_duration(frames_available as usize, sample_rate);
     let playback = callback
         .add(buffer_duration)
         .expect("`playback` occurs beyond representation supported by `StreamInstant`");
@@ -6,12 +6,14 @@ use self::js_sys::eval;
 use self::wasm_bindgen::prelude::*;
 use self::wasm_bindgen::JsCast;
 use self::web_sys::{AudioContext, AudioContextOptions};
+use crate::position::PositionCounter;
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
     BackendSpecificError, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
     DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError, SupportedBufferSize,
-    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError,
+    PlayStreamError, SampleFormat, SampleLayout, SampleRate, StreamConfig, StreamError,
+    StreamPositionError, SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+    SupportedStreamConfigsError,
 };
 use std::ops::DerefMut;
 use std::sync::{Arc, Mutex, RwLock};
@@ -30,6 +32,7 @@ pub struct Stream {
     on_ended_closures: Vec<Arc<RwLock<Option<Closure<dyn FnMut()>>>>>,
     config: StreamConfig,
     buffer_size_frames: usize,
+    position: Arc<PositionCounter>,
 }
 
 pub type SupportedInputConfigs = ::std::vec::IntoIter<SupportedStreamConfigRange>;
@@ -189,13 +192,23 @@ impl DeviceTrait for Device {
         config: &StreamConfig,
         sample_format: SampleFormat,
         data_callback: D,
-        _error_callback: E,
-        _timeout: Option<Duration>,
+        error_callback: E,
+        timeout: Option<Duration>,
     ) -> Result<Self::Stream, BuildStreamError>
     where
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout != SampleLayout::Interleaved {
+            return crate::planar::build_output_stream_planar_fallback(
+                self,
+                config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            );
+        }
         if !valid_config(config, sample_format) {
             return Err(BuildStreamError::StreamConfigNotSupported);
         }
@@ -239,12 +252,18 @@ impl DeviceTrait for Device {
 
         let ctx = Arc::new(ctx);
 
+        // The offset of the context time onto the epoch of `StreamInstant`s.
+        let epoch_offset = crate::StreamInstant::clock_offset_secs(ctx.current_time());
+
         // A container for managing the lifecycle of the audio callbacks.
         let mut on_ended_closures: Vec<Arc<RwLock<Option<Closure<dyn FnMut()>>>>> = Vec::new();
 
         // A cursor keeping track of the current time at which new frames should be scheduled.
         let time = Arc::new(RwLock::new(0f64));
 
+        // The number of frames scheduled so far, shared by all workers.
+        let position = Arc::new(PositionCounter::default());
+
         // Create a set of closures / callbacks which will continuously fetch and schedule sample
         // playback. Starting with two workers, e.g. a front and back buffer so that audio frames
         // can be fetched in the background.
@@ -252,6 +271,7 @@ impl DeviceTrait for Device {
             let data_callback_handle = data_callback.clone();
             let ctx_handle = ctx.clone();
             let time_handle = time.clone();
+            let position_handle = position.clone();
 
             // A set of temporary buffers to be used for intermediate sample transformation steps.
             let mut temporary_buffer = vec![0f32; buffer_size_samples];
@@ -311,10 +331,15 @@ impl DeviceTrait for Device {
                         let data = temporary_buffer.as_mut_ptr() as *mut ();
                         let mut data = unsafe { Data::from_parts(data, len, sample_format) };
                         let mut data_callback = data_callback_handle.lock().unwrap();
-                        let callback = crate::StreamInstant::from_secs_f64(now);
-                        let playback = crate::StreamInstant::from_secs_f64(time_at_start_of_buffer);
+                        let callback = crate::StreamInstant::from_secs_f64(now + epoch_offset);
+                        let playback = crate::StreamInstant::from_secs_f64(
+                            time_at_start_of_buffer + epoch_offset,
+                        );
                         let timestamp = crate::OutputStreamTimestamp { callback, playback };
-                        let info = OutputCallbackInfo { timestamp };
+                        let info = OutputCallbackInfo {
+                            timestamp,
+                            position: position_handle.advance(buffer_size_frames),
+                        };
                         (data_callback.deref_mut())(&mut data, &info);
                     }
 
@@ -390,6 +415,7 @@ impl DeviceTrait for Device {
             on_ended_closures,
             config: config.clone(),
             buffer_size_frames,
+            position,
         })
     }
 }
@@ -448,6 +474,10 @@ impl StreamTrait for Stream {
             }
         }
     }
+
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Ok(self.position.frames())
+    }
 }
 
 impl Drop for Stream {
@@ -164,24 +164,194 @@ extern crate js_sys;
 #[cfg(target_os = "emscripten")]
 extern crate web_sys;
 
+pub use adapt::{AdapterConfig, ChannelMapping};
+pub use block::{fixed_block_input, fixed_block_output};
+pub use convert::Dither;
+pub use duplex::DuplexStream;
 pub use error::*;
+pub use group::StreamGroup;
+pub use mix::ChannelMix;
+pub use planar::PlanarData;
 pub use platform::{
     available_hosts, default_host, host_from_id, Device, Devices, Host, HostId, Stream,
     SupportedInputConfigs, SupportedOutputConfigs, ALL_HOSTS,
 };
 pub use samples_formats::{FromSample, Sample, SampleFormat, SizedSample, I24, I48, U24, U48};
+pub use schedule::{scheduled_output, StartAccuracy, StartHandle};
 use std::convert::TryInto;
 use std::ops::{Div, Mul};
-use std::time::Duration;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 #[cfg(target_os = "emscripten")]
 use wasm_bindgen::prelude::*;
 
+mod adapt;
+mod block;
+mod buffer;
+mod convert;
+mod duplex;
 mod error;
+mod group;
 mod host;
+mod mix;
+mod planar;
 pub mod platform;
+mod position;
+mod resample;
 mod samples_formats;
+mod schedule;
+mod stats;
 pub mod traits;
 
+/// A stable identifier of a device, returned by [`DeviceTrait::id`](traits::DeviceTrait::id).
+///
+/// It is made up of the host of the device and a host-specific string, e.g. the PCM name on ALSA
+/// or the client name on JACK. Device ids can be stored, e.g. in a configuration file, via their
+/// string representation and the device can be looked up again via
+/// [`HostTrait::device_by_id`](traits::HostTrait::device_by_id).
+///
+/// The string representation is the name of the host, followed by a colon and the host-specific
+/// string, e.g. `ALSA:plughw:CARD=PCH`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct DeviceId {
+    host: HostId,
+    id: String,
+}
+
+impl DeviceId {
+    /// Create a device id from the id of its host and a host-specific string.
+    pub fn new(host: HostId, id: &str) -> Self {
+        DeviceId {
+            host,
+            id: id.to_owned(),
+        }
+    }
+
+    /// The id of the host the device belongs to.
+    pub fn host(&self) -> HostId {
+        self.host
+    }
+
+    /// The host-specific part of the device id.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl std::fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.host.name(), self.id)
+    }
+}
+
+impl std::str::FromStr for DeviceId {
+    type Err = ParseDeviceIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, id) = s.split_once(':').ok_or(ParseDeviceIdError)?;
+        let host = ALL_HOSTS
+            .iter()
+            .find(|host_id| host_id.name() == host)
+            .ok_or(ParseDeviceIdError)?;
+        Ok(DeviceId::new(*host, id))
+    }
+}
+
+/// Metadata describing a device, returned by
+/// [`DeviceTrait::description`](traits::DeviceTrait::description).
+///
+/// Hosts fill in what they know about a device and leave the remaining fields at their defaults.
+/// More fields may be added in the future.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct DeviceDescription {
+    /// The human-readable name of the device, as returned by
+    /// [`DeviceTrait::name`](traits::DeviceTrait::name).
+    pub name: String,
+    /// A longer, more descriptive name of the device.
+    pub long_name: Option<String>,
+    /// The name of the driver of the device.
+    pub driver: Option<String>,
+    /// The index of the sound card the device belongs to.
+    pub card_index: Option<u32>,
+    /// The index of the device on its sound card.
+    pub device_index: Option<u32>,
+    /// The index of the subdevice of the device.
+    pub subdevice_index: Option<u32>,
+    /// How the device is connected to the system.
+    pub interface: DeviceInterface,
+    /// Whether the device is provided by a software layer such as a sound server or a plugin,
+    /// rather than directly by the hardware.
+    pub is_plugin: bool,
+}
+
+/// How a device is connected to the system, see [`DeviceDescription::interface`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum DeviceInterface {
+    /// The interface is not known.
+    #[default]
+    Unknown,
+    /// A device on the PCI bus, e.g. an onboard or internal sound card.
+    Pci,
+    /// A USB audio interface.
+    Usb,
+    /// A device without hardware of its own, e.g. a sound server or a software mixer.
+    Virtual,
+}
+
+/// A change to the devices available on a host, delivered via
+/// [`HostTrait::watch_devices`](traits::HostTrait::watch_devices).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum DeviceEvent {
+    /// A device has become available. Contains the id of the device, which can be looked up via
+    /// [`HostTrait::device_by_id`](traits::HostTrait::device_by_id).
+    Added(DeviceId),
+    /// A device is no longer available. Contains the id of the device, as returned by
+    /// [`DeviceTrait::id`](traits::DeviceTrait::id) while it was available.
+    Removed(DeviceId),
+    /// The default input or output device has changed, or now refers to different hardware. The
+    /// new defaults are returned by
+    /// [`HostTrait::default_input_device`](traits::HostTrait::default_input_device) and
+    /// [`HostTrait::default_output_device`](traits::HostTrait::default_output_device).
+    DefaultChanged,
+}
+
+/// A handle to an active device watch, created via
+/// [`HostTrait::watch_devices`](traits::HostTrait::watch_devices).
+///
+/// No more [`DeviceEvent`]s are delivered once the watcher is dropped. The watcher may also be
+/// dropped from within the callback, e.g. after the awaited device has been added.
+#[must_use = "If the watcher is not stored no device events will be delivered."]
+pub struct DeviceWatcher {
+    stop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl DeviceWatcher {
+    // Internal constructor for host implementations to use.
+    //
+    // `stop` is called when the watcher is dropped and must not return before the host has
+    // stopped calling the user's callback. If it is called from within the callback, it must not
+    // wait for the callback to return, but no further events may be delivered.
+    #[allow(dead_code)]
+    pub(crate) fn new<F>(stop: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        DeviceWatcher {
+            stop: Some(Box::new(stop)),
+        }
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
 /// A host's device iterator yielding only *input* devices.
 pub type InputDevices<I> = std::iter::Filter<I, fn(&<I as Iterator>::Item) -> bool>;
 
@@ -253,6 +423,40 @@ impl wasm_bindgen::convert::IntoWasmAbi for BufferSize {
     }
 }
 
+/// How the samples of the channels of a stream are arranged in the buffers passed to its data
+/// callback.
+///
+/// Hosts that do not support the requested layout natively convert from or to the layout of the
+/// device, which costs a copy of every buffer.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum SampleLayout {
+    /// The samples of a frame are stored next to each other, e.g. `LRLRLR` for stereo.
+    #[default]
+    Interleaved,
+    /// The samples of a channel are stored next to each other, one channel after the other, e.g.
+    /// `LLLRRR` for stereo. Every channel holds `data.len() / channels` samples.
+    Planar,
+    /// The samples of every channel are stored in a buffer of their own, accessed through
+    /// [`Data::planar`]. JACK hands out its port buffers this way without copying them.
+    ///
+    /// Only raw stream callbacks can access split data, so the typed stream builders fail with
+    /// [`BuildStreamError::StreamConfigNotSupported`] for this layout.
+    Split,
+}
+
+#[cfg(target_os = "emscripten")]
+impl wasm_bindgen::describe::WasmDescribe for SampleLayout {
+    fn describe() {}
+}
+
+#[cfg(target_os = "emscripten")]
+impl wasm_bindgen::convert::IntoWasmAbi for SampleLayout {
+    type Abi = u32;
+    fn into_abi(self) -> Self::Abi {
+        self as u32
+    }
+}
+
 /// The set of parameters used to describe how to open a stream.
 ///
 /// The sample format is omitted in favour of using a sample type.
@@ -262,6 +466,60 @@ pub struct StreamConfig {
     pub channels: ChannelCount,
     pub sample_rate: SampleRate,
     pub buffer_size: BufferSize,
+    pub layout: SampleLayout,
+}
+
+/// The set of parameters used to describe how to open a full-duplex stream.
+///
+/// Both directions of a duplex stream share the same sample rate, buffer size and sample format,
+/// but may have a different number of channels.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuplexStreamConfig {
+    pub input_channels: ChannelCount,
+    pub output_channels: ChannelCount,
+    pub sample_rate: SampleRate,
+    pub buffer_size: BufferSize,
+}
+
+/// The buffer size a stream was built with, as negotiated with the host. This may differ from the
+/// [`BufferSize`] requested in the stream config.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StreamBufferSize {
+    /// The size of the buffer of the device in frames, e.g. the ring buffer on ALSA or the period
+    /// on JACK.
+    pub buffer_frames: FrameCount,
+    /// The number of frames the device processes per wakeup, e.g. the period on ALSA or the
+    /// buffer on JACK. The buffer holds `buffer_frames / period_frames` periods.
+    pub period_frames: FrameCount,
+    /// The maximum number of frames passed to a single data callback. Buffers of this size can be
+    /// allocated before the stream starts and only have to grow in the callback if the host
+    /// changes its buffer size while the stream runs, as the JACK server may.
+    pub max_callback_frames: FrameCount,
+}
+
+/// Timing statistics of the data callback of a stream, collected since the stream was built. See
+/// [`StreamTrait::stats`](traits::StreamTrait::stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StreamStats {
+    /// The number of calls of the data callback.
+    pub callbacks: u64,
+    /// The shortest time a call of the data callback took.
+    pub min_callback_duration: Duration,
+    /// The average time a call of the data callback took.
+    pub avg_callback_duration: Duration,
+    /// The longest time a call of the data callback took.
+    pub max_callback_duration: Duration,
+    /// The time spent in the data callback as a fraction of the duration of the frames it
+    /// processed, i.e. of the buffer period, on average. Streams glitch when this approaches
+    /// `1.0`, as the callback then cannot keep up with the device.
+    pub avg_load: f64,
+    /// The highest load of a single call of the data callback.
+    pub max_load: f64,
+    /// The number of buffer underruns and overruns.
+    pub xruns: u64,
+    /// The number of times the stream woke up more than half a buffer period late, leaving the
+    /// data callback less time than usual to meet its deadline.
+    pub late_wakeups: u64,
 }
 
 /// Describes the minimum and maximum supported buffer size for the device
@@ -305,13 +563,14 @@ pub struct SupportedStreamConfig {
 /// A buffer of dynamically typed audio data, passed to raw stream callbacks.
 ///
 /// Raw input stream callbacks receive `&Data`, while raw output stream callbacks expect `&mut
-/// Data`.
+/// Data`. The samples are arranged according to the [`SampleLayout`] of the stream config.
 #[cfg_attr(target_os = "emscripten", wasm_bindgen)]
 #[derive(Debug)]
 pub struct Data {
     data: *mut (),
     len: usize,
     sample_format: SampleFormat,
+    planar: Option<PlanarData>,
 }
 
 /// A monotonic time instance associated with a stream, retrieved from either:
@@ -320,18 +579,30 @@ pub struct Data {
 /// 2. The same time source used to generate timestamps for a stream's underlying audio data
 ///    callback.
 ///
-/// `StreamInstant` represents a duration since some unspecified origin occurring either before
-/// or equal to the moment the stream from which it was created begins.
+/// `StreamInstant` represents a duration since an epoch shared by all streams of the process, so
+/// that instants of different streams, e.g. of an input and an output stream, can be compared.
+/// The epoch is the first time an instant is taken. Instants can be converted to and from
+/// [`std::time::Instant`] with [`StreamInstant::to_instant`] and [`StreamInstant::from_instant`].
 ///
 /// ## Host `StreamInstant` Sources
 ///
+/// Hosts take timestamps from the clocks below and map them onto the epoch.
+///
 /// | Host | Source |
 /// | ---- | ------ |
 /// | alsa | `snd_pcm_status_get_htstamp` |
+/// | jack | `jack_get_cycle_times` |
 /// | coreaudio | `mach_absolute_time` |
 /// | wasapi | `QueryPerformanceCounter` |
 /// | asio | `timeGetTime` |
-/// | emscripten | `AudioContext.getOutputTimestamp` |
+/// | oboe | `CLOCK_MONOTONIC` |
+/// | emscripten | `AudioContext.currentTime` |
+/// | webaudio | `AudioContext.currentTime` |
+/// | dummy | `std::time::Instant`, or the frames advanced by a manual clock |
+///
+/// The web hosts map the time of the `AudioContext` onto the epoch when the stream is built.
+/// `std::time::Instant` is not available on `wasm32-unknown-unknown`, so the epoch is the time
+/// origin of the page there, see [`StreamInstant::now`].
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct StreamInstant {
     secs: i64,
@@ -360,10 +631,33 @@ pub struct OutputStreamTimestamp {
     pub playback: StreamInstant,
 }
 
+/// A timestamp associated with a call to a duplex stream's data callback.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct DuplexStreamTimestamp {
+    /// The instant the stream's data callback was invoked.
+    pub callback: StreamInstant,
+    /// The instant that the input data was captured from the device.
+    pub capture: StreamInstant,
+    /// The predicted instant that the output data will be delivered to the device for playback.
+    pub playback: StreamInstant,
+}
+
+/// The position of the buffer passed to a data callback within the stream.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct StreamPosition {
+    /// The number of frames passed to or from the data callback since the stream was built, i.e.
+    /// the index of the first frame of the buffer.
+    pub frames: u64,
+    /// Whether frames were lost between the previous buffer and this one, e.g. due to an xrun.
+    /// Frames lost are not counted, so `frames` no longer reflects the time the stream has run.
+    pub discontinuity: bool,
+}
+
 /// Information relevant to a single call to the user's input stream data callback.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InputCallbackInfo {
     timestamp: InputStreamTimestamp,
+    position: StreamPosition,
 }
 
 /// Information relevant to a single call to the user's output stream data callback.
@@ -371,6 +665,14 @@ pub struct InputCallbackInfo {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OutputCallbackInfo {
     timestamp: OutputStreamTimestamp,
+    position: StreamPosition,
+}
+
+/// Information relevant to a single call to the user's duplex stream data callback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplexCallbackInfo {
+    timestamp: DuplexStreamTimestamp,
+    position: StreamPosition,
 }
 
 impl SupportedStreamConfig {
@@ -409,11 +711,61 @@ impl SupportedStreamConfig {
             channels: self.channels,
             sample_rate: self.sample_rate,
             buffer_size: BufferSize::Default,
+            layout: SampleLayout::Interleaved,
         }
     }
 }
 
 impl StreamInstant {
+    /// The current instant.
+    ///
+    /// This is [`std::time::Instant::now`] converted with [`StreamInstant::from_instant`]. On
+    /// `wasm32-unknown-unknown`, where `std::time::Instant` is unavailable, it is the time since
+    /// the time origin of the page as per `performance.now()` instead.
+    pub fn now() -> Self {
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        {
+            let millis = web_sys::window()
+                .and_then(|window| window.performance())
+                .map_or_else(js_sys::Date::now, |performance| performance.now());
+            Self::from_secs_f64(millis / 1000.0)
+        }
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        Self::from_instant(Instant::now())
+    }
+
+    /// The stream instant corresponding to the given [`std::time::Instant`].
+    ///
+    /// Saturates at the earliest or latest instant `StreamInstant` can represent, which lie
+    /// billions of years before and after the epoch.
+    pub fn from_instant(instant: Instant) -> Self {
+        let epoch = stream_epoch();
+        let nanos = match instant.checked_duration_since(epoch) {
+            Some(duration) => duration.as_nanos() as i128,
+            None => -(epoch.duration_since(instant).as_nanos() as i128),
+        };
+        Self::from_nanos_i128(nanos).unwrap_or(if nanos < 0 {
+            Self::new(i64::MIN, 0)
+        } else {
+            Self::new(i64::MAX, 999_999_999)
+        })
+    }
+
+    /// The [`std::time::Instant`] corresponding to this stream instant.
+    ///
+    /// Returns `None` if the instant cannot be represented by `std::time::Instant`, e.g. because
+    /// it lies before the boot of the machine.
+    pub fn to_instant(&self) -> Option<Instant> {
+        let epoch = stream_epoch();
+        let nanos = self.as_nanos();
+        let duration = Duration::from_nanos(nanos.unsigned_abs().try_into().ok()?);
+        if nanos < 0 {
+            epoch.checked_sub(duration)
+        } else {
+            epoch.checked_add(duration)
+        }
+    }
+
     /// The amount of time elapsed from another instant to this one.
     ///
     /// Returns `None` if `earlier` is later than self.
@@ -455,23 +807,40 @@ impl StreamInstant {
 
     #[allow(dead_code)]
     fn from_nanos(nanos: i64) -> Self {
-        let secs = nanos / 1_000_000_000;
-        let subsec_nanos = nanos - secs * 1_000_000_000;
+        let secs = nanos.div_euclid(1_000_000_000);
+        let subsec_nanos = nanos.rem_euclid(1_000_000_000);
         Self::new(secs, subsec_nanos as u32)
     }
 
     #[allow(dead_code)]
     fn from_nanos_i128(nanos: i128) -> Option<Self> {
-        let secs = nanos / 1_000_000_000;
+        let secs = nanos.div_euclid(1_000_000_000);
         if secs > i64::MAX as i128 || secs < i64::MIN as i128 {
             None
         } else {
-            let subsec_nanos = nanos - secs * 1_000_000_000;
-            debug_assert!(subsec_nanos < u32::MAX as i128);
+            let subsec_nanos = nanos.rem_euclid(1_000_000_000);
             Some(Self::new(secs as i64, subsec_nanos as u32))
         }
     }
 
+    // The instant of a timestamp `stamp_nanos` of another monotonic clock, which reads `now_nanos`
+    // at the current instant.
+    #[allow(dead_code)]
+    fn from_clock_nanos(stamp_nanos: i128, now_nanos: i128) -> Option<Self> {
+        Self::now()
+            .as_nanos()
+            .checked_sub(now_nanos - stamp_nanos)
+            .and_then(Self::from_nanos_i128)
+    }
+
+    // The offset to add to a time in seconds of another clock, which reads `now_secs` at the
+    // current instant, to map it onto the epoch of `StreamInstant`s, e.g. for the `AudioContext`
+    // time of the web hosts.
+    #[allow(dead_code)]
+    fn clock_offset_secs(now_secs: f64) -> f64 {
+        Self::now().as_nanos() as f64 / 1e9 - now_secs
+    }
+
     #[allow(dead_code)]
     fn from_secs_f64(secs: f64) -> crate::StreamInstant {
         let s = secs.floor() as i64;
@@ -484,11 +853,49 @@ impl StreamInstant {
     }
 }
 
+// The epoch of all `StreamInstant`s, set the first time it is needed.
+fn stream_epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+// Convert the given duration in frames at the given sample rate to a `std::time::Duration`.
+pub(crate) fn frames_to_duration(frames: usize, rate: SampleRate) -> Duration {
+    let secsf = frames as f64 / rate.0 as f64;
+    let secs = secsf as u64;
+    let nanos = ((secsf - secs as f64) * 1_000_000_000.0) as u32;
+    Duration::new(secs, nanos)
+}
+
+// The number of frames per callback assumed for streams that do not report their buffer size.
+const DEFAULT_CALLBACK_FRAMES: usize = 4096;
+
+// The number of frames `stream` is expected to pass to a single call of its data callback at
+// most, for sizing buffers before the stream is played. `buffer_size` is the requested buffer
+// size, used if the stream does not report it.
+pub(crate) fn max_callback_frames<S: traits::StreamTrait>(
+    stream: &S,
+    buffer_size: BufferSize,
+) -> usize {
+    match stream.buffer_size() {
+        Ok(size) => size.max_callback_frames as usize,
+        Err(_) => match buffer_size {
+            BufferSize::Fixed(frames) => frames as usize,
+            BufferSize::Default => DEFAULT_CALLBACK_FRAMES,
+        },
+    }
+}
+
 impl InputCallbackInfo {
     /// The timestamp associated with the call to an input stream's data callback.
     pub fn timestamp(&self) -> InputStreamTimestamp {
         self.timestamp
     }
+
+    /// The position of the captured buffer within the stream.
+    pub fn position(&self) -> StreamPosition {
+        self.position
+    }
 }
 
 impl OutputCallbackInfo {
@@ -496,6 +903,45 @@ impl OutputCallbackInfo {
     pub fn timestamp(&self) -> OutputStreamTimestamp {
         self.timestamp
     }
+
+    /// The position of the requested buffer within the stream.
+    pub fn position(&self) -> StreamPosition {
+        self.position
+    }
+}
+
+impl DuplexCallbackInfo {
+    /// The timestamp associated with the call to a duplex stream's data callback.
+    pub fn timestamp(&self) -> DuplexStreamTimestamp {
+        self.timestamp
+    }
+
+    /// The position of the buffers within the stream.
+    pub fn position(&self) -> StreamPosition {
+        self.position
+    }
+}
+
+impl DuplexStreamConfig {
+    /// The configuration used for the input half of the duplex stream.
+    pub fn input_config(&self) -> StreamConfig {
+        StreamConfig {
+            channels: self.input_channels,
+            sample_rate: self.sample_rate,
+            buffer_size: self.buffer_size,
+            layout: SampleLayout::Interleaved,
+        }
+    }
+
+    /// The configuration used for the output half of the duplex stream.
+    pub fn output_config(&self) -> StreamConfig {
+        StreamConfig {
+            channels: self.output_channels,
+            sample_rate: self.sample_rate,
+            buffer_size: self.buffer_size,
+            layout: SampleLayout::Interleaved,
+        }
+    }
 }
 
 #[allow(clippy::len_without_is_empty)]
@@ -518,6 +964,19 @@ impl Data {
             data,
             len,
             sample_format,
+            planar: None,
+        }
+    }
+
+    // Internal constructor for the data of streams of the split layout.
+    //
+    // See the unsafe `PlanarData::from_parts` constructor for the requirements on `planar`.
+    pub(crate) fn from_planar(planar: PlanarData) -> Self {
+        Data {
+            data: std::ptr::null_mut(),
+            len: planar.frames() * planar.channel_count() as usize,
+            sample_format: planar.sample_format(),
+            planar: Some(planar),
         }
     }
 
@@ -537,7 +996,11 @@ impl Data {
     /// The raw slice of memory representing the underlying audio data as a slice of bytes.
     ///
     /// It is up to the user to interpret the slice of memory based on [`Data::sample_format`].
+    /// The slice is empty for data of the split [`SampleLayout`], see [`Data::planar`].
     pub fn bytes(&self) -> &[u8] {
+        if self.planar.is_some() {
+            return &[];
+        }
         let len = self.len * self.sample_format.sample_size();
         // The safety of this block relies on correct construction of the `Data` instance.
         // See the unsafe `from_parts` constructor for these requirements.
@@ -547,7 +1010,11 @@ impl Data {
     /// The raw slice of memory representing the underlying audio data as a slice of bytes.
     ///
     /// It is up to the user to interpret the slice of memory based on [`Data::sample_format`].
+    /// The slice is empty for data of the split [`SampleLayout`], see [`Data::planar_mut`].
     pub fn bytes_mut(&mut self) -> &mut [u8] {
+        if self.planar.is_some() {
+            return &mut [];
+        }
         let len = self.len * self.sample_format.sample_size();
         // The safety of this block relies on correct construction of the `Data` instance. See
         // the unsafe `from_parts` constructor for these requirements.
@@ -556,12 +1023,13 @@ impl Data {
 
     /// Access the data as a slice of sample type `T`.
     ///
-    /// Returns `None` if the sample type does not match the expected sample format.
+    /// Returns `None` if the sample type does not match the expected sample format or if the data
+    /// is split, see [`Data::planar`].
     pub fn as_slice<T>(&self) -> Option<&[T]>
     where
         T: SizedSample,
     {
-        if T::FORMAT == self.sample_format {
+        if T::FORMAT == self.sample_format && self.planar.is_none() {
             // The safety of this block relies on correct construction of the `Data` instance. See
             // the unsafe `from_parts` constructor for these requirements.
             unsafe { Some(std::slice::from_raw_parts(self.data as *const T, self.len)) }
@@ -572,12 +1040,13 @@ impl Data {
 
     /// Access the data as a slice of sample type `T`.
     ///
-    /// Returns `None` if the sample type does not match the expected sample format.
+    /// Returns `None` if the sample type does not match the expected sample format or if the data
+    /// is split, see [`Data::planar_mut`].
     pub fn as_slice_mut<T>(&mut self) -> Option<&mut [T]>
     where
         T: SizedSample,
     {
-        if T::FORMAT == self.sample_format {
+        if T::FORMAT == self.sample_format && self.planar.is_none() {
             // The safety of this block relies on correct construction of the `Data` instance. See
             // the unsafe `from_parts` constructor for these requirements.
             unsafe {
@@ -590,6 +1059,20 @@ impl Data {
             None
         }
     }
+
+    /// The channels of data of the split [`SampleLayout`], each stored in a buffer of its own.
+    ///
+    /// Returns `None` for data of the other layouts.
+    pub fn planar(&self) -> Option<&PlanarData> {
+        self.planar.as_ref()
+    }
+
+    /// The channels of data of the split [`SampleLayout`], each stored in a buffer of its own.
+    ///
+    /// Returns `None` for data of the other layouts.
+    pub fn planar_mut(&mut self) -> Option<&mut PlanarData> {
+        self.planar.as_mut()
+    }
 }
 
 impl SupportedStreamConfigRange {
@@ -869,3 +1352,31 @@ fn test_stream_instant() {
     );
     assert_eq!(max.add(Duration::from_secs(1)), None);
 }
+
+#[test]
+fn test_stream_instant_conversion() {
+    let now = Instant::now();
+    let instant = StreamInstant::from_instant(now);
+    assert_eq!(instant.to_instant(), Some(now));
+    let earlier = now - Duration::from_millis(1500);
+    let earlier_instant = StreamInstant::from_instant(earlier);
+    assert_eq!(earlier_instant.to_instant(), Some(earlier));
+    assert_eq!(
+        instant.duration_since(&earlier_instant),
+        Some(Duration::from_millis(1500))
+    );
+    assert_eq!(
+        StreamInstant::new(0, 0).sub(Duration::from_millis(500)),
+        Some(StreamInstant::new(-1, 500_000_000))
+    );
+}
+
+#[test]
+fn test_device_id() {
+    let id = DeviceId::new(ALL_HOSTS[0], "hw:CARD=PCH,DEV=0");
+    let parsed: DeviceId = id.to_string().parse().unwrap();
+    assert_eq!(parsed, id);
+    assert_eq!(parsed.id(), "hw:CARD=PCH,DEV=0");
+    assert!("hw:CARD=PCH".parse::<DeviceId>().is_err());
+    assert!("PCH".parse::<DeviceId>().is_err());
+}
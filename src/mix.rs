@@ -0,0 +1,197 @@
+//! Mixing between the channels of the data callback and those of the device.
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use crate::{BuildStreamError, ChannelCount, StreamConfig, SupportedStreamConfigRange};
+
+/// A matrix of gains mapping one set of channels onto another.
+///
+/// For output streams, the mix maps the channels of the data callback to those of the device,
+/// and for input streams the channels of the device to those of the data callback.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelMix {
+    from: ChannelCount,
+    to: ChannelCount,
+    // The gain of every source channel for every destination channel, one row per destination
+    // channel.
+    gains: Vec<f32>,
+}
+
+impl ChannelMix {
+    /// Create a mix from `from` to `to` channels with the given gains, one row of `from` gains per
+    /// destination channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gains` does not hold `from * to` gains.
+    pub fn new(from: ChannelCount, to: ChannelCount, gains: Vec<f32>) -> Self {
+        assert_eq!(
+            gains.len(),
+            from as usize * to as usize,
+            "a channel mix needs one gain per source and destination channel"
+        );
+        ChannelMix { from, to, gains }
+    }
+
+    /// The usual mix from `from` to `to` channels:
+    ///
+    /// - Mono is copied to the first two channels, i.e. front left and right.
+    /// - Stereo is down-mixed to mono by averaging both channels.
+    /// - 5.1 is down-mixed to stereo following ITU-R BS.775, with the center and surround
+    ///   channels at -3 dB and without the LFE channel, and to mono by averaging that. The
+    ///   channels are expected in the WAVE order: front left, front right, center, LFE, surround
+    ///   left and surround right. Devices using another order need a custom mix.
+    /// - Otherwise, channels are mapped one to one. Extra destination channels are silent and
+    ///   extra source channels are dropped.
+    pub fn standard(from: ChannelCount, to: ChannelCount) -> Self {
+        let (sources, destinations) = (from as usize, to as usize);
+        let mut gains = vec![0.0; sources * destinations];
+        let mut set = |destination: usize, source: usize, gain: f32| {
+            gains[destination * sources + source] = gain;
+        };
+        match (from, to) {
+            (1, _) => {
+                for destination in 0..destinations.min(2) {
+                    set(destination, 0, 1.0);
+                }
+            }
+            (2, 1) => {
+                set(0, 0, 0.5);
+                set(0, 1, 0.5);
+            }
+            (6, 1 | 2) => {
+                // Gains of the front left, front right, center, LFE, surround left and surround
+                // right channels for the left and right channel of the stereo down-mix.
+                let left = [1.0, 0.0, FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2, 0.0];
+                let right = [0.0, 1.0, FRAC_1_SQRT_2, 0.0, 0.0, FRAC_1_SQRT_2];
+                for source in 0..sources {
+                    if to == 1 {
+                        set(0, source, (left[source] + right[source]) / 2.0);
+                    } else {
+                        set(0, source, left[source]);
+                        set(1, source, right[source]);
+                    }
+                }
+            }
+            _ => {
+                for channel in 0..sources.min(destinations) {
+                    set(channel, channel, 1.0);
+                }
+            }
+        }
+        ChannelMix { from, to, gains }
+    }
+
+    /// The number of source channels.
+    pub fn from_channels(&self) -> ChannelCount {
+        self.from
+    }
+
+    /// The number of destination channels.
+    pub fn to_channels(&self) -> ChannelCount {
+        self.to
+    }
+
+    /// The gain of the `source` channel in the `destination` channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either channel is out of range.
+    pub fn gain(&self, destination: ChannelCount, source: ChannelCount) -> f32 {
+        assert!(source < self.from && destination < self.to);
+        self.gains[destination as usize * self.from as usize + source as usize]
+    }
+
+    // Mix the interleaved frames of `input` into `output`, which must hold as many frames.
+    pub(crate) fn apply(&self, input: &[f32], output: &mut [f32]) {
+        let (sources, destinations) = (self.from as usize, self.to as usize);
+        for (input, output) in input
+            .chunks_exact(sources)
+            .zip(output.chunks_exact_mut(destinations))
+        {
+            for (sample, gains) in output.iter_mut().zip(self.gains.chunks_exact(sources)) {
+                *sample = gains.iter().zip(input).map(|(gain, x)| gain * x).sum();
+            }
+        }
+    }
+}
+
+// The number of channels to open the device with for the given config: the requested number if
+// it is supported, otherwise the closest larger number, or the largest one if there is none.
+// Unless `any_rate` is set, only configs supporting the requested sample rate are considered.
+pub(crate) fn native_channels(
+    configs: impl Iterator<Item = SupportedStreamConfigRange>,
+    config: &StreamConfig,
+    any_rate: bool,
+) -> Result<ChannelCount, BuildStreamError> {
+    let channels: Vec<_> = configs
+        .filter(|range| {
+            any_rate
+                || range.min_sample_rate() <= config.sample_rate
+                    && config.sample_rate <= range.max_sample_rate()
+        })
+        .map(|range| range.channels())
+        .collect();
+    channels
+        .iter()
+        .filter(|&&channels| channels >= config.channels)
+        .min()
+        .or_else(|| channels.iter().max())
+        .copied()
+        .ok_or(BuildStreamError::StreamConfigNotSupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChannelMix;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    fn mix(from: u16, to: u16, input: &[f32]) -> Vec<f32> {
+        let mut output = vec![f32::NAN; input.len() / from as usize * to as usize];
+        ChannelMix::standard(from, to).apply(input, &mut output);
+        output
+    }
+
+    #[test]
+    fn test_mono_to_stereo() {
+        assert_eq!(mix(1, 2, &[0.25, -0.5]), [0.25, 0.25, -0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_stereo_to_mono() {
+        assert_eq!(mix(2, 1, &[0.25, 0.75, -0.5, 0.5]), [0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_surround_to_stereo() {
+        // Front left, front right, center, LFE, surround left and surround right.
+        let output = mix(6, 2, &[0.1, 0.2, 0.4, 1.0, 0.3, 0.5]);
+        let left = 0.1 + (0.4 + 0.3) * FRAC_1_SQRT_2;
+        let right = 0.2 + (0.4 + 0.5) * FRAC_1_SQRT_2;
+        assert!((output[0] - left).abs() < 1e-6);
+        assert!((output[1] - right).abs() < 1e-6);
+
+        // The LFE channel is dropped.
+        assert_eq!(mix(6, 2, &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_surround_to_mono() {
+        let output = mix(6, 1, &[0.1, 0.2, 0.4, 1.0, 0.3, 0.5]);
+        let left = 0.1 + (0.4 + 0.3) * FRAC_1_SQRT_2;
+        let right = 0.2 + (0.4 + 0.5) * FRAC_1_SQRT_2;
+        assert!((output[0] - (left + right) / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_silence_fill() {
+        // Extra destination channels are silent, also for mono sources.
+        assert_eq!(
+            mix(2, 4, &[0.25, 0.75, -0.5, 0.5]),
+            [0.25, 0.75, 0.0, 0.0, -0.5, 0.5, 0.0, 0.0]
+        );
+        assert_eq!(mix(1, 4, &[0.5]), [0.5, 0.5, 0.0, 0.0]);
+        // Extra source channels are dropped.
+        assert_eq!(mix(4, 2, &[0.25, 0.75, 1.0, 1.0]), [0.25, 0.75]);
+    }
+}
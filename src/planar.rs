@@ -0,0 +1,551 @@
+//! Non-interleaved audio data, and converting it from and to the interleaved sample layout for
+//! hosts that only support the latter.
+
+use std::time::Duration;
+
+use crate::buffer::build_with_buffer;
+use crate::duplex::fill_equilibrium;
+use crate::traits::DeviceTrait;
+use crate::{
+    BuildStreamError, ChannelCount, Data, InputCallbackInfo, OutputCallbackInfo, SampleFormat,
+    SampleLayout, SizedSample, StreamConfig, StreamError,
+};
+
+type InputDataCallback = Box<dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static>;
+type OutputDataCallback = Box<dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static>;
+
+/// A buffer of dynamically typed audio data, with the samples of every channel stored in a
+/// separate slice.
+///
+/// The data passed to the raw callbacks of streams of the split [`SampleLayout`] is accessed
+/// through `PlanarData`, see [`Data::planar`].
+#[derive(Debug)]
+pub struct PlanarData {
+    channels: *const *mut (),
+    channel_count: usize,
+    frames: usize,
+    sample_format: SampleFormat,
+}
+
+impl PlanarData {
+    // Internal constructor for host implementations to use.
+    //
+    // The following requirements must be met in order for the safety of `PlanarData`'s public
+    // API.
+    //
+    // - The `channels` pointer must point to `channel_count` pointers, each pointing to the first
+    //   sample of a channel.
+    // - Every channel must hold `frames` samples in the format specified via the `sample_format`
+    //   argument, and channels must not overlap.
+    pub(crate) unsafe fn from_parts(
+        channels: *const *mut (),
+        channel_count: usize,
+        frames: usize,
+        sample_format: SampleFormat,
+    ) -> Self {
+        PlanarData {
+            channels,
+            channel_count,
+            frames,
+            sample_format,
+        }
+    }
+
+    /// The sample format of the internal audio data.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// The number of channels.
+    pub fn channel_count(&self) -> ChannelCount {
+        self.channel_count as ChannelCount
+    }
+
+    /// The length of every channel in samples.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// The raw slice of memory representing the audio data of a channel as a slice of bytes.
+    ///
+    /// Returns `None` if `index` is out of range.
+    pub fn channel_bytes(&self, index: usize) -> Option<&[u8]> {
+        let ptr = self.channel_ptr(index)?;
+        let len = self.frames * self.sample_format.sample_size();
+        // The safety of this block relies on correct construction of the `PlanarData` instance.
+        // See the unsafe `from_parts` constructor for these requirements.
+        unsafe { Some(std::slice::from_raw_parts(ptr as *const u8, len)) }
+    }
+
+    /// The raw slice of memory representing the audio data of a channel as a slice of bytes.
+    ///
+    /// Returns `None` if `index` is out of range.
+    pub fn channel_bytes_mut(&mut self, index: usize) -> Option<&mut [u8]> {
+        let ptr = self.channel_ptr(index)?;
+        let len = self.frames * self.sample_format.sample_size();
+        // The safety of this block relies on correct construction of the `PlanarData` instance.
+        // See the unsafe `from_parts` constructor for these requirements.
+        unsafe { Some(std::slice::from_raw_parts_mut(ptr as *mut u8, len)) }
+    }
+
+    /// Access the data of a channel as a slice of sample type `T`.
+    ///
+    /// Returns `None` if `index` is out of range or the sample type does not match the expected
+    /// sample format.
+    pub fn channel<T>(&self, index: usize) -> Option<&[T]>
+    where
+        T: SizedSample,
+    {
+        if T::FORMAT != self.sample_format {
+            return None;
+        }
+        let ptr = self.channel_ptr(index)?;
+        // The safety of this block relies on correct construction of the `PlanarData` instance.
+        // See the unsafe `from_parts` constructor for these requirements.
+        unsafe { Some(std::slice::from_raw_parts(ptr as *const T, self.frames)) }
+    }
+
+    /// Access the data of a channel as a slice of sample type `T`.
+    ///
+    /// Returns `None` if `index` is out of range or the sample type does not match the expected
+    /// sample format.
+    pub fn channel_mut<T>(&mut self, index: usize) -> Option<&mut [T]>
+    where
+        T: SizedSample,
+    {
+        if T::FORMAT != self.sample_format {
+            return None;
+        }
+        let ptr = self.channel_ptr(index)?;
+        // The safety of this block relies on correct construction of the `PlanarData` instance.
+        // See the unsafe `from_parts` constructor for these requirements.
+        unsafe { Some(std::slice::from_raw_parts_mut(ptr as *mut T, self.frames)) }
+    }
+
+    /// Access the data of all channels at once as slices of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format.
+    pub fn channels_mut<'a, T>(&'a mut self) -> Option<impl Iterator<Item = &'a mut [T]> + 'a>
+    where
+        T: SizedSample + 'a,
+    {
+        if T::FORMAT != self.sample_format {
+            return None;
+        }
+        let frames = self.frames;
+        let channels = (0..self.channel_count).map(move |index| {
+            let ptr = self.channel_ptr(index).unwrap();
+            // Channels do not overlap, so handing out all of them at once is sound. See the
+            // unsafe `from_parts` constructor for the requirements this relies on.
+            unsafe { std::slice::from_raw_parts_mut(ptr as *mut T, frames) }
+        });
+        Some(channels)
+    }
+
+    fn channel_ptr(&self, index: usize) -> Option<*mut ()> {
+        if index < self.channel_count {
+            // The safety of this block relies on correct construction of the `PlanarData`
+            // instance. See the unsafe `from_parts` constructor for these requirements.
+            unsafe { Some(*self.channels.add(index)) }
+        } else {
+            None
+        }
+    }
+}
+
+// Pointers to the first sample of every channel, kept by a stream of the split layout so that
+// its data can be passed to the callback without allocating.
+pub(crate) struct ChannelPointers(Vec<*mut ()>);
+
+// The pointers are only dereferenced through the `PlanarData` handed to the data callback.
+unsafe impl Send for ChannelPointers {}
+
+impl ChannelPointers {
+    pub(crate) fn new(channels: usize) -> Self {
+        ChannelPointers(vec![std::ptr::null_mut(); channels])
+    }
+
+    // Only used by hosts that hand out their own channel buffers.
+    #[allow(dead_code)]
+    pub(crate) fn set(&mut self, channel: usize, ptr: *mut ()) {
+        self.0[channel] = ptr;
+    }
+
+    // Point at the channels of `planar`, a buffer of contiguous planar samples.
+    fn set_contiguous(&mut self, planar: &mut [u8]) {
+        let channel_len = planar.len() / self.0.len();
+        for (ptr, channel) in self.0.iter_mut().zip(planar.chunks_exact_mut(channel_len)) {
+            *ptr = channel.as_mut_ptr() as *mut ();
+        }
+    }
+
+    // The data of the channels pointed at, holding `frames` samples of `sample_format` each.
+    //
+    // Every pointer must meet the requirements of `PlanarData::from_parts`.
+    pub(crate) unsafe fn data(&self, frames: usize, sample_format: SampleFormat) -> Data {
+        Data::from_planar(PlanarData::from_parts(
+            self.0.as_ptr(),
+            self.0.len(),
+            frames,
+            sample_format,
+        ))
+    }
+}
+
+// Copy the interleaved samples of `channels` channels in `interleaved` into `planar`, which must
+// be of the same length. Each sample spans `sample_size` elements, e.g. bytes.
+pub(crate) fn deinterleave<T: Copy>(
+    interleaved: &[T],
+    planar: &mut [T],
+    channels: usize,
+    sample_size: usize,
+) {
+    let channel_len = interleaved.len() / channels;
+    for (frame, samples) in interleaved.chunks_exact(channels * sample_size).enumerate() {
+        for (channel, sample) in samples.chunks_exact(sample_size).enumerate() {
+            let offset = channel * channel_len + frame * sample_size;
+            planar[offset..offset + sample_size].copy_from_slice(sample);
+        }
+    }
+}
+
+// Copy the planar samples of `channels` channels in `planar` into `interleaved`, which must be of
+// the same length. Each sample spans `sample_size` elements, e.g. bytes.
+pub(crate) fn interleave<T: Copy>(
+    planar: &[T],
+    interleaved: &mut [T],
+    channels: usize,
+    sample_size: usize,
+) {
+    let channel_len = planar.len() / channels;
+    for (frame, samples) in interleaved
+        .chunks_exact_mut(channels * sample_size)
+        .enumerate()
+    {
+        for (channel, sample) in samples.chunks_exact_mut(sample_size).enumerate() {
+            let offset = channel * channel_len + frame * sample_size;
+            sample.copy_from_slice(&planar[offset..offset + sample_size]);
+        }
+    }
+}
+
+/// Build a planar or split input stream out of an interleaved input stream.
+///
+/// Frames captured while the stream is being built are dropped.
+pub(crate) fn build_input_stream_planar_fallback<Dev, D, E>(
+    device: &Dev,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+    mut data_callback: D,
+    error_callback: E,
+    timeout: Option<Duration>,
+) -> Result<Dev::Stream, BuildStreamError>
+where
+    Dev: DeviceTrait,
+    D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    let channels = config.channels as usize;
+    let sample_size = sample_format.sample_size();
+    let interleaved_config = StreamConfig {
+        layout: SampleLayout::Interleaved,
+        ..config.clone()
+    };
+    let mut pointers =
+        (config.layout == SampleLayout::Split).then(|| ChannelPointers::new(channels));
+
+    build_with_buffer(
+        config.buffer_size,
+        |frames| frames * channels * sample_size,
+        0u8,
+        |buffer| {
+            // Boxed, so that hosts building the interleaved stream with the same function do
+            // not instantiate it recursively.
+            let data_callback: InputDataCallback =
+                Box::new(move |data: &Data, info: &InputCallbackInfo| {
+                    let bytes = data.bytes();
+                    // Only fails while the stream is being built.
+                    let Some(mut buffer) = buffer.lock_buffer(bytes.len(), 0) else {
+                        return;
+                    };
+                    let planar = &mut buffer[..bytes.len()];
+                    deinterleave(bytes, planar, channels, sample_size);
+                    let data = match pointers {
+                        Some(ref mut pointers) => {
+                            pointers.set_contiguous(planar);
+                            unsafe { pointers.data(data.len() / channels, sample_format) }
+                        }
+                        None => unsafe {
+                            Data::from_parts(
+                                planar.as_mut_ptr() as *mut (),
+                                data.len(),
+                                sample_format,
+                            )
+                        },
+                    };
+                    data_callback(&data, info);
+                });
+            device.build_input_stream_raw(
+                &interleaved_config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            )
+        },
+    )
+}
+
+/// Build a planar or split output stream out of an interleaved output stream.
+///
+/// The stream plays silence while it is being built.
+pub(crate) fn build_output_stream_planar_fallback<Dev, D, E>(
+    device: &Dev,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+    mut data_callback: D,
+    error_callback: E,
+    timeout: Option<Duration>,
+) -> Result<Dev::Stream, BuildStreamError>
+where
+    Dev: DeviceTrait,
+    D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+    E: FnMut(StreamError) + Send + 'static,
+{
+    let channels = config.channels as usize;
+    let sample_size = sample_format.sample_size();
+    let interleaved_config = StreamConfig {
+        layout: SampleLayout::Interleaved,
+        ..config.clone()
+    };
+    let mut pointers =
+        (config.layout == SampleLayout::Split).then(|| ChannelPointers::new(channels));
+
+    build_with_buffer(
+        config.buffer_size,
+        |frames| frames * channels * sample_size,
+        0u8,
+        |buffer| {
+            // Boxed, so that hosts building the interleaved stream with the same function do
+            // not instantiate it recursively.
+            let data_callback: OutputDataCallback =
+                Box::new(move |data: &mut Data, info: &OutputCallbackInfo| {
+                    let len = data.bytes().len();
+                    // Only fails while the stream is being built.
+                    let Some(mut buffer) = buffer.lock_buffer(len, 0) else {
+                        fill_equilibrium(data, 0);
+                        return;
+                    };
+                    let planar = &mut buffer[..len];
+                    let mut planar_data = match pointers {
+                        Some(ref mut pointers) => {
+                            pointers.set_contiguous(planar);
+                            unsafe { pointers.data(data.len() / channels, sample_format) }
+                        }
+                        None => unsafe {
+                            Data::from_parts(
+                                planar.as_mut_ptr() as *mut (),
+                                data.len(),
+                                sample_format,
+                            )
+                        },
+                    };
+                    data_callback(&mut planar_data, info);
+                    interleave(planar, data.bytes_mut(), channels, sample_size);
+                });
+            device.build_output_stream_raw(
+                &interleaved_config,
+                sample_format,
+                data_callback,
+                error_callback,
+                timeout,
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deinterleave, interleave};
+
+    #[test]
+    fn test_deinterleave() {
+        let interleaved = [1u8, 10, 2, 20, 3, 30];
+        let mut planar = [0; 6];
+        deinterleave(&interleaved, &mut planar, 2, 1);
+        assert_eq!(planar, [1, 2, 3, 10, 20, 30]);
+
+        // Multi-byte samples are copied as a whole.
+        let interleaved = [1u8, 2, 10, 20, 30, 40, 3, 4, 50, 60, 70, 80];
+        let mut planar = [0; 12];
+        deinterleave(&interleaved, &mut planar, 3, 2);
+        assert_eq!(planar, [1, 2, 3, 4, 10, 20, 50, 60, 30, 40, 70, 80]);
+    }
+
+    #[test]
+    fn test_interleave() {
+        let planar = [1u8, 2, 3, 10, 20, 30];
+        let mut interleaved = [0; 6];
+        interleave(&planar, &mut interleaved, 2, 1);
+        assert_eq!(interleaved, [1, 10, 2, 20, 3, 30]);
+
+        let planar = [1u8, 2, 3, 4, 10, 20, 50, 60, 30, 40, 70, 80];
+        let mut interleaved = [0; 12];
+        interleave(&planar, &mut interleaved, 3, 2);
+        assert_eq!(interleaved, [1, 2, 10, 20, 30, 40, 3, 4, 50, 60, 70, 80]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let interleaved: Vec<u8> = (0..48).collect();
+        let mut planar = vec![0; 48];
+        let mut round_trip = vec![0; 48];
+        deinterleave(&interleaved, &mut planar, 4, 4);
+        interleave(&planar, &mut round_trip, 4, 4);
+        assert_eq!(round_trip, interleaved);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn test_fallback_streams() {
+        use crate::host::dummy::{Device, ManualClock};
+        use crate::traits::{DeviceTrait, StreamTrait};
+        use crate::{
+            BufferSize, SampleFormat, SampleLayout, SampleRate, StreamConfig, SupportedBufferSize,
+            SupportedStreamConfigRange,
+        };
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let range = SupportedStreamConfigRange::new(
+            2,
+            SampleRate(1000),
+            SampleRate(1000),
+            SupportedBufferSize::Unknown,
+            SampleFormat::I16,
+        );
+        let clock = ManualClock::new();
+        let device = Device::new("test")
+            .with_input_configs(vec![range])
+            .with_output_configs(vec![range])
+            .with_clock(clock.clone());
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(1000),
+            buffer_size: BufferSize::Fixed(8),
+            layout: SampleLayout::Planar,
+        };
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let input_calls = calls.clone();
+        let input = device
+            .build_input_stream(
+                &config,
+                move |data: &[i16], _| {
+                    assert_eq!(data, [0; 16]);
+                    input_calls.fetch_add(1, Ordering::Relaxed);
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        let output_calls = calls.clone();
+        let output = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [i16], _| {
+                    assert_eq!(data.len(), 16);
+                    let (left, right) = data.split_at_mut(8);
+                    left.fill(1);
+                    right.fill(-1);
+                    output_calls.fetch_add(1, Ordering::Relaxed);
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        input.play().unwrap();
+        output.play().unwrap();
+        clock.advance(8);
+        clock.advance(8);
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn test_split_fallback_streams() {
+        use crate::host::dummy::{Device, ManualClock};
+        use crate::traits::{DeviceTrait, StreamTrait};
+        use crate::{
+            BufferSize, BuildStreamError, SampleFormat, SampleLayout, SampleRate, StreamConfig,
+            SupportedBufferSize, SupportedStreamConfigRange,
+        };
+        use std::sync::{Arc, Mutex};
+
+        let range = SupportedStreamConfigRange::new(
+            2,
+            SampleRate(1000),
+            SampleRate(1000),
+            SupportedBufferSize::Unknown,
+            SampleFormat::I16,
+        );
+        let clock = ManualClock::new();
+        let device = Device::new("test")
+            .with_input_configs(vec![range])
+            .with_output_configs(vec![range])
+            .with_clock(clock.clone());
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(1000),
+            buffer_size: BufferSize::Fixed(8),
+            layout: SampleLayout::Split,
+        };
+
+        // Typed callbacks cannot access split data.
+        let result = device.build_output_stream(&config, |_: &mut [i16], _| {}, |_| {}, None);
+        assert!(matches!(
+            result,
+            Err(BuildStreamError::StreamConfigNotSupported)
+        ));
+
+        let channels = Arc::new(Mutex::new(Vec::new()));
+        let input_channels = channels.clone();
+        let input = device
+            .build_input_stream_raw(
+                &config,
+                SampleFormat::I16,
+                move |data, _| {
+                    assert!(data.bytes().is_empty());
+                    assert!(data.as_slice::<i16>().is_none());
+                    let planar = data.planar().unwrap();
+                    assert_eq!((planar.channel_count(), planar.frames()), (2, 8));
+                    assert_eq!(planar.channel::<i16>(0), Some(&[0; 8][..]));
+                    assert!(planar.channel::<i16>(2).is_none());
+                    input_channels.lock().unwrap().push(planar.channel_count());
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        let output_channels = channels.clone();
+        let output = device
+            .build_output_stream_raw(
+                &config,
+                SampleFormat::I16,
+                move |data, _| {
+                    let planar = data.planar_mut().unwrap();
+                    for (channel, samples) in planar.channels_mut::<i16>().unwrap().enumerate() {
+                        samples.fill(channel as i16);
+                    }
+                    output_channels.lock().unwrap().push(planar.channel_count());
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        input.play().unwrap();
+        output.play().unwrap();
+        clock.advance(8);
+        assert_eq!(*channels.lock().unwrap(), [2, 2]);
+    }
+}
@@ -7,6 +7,65 @@
 #[doc(inline)]
 pub use self::platform_impl::*;
 
+#[cfg(any(
+    feature = "dummy",
+    not(any(
+        windows,
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "emscripten",
+        target_os = "android",
+        all(target_arch = "wasm32", feature = "wasm-bindgen"),
+    ))
+))]
+#[doc(inline)]
+pub use self::dummy::*;
+
+// The dummy host is available on every platform with the `dummy` feature, and is the only host of
+// unsupported platforms.
+#[cfg(any(
+    feature = "dummy",
+    not(any(
+        windows,
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "emscripten",
+        target_os = "android",
+        all(target_arch = "wasm32", feature = "wasm-bindgen"),
+    ))
+))]
+mod dummy {
+    pub use crate::host::dummy::{
+        Device as DummyDevice, Devices as DummyDevices, Host as DummyHost,
+        ManualClock as DummyManualClock, Stream as DummyStream,
+        SupportedInputConfigs as DummySupportedInputConfigs,
+        SupportedOutputConfigs as DummySupportedOutputConfigs,
+    };
+
+    #[deprecated(note = "the null host was replaced by the dummy host, use `DummyDevice`")]
+    pub type NullDevice = DummyDevice;
+    #[deprecated(note = "the null host was replaced by the dummy host, use `DummyDevices`")]
+    pub type NullDevices = DummyDevices;
+    #[deprecated(note = "the null host was replaced by the dummy host, use `DummyHost`")]
+    pub type NullHost = DummyHost;
+    #[deprecated(
+        note = "the null host was replaced by the dummy host, use `DummySupportedInputConfigs`"
+    )]
+    pub type NullSupportedInputConfigs = DummySupportedInputConfigs;
+    #[deprecated(
+        note = "the null host was replaced by the dummy host, use `DummySupportedOutputConfigs`"
+    )]
+    pub type NullSupportedOutputConfigs = DummySupportedOutputConfigs;
+}
+
 /// A macro to assist with implementing a platform's dynamically dispatched [`Host`] type.
 ///
 /// These dynamically dispatched types are necessary to allow for users to switch between hosts at
@@ -315,6 +374,24 @@ macro_rules! impl_platform_host {
                 }
             }
 
+            fn id(&self) -> Result<crate::DeviceId, crate::DeviceIdError> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        DeviceInner::$HostVariant(ref d) => d.id(),
+                    )*
+                }
+            }
+
+            fn description(&self) -> Result<crate::DeviceDescription, crate::DeviceNameError> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        DeviceInner::$HostVariant(ref d) => d.description(),
+                    )*
+                }
+            }
+
             fn supports_input(&self) -> bool {
                 match self.0 {
                     $(
@@ -434,6 +511,34 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn build_duplex_stream_raw<D, E>(
+                &self,
+                config: &crate::DuplexStreamConfig,
+                sample_format: crate::SampleFormat,
+                data_callback: D,
+                error_callback: E,
+                timeout: Option<std::time::Duration>,
+            ) -> Result<crate::DuplexStream<Self::Stream>, crate::BuildStreamError>
+            where
+                D: FnMut(&crate::Data, &mut crate::Data, &crate::DuplexCallbackInfo) + Send + 'static,
+                E: FnMut(crate::StreamError) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_duplex_stream_raw(
+                                config,
+                                sample_format,
+                                data_callback,
+                                error_callback,
+                                timeout,
+                            )
+                            .map(|s| s.map(|s| Stream::from(StreamInner::$HostVariant(s)))),
+                    )*
+                }
+            }
         }
 
         impl crate::traits::HostTrait for Host {
@@ -480,6 +585,29 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn device_by_id(&self, id: &crate::DeviceId) -> Option<Self::Device> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        HostInner::$HostVariant(ref h) => {
+                            h.device_by_id(id).map(DeviceInner::$HostVariant).map(Device::from)
+                        }
+                    )*
+                }
+            }
+
+            fn watch_devices<F>(&self, callback: F) -> Result<crate::DeviceWatcher, crate::WatchDevicesError>
+            where
+                F: FnMut(crate::DeviceEvent) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        HostInner::$HostVariant(ref h) => h.watch_devices(callback),
+                    )*
+                }
+            }
         }
 
         impl crate::traits::StreamTrait for Stream {
@@ -504,6 +632,102 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn latency(&self) -> Result<std::time::Duration, crate::StreamLatencyError> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.latency()
+                        }
+                    )*
+                }
+            }
+
+            fn buffer_size(&self) -> Result<crate::StreamBufferSize, crate::StreamBufferSizeError> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.buffer_size()
+                        }
+                    )*
+                }
+            }
+
+            fn position(&self) -> Result<u64, crate::StreamPositionError> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.position()
+                        }
+                    )*
+                }
+            }
+
+            fn enable_stats(&self) -> Result<(), crate::StreamStatsError> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.enable_stats()
+                        }
+                    )*
+                }
+            }
+
+            fn stats(&self) -> Result<crate::StreamStats, crate::StreamStatsError> {
+                match self.0 {
+                    $(
+                        $(#[cfg($feat)])?
+                        StreamInner::$HostVariant(ref s) => {
+                            s.stats()
+                        }
+                    )*
+                }
+            }
+
+            fn play_all(streams: &[&Self]) -> Result<bool, crate::PlayStreamError> {
+                // Streams can only be started together by their host, if they share one.
+                $(
+                    $(#[cfg($feat)])?
+                    {
+                        let inner: Vec<_> = streams
+                            .iter()
+                            .filter_map(|stream| match stream.0 {
+                                StreamInner::$HostVariant(ref s) => Some(s),
+                                #[allow(unreachable_patterns)]
+                                _ => None,
+                            })
+                            .collect();
+                        if !inner.is_empty() && inner.len() == streams.len() {
+                            return crate::traits::StreamTrait::play_all(&inner[..]);
+                        }
+                    }
+                )*
+                crate::group::play_each(streams)
+            }
+
+            fn pause_all(streams: &[&Self]) -> Result<bool, crate::PauseStreamError> {
+                $(
+                    $(#[cfg($feat)])?
+                    {
+                        let inner: Vec<_> = streams
+                            .iter()
+                            .filter_map(|stream| match stream.0 {
+                                StreamInner::$HostVariant(ref s) => Some(s),
+                                #[allow(unreachable_patterns)]
+                                _ => None,
+                            })
+                            .collect();
+                        if !inner.is_empty() && inner.len() == streams.len() {
+                            return crate::traits::StreamTrait::pause_all(&inner[..]);
+                        }
+                    }
+                )*
+                crate::group::pause_each(streams)
+            }
         }
 
         impl From<DeviceInner> for Device {
@@ -600,6 +824,7 @@ mod platform_impl {
         Device as AlsaDevice, Devices as AlsaDevices, Host as AlsaHost, Stream as AlsaStream,
         SupportedInputConfigs as AlsaSupportedInputConfigs,
         SupportedOutputConfigs as AlsaSupportedOutputConfigs,
+        ThreadScheduling as AlsaThreadScheduling,
     };
     #[cfg(feature = "jack")]
     pub use crate::host::jack::{
@@ -608,7 +833,11 @@ mod platform_impl {
         SupportedOutputConfigs as JackSupportedOutputConfigs,
     };
 
-    impl_platform_host!(#[cfg(feature = "jack")] Jack jack "JACK", Alsa alsa "ALSA");
+    impl_platform_host!(
+        #[cfg(feature = "jack")] Jack jack "JACK",
+        Alsa alsa "ALSA",
+        #[cfg(feature = "dummy")] Dummy dummy "Dummy"
+    );
 
     /// The default host for the current compilation target platform.
     pub fn default_host() -> Host {
@@ -626,7 +855,10 @@ mod platform_impl {
         SupportedOutputConfigs as CoreAudioSupportedOutputConfigs,
     };
 
-    impl_platform_host!(CoreAudio coreaudio "CoreAudio");
+    impl_platform_host!(
+        CoreAudio coreaudio "CoreAudio",
+        #[cfg(feature = "dummy")] Dummy dummy "Dummy"
+    );
 
     /// The default host for the current compilation target platform.
     pub fn default_host() -> Host {
@@ -644,7 +876,10 @@ mod platform_impl {
         SupportedOutputConfigs as EmscriptenSupportedOutputConfigs,
     };
 
-    impl_platform_host!(Emscripten emscripten "Emscripten");
+    impl_platform_host!(
+        Emscripten emscripten "Emscripten",
+        #[cfg(feature = "dummy")] Dummy dummy "Dummy"
+    );
 
     /// The default host for the current compilation target platform.
     pub fn default_host() -> Host {
@@ -662,7 +897,10 @@ mod platform_impl {
         SupportedOutputConfigs as WebAudioSupportedOutputConfigs,
     };
 
-    impl_platform_host!(WebAudio webaudio "WebAudio");
+    impl_platform_host!(
+        WebAudio webaudio "WebAudio",
+        #[cfg(feature = "dummy")] Dummy dummy "Dummy"
+    );
 
     /// The default host for the current compilation target platform.
     pub fn default_host() -> Host {
@@ -686,7 +924,11 @@ mod platform_impl {
         SupportedOutputConfigs as WasapiSupportedOutputConfigs,
     };
 
-    impl_platform_host!(#[cfg(feature = "asio")] Asio asio "ASIO", Wasapi wasapi "WASAPI");
+    impl_platform_host!(
+        #[cfg(feature = "asio")] Asio asio "ASIO",
+        Wasapi wasapi "WASAPI",
+        #[cfg(feature = "dummy")] Dummy dummy "Dummy"
+    );
 
     /// The default host for the current compilation target platform.
     pub fn default_host() -> Host {
@@ -704,7 +946,10 @@ mod platform_impl {
         SupportedOutputConfigs as OboeSupportedOutputConfigs,
     };
 
-    impl_platform_host!(Oboe oboe "Oboe");
+    impl_platform_host!(
+        Oboe oboe "Oboe",
+        #[cfg(feature = "dummy")] Dummy dummy "Dummy"
+    );
 
     /// The default host for the current compilation target platform.
     pub fn default_host() -> Host {
@@ -727,17 +972,13 @@ mod platform_impl {
     all(target_arch = "wasm32", feature = "wasm-bindgen"),
 )))]
 mod platform_impl {
-    pub use crate::host::null::{
-        Device as NullDevice, Devices as NullDevices, Host as NullHost,
-        SupportedInputConfigs as NullSupportedInputConfigs,
-        SupportedOutputConfigs as NullSupportedOutputConfigs,
-    };
+    use super::DummyHost;
 
-    impl_platform_host!(Null null "Null");
+    impl_platform_host!(Dummy dummy "Dummy");
 
     /// The default host for the current compilation target platform.
     pub fn default_host() -> Host {
-        NullHost::new()
+        DummyHost::new()
             .expect("the default host should always be available")
             .into()
     }
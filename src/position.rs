@@ -0,0 +1,140 @@
+//! Counting the frames passed to or from the data callback of a stream.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::StreamPosition;
+
+/// The position of a stream, shared between its worker and the stream handle.
+#[derive(Debug, Default)]
+pub(crate) struct PositionCounter {
+    frames: AtomicU64,
+    discontinuity: AtomicBool,
+}
+
+impl PositionCounter {
+    /// Advance the position by a buffer of `frames` frames, returning the position of the buffer.
+    pub(crate) fn advance(&self, frames: usize) -> StreamPosition {
+        StreamPosition {
+            frames: self.frames.fetch_add(frames as u64, Ordering::Relaxed),
+            discontinuity: self.discontinuity.swap(false, Ordering::Relaxed),
+        }
+    }
+
+    /// Flag the next buffer as following lost frames.
+    pub(crate) fn mark_discontinuity(&self) {
+        self.discontinuity.store(true, Ordering::Relaxed);
+    }
+
+    /// The number of frames passed so far, i.e. the position of the next buffer.
+    pub(crate) fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PositionCounter;
+    use crate::StreamPosition;
+
+    #[test]
+    fn test_position_counter() {
+        let counter = PositionCounter::default();
+        assert_eq!(counter.advance(4), position(0, false));
+        assert_eq!(counter.advance(4), position(4, false));
+
+        // Only the buffer following the discontinuity is flagged.
+        counter.mark_discontinuity();
+        assert_eq!(counter.frames(), 8);
+        assert_eq!(counter.advance(2), position(8, true));
+        assert_eq!(counter.advance(4), position(10, false));
+        assert_eq!(counter.frames(), 14);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn test_dummy_stream_positions() {
+        use crate::host::dummy::{Device, ManualClock};
+        use crate::traits::{DeviceTrait, StreamTrait};
+        use crate::{
+            BufferSize, SampleFormat, SampleLayout, SampleRate, StreamConfig, StreamError,
+            StreamInstant, SupportedBufferSize, SupportedStreamConfigRange,
+        };
+        use std::sync::{Arc, Mutex};
+
+        let range = SupportedStreamConfigRange::new(
+            1,
+            SampleRate(1000),
+            SampleRate(1000),
+            SupportedBufferSize::Unknown,
+            SampleFormat::F32,
+        );
+        let clock = ManualClock::new();
+        let device = Device::new("test")
+            .with_input_configs(vec![range])
+            .with_clock(clock.clone());
+        let config = StreamConfig {
+            channels: 1,
+            sample_rate: SampleRate(1000),
+            buffer_size: BufferSize::Fixed(4),
+            layout: SampleLayout::Interleaved,
+        };
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let stream_calls = calls.clone();
+        let stream_errors = errors.clone();
+        let stream = device
+            .build_input_stream(
+                &config,
+                move |_: &[f32], info| {
+                    let callback = info.timestamp().callback;
+                    stream_calls
+                        .lock()
+                        .unwrap()
+                        .push((info.position(), callback));
+                },
+                move |err| stream_errors.lock().unwrap().push(err),
+                None,
+            )
+            .unwrap();
+        stream.play().unwrap();
+
+        // Positions increase by the frames of every buffer.
+        clock.advance(10);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                (position(0, false), StreamInstant::new(0, 0)),
+                (position(4, false), StreamInstant::new(0, 4_000_000)),
+                (position(8, false), StreamInstant::new(0, 8_000_000)),
+            ]
+        );
+
+        // Frames lost to an xrun are not counted, but flag the next buffer.
+        calls.lock().unwrap().clear();
+        clock.skip(6);
+        assert_eq!(stream.position().unwrap(), 10);
+        clock.advance(8);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                (position(10, true), StreamInstant::new(0, 16_000_000)),
+                (position(14, false), StreamInstant::new(0, 20_000_000)),
+            ]
+        );
+        assert_eq!(stream.position().unwrap(), 18);
+        assert!(matches!(
+            errors.lock().unwrap()[..],
+            [StreamError::BufferOverrun {
+                frames_lost: Some(6)
+            }]
+        ));
+    }
+
+    fn position(frames: u64, discontinuity: bool) -> StreamPosition {
+        StreamPosition {
+            frames,
+            discontinuity,
+        }
+    }
+}
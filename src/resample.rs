@@ -0,0 +1,422 @@
+//! Resampling between the sample rate of the data callback and that of the device.
+
+use std::f64::consts::PI;
+
+use crate::{
+    frames_to_duration, BuildStreamError, SampleRate, StreamConfig, StreamInstant,
+    SupportedStreamConfigRange,
+};
+
+// Half the number of taps of the interpolation filter when upsampling. When downsampling, the
+// filter is widened by the rate ratio to keep the same transition band relative to the output.
+const HALF_TAPS: usize = 64;
+
+// The number of fractional positions between two input frames for which the filter is
+// tabulated. The filter at other positions is linearly interpolated from the nearest ones.
+const PHASES: usize = 256;
+
+// The cutoff frequency of the filter, relative to the lower of both Nyquist frequencies.
+const CUTOFF: f64 = 0.92;
+
+// The shape parameter of the Kaiser window, trading the width of the transition band for
+// stopband attenuation.
+const KAISER_BETA: f64 = 9.0;
+
+// The sample rate to open the device at for the given config: the requested rate if it is
+// supported, otherwise the closest supported rate, preferring higher ones.
+pub(crate) fn native_rate(
+    configs: impl Iterator<Item = SupportedStreamConfigRange>,
+    config: &StreamConfig,
+) -> Result<SampleRate, BuildStreamError> {
+    let requested = config.sample_rate.0;
+    configs
+        .filter(|range| range.channels() == config.channels)
+        .map(|range| requested.clamp(range.min_sample_rate().0, range.max_sample_rate().0))
+        .min_by_key(|&rate| (rate.abs_diff(requested), u32::MAX - rate))
+        .map(SampleRate)
+        .ok_or(BuildStreamError::StreamConfigNotSupported)
+}
+
+// `instant` shifted by `frames` frames at the given rate, rounded to a whole frame.
+pub(crate) fn offset_instant(
+    instant: StreamInstant,
+    frames: f64,
+    rate: SampleRate,
+) -> StreamInstant {
+    let duration = frames_to_duration(frames.abs().round() as usize, rate);
+    let shifted = if frames < 0.0 {
+        instant.sub(duration)
+    } else {
+        instant.add(duration)
+    };
+    shifted.unwrap_or(instant)
+}
+
+// A streaming windowed sinc resampler for interleaved samples.
+pub(crate) struct Resampler {
+    pub(crate) channels: usize,
+    // The number of input frames per output frame.
+    step: f64,
+    half_taps: usize,
+    // The filter for each of the `PHASES + 1` fractional positions, `2 * half_taps` taps each.
+    filters: Vec<f32>,
+    // Input frames, of which those from frame `start` on are still needed by the filter. The
+    // frames before are dropped when more room is needed, so that the buffer does not have to
+    // be shifted on every call.
+    buffer: Vec<f32>,
+    start: usize,
+    // The position of the next output frame relative to frame `start` of `buffer`, in input
+    // frames.
+    position: f64,
+}
+
+impl Resampler {
+    pub(crate) fn new(channels: usize, input_rate: SampleRate, output_rate: SampleRate) -> Self {
+        let step = input_rate.0 as f64 / output_rate.0 as f64;
+        let scale = step.max(1.0);
+        let half_taps = (HALF_TAPS as f64 * scale).ceil() as usize;
+        let cutoff = CUTOFF / scale;
+        let taps = 2 * half_taps;
+
+        let mut filters = Vec::with_capacity((PHASES + 1) * taps);
+        for phase in 0..=PHASES {
+            let fraction = phase as f64 / PHASES as f64;
+            let start = filters.len();
+            filters.extend((0..taps).map(|tap| {
+                // The distance of the tap from the position of the output frame.
+                let x = tap as f64 - (half_taps - 1) as f64 - fraction;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * cutoff * x).sin() / (PI * cutoff * x)
+                };
+                (sinc * kaiser(x / half_taps as f64)) as f32
+            }));
+            // Normalize the gain at DC, which the window and truncation slightly change.
+            let sum: f32 = filters[start..].iter().sum();
+            filters[start..].iter_mut().for_each(|tap| *tap /= sum);
+        }
+
+        // The filter starts on silence, centered on the first input frame.
+        Resampler {
+            channels,
+            step,
+            half_taps,
+            filters,
+            buffer: vec![0.0; half_taps * channels],
+            start: 0,
+            position: half_taps as f64,
+        }
+    }
+
+    // Make room for pushing up to `frames` input frames at once without allocating.
+    pub(crate) fn reserve(&mut self, frames: usize) {
+        // At most the frames covered by the filter remain buffered between two calls.
+        let capacity = (2 * self.half_taps + 2 + frames) * self.channels;
+        self.buffer
+            .reserve(capacity.saturating_sub(self.buffer.len()));
+    }
+
+    // The maximum number of output frames processed after pushing `frames` input frames.
+    pub(crate) fn output_frames_for(&self, frames: usize) -> usize {
+        (frames as f64 / self.step).ceil() as usize + 1
+    }
+
+    // The maximum number of input frames needed to process `frames` output frames.
+    pub(crate) fn input_frames_for(&self, frames: usize) -> usize {
+        (frames as f64 * self.step).ceil() as usize + 1
+    }
+
+    pub(crate) fn buffered_frames(&self) -> usize {
+        self.buffer.len() / self.channels - self.start
+    }
+
+    pub(crate) fn position(&self) -> f64 {
+        self.position
+    }
+
+    // The position of the output frame `index` frames after the next one.
+    fn output_position(&self, index: usize) -> f64 {
+        self.position + index as f64 * self.step
+    }
+
+    // The number of input frames that have to be pushed before `frames` output frames can be
+    // processed.
+    pub(crate) fn input_frames_needed(&self, frames: usize) -> usize {
+        if frames == 0 {
+            return 0;
+        }
+        let last = self.output_position(frames - 1).floor() as usize;
+        (last + self.half_taps + 1).saturating_sub(self.buffered_frames())
+    }
+
+    // The number of output frames that can be processed with the pushed input frames.
+    pub(crate) fn available_frames(&self) -> usize {
+        let limit = self.buffered_frames() as f64 - self.half_taps as f64 - self.position;
+        let mut frames = if limit > 0.0 {
+            (limit / self.step).ceil() as usize
+        } else {
+            0
+        };
+        // Correct rounding errors of the estimate.
+        while frames > 0 && self.input_frames_needed(frames) > 0 {
+            frames -= 1;
+        }
+        while self.input_frames_needed(frames + 1) == 0 {
+            frames += 1;
+        }
+        frames
+    }
+
+    pub(crate) fn push(&mut self, input: &[f32]) {
+        self.make_room(input.len());
+        self.buffer.extend_from_slice(input);
+    }
+
+    // Append `frames` silent input frames and return them to be filled.
+    pub(crate) fn push_with(&mut self, frames: usize) -> &mut [f32] {
+        self.make_room(frames * self.channels);
+        let end = self.buffer.len();
+        self.buffer.resize(end + frames * self.channels, 0.0);
+        &mut self.buffer[end..]
+    }
+
+    // Drop the frames the filter has moved past if `samples` more samples do not fit otherwise.
+    fn make_room(&mut self, samples: usize) {
+        if self.buffer.len() + samples > self.buffer.capacity() {
+            self.buffer.copy_within(self.start * self.channels.., 0);
+            self.buffer
+                .truncate(self.buffer.len() - self.start * self.channels);
+            self.start = 0;
+        }
+    }
+
+    // Fill `output` with the next output frames, which must be available.
+    pub(crate) fn process(&mut self, output: &mut [f32]) {
+        let channels = self.channels;
+        let taps = 2 * self.half_taps;
+        let frames = output.len() / channels;
+        for (index, frame) in output.chunks_exact_mut(channels).enumerate() {
+            let position = self.output_position(index);
+            let first = position.floor() as usize + 1 - self.half_taps;
+            let phase = (position - position.floor()) * PHASES as f64;
+            let weight = phase.fract() as f32;
+            let phase = phase as usize;
+            let filter = &self.filters[phase * taps..(phase + 1) * taps];
+            let next_filter = &self.filters[(phase + 1) * taps..(phase + 2) * taps];
+            let first = self.start + first;
+            let input = &self.buffer[first * channels..(first + taps) * channels];
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                *sample = filter
+                    .iter()
+                    .zip(next_filter)
+                    .zip(input[channel..].iter().step_by(channels))
+                    .map(|((a, b), x)| (a + (b - a) * weight) * x)
+                    .sum();
+            }
+        }
+        self.position = self.output_position(frames);
+
+        // Skip the input frames the filter has moved past.
+        let consumed = (self.position.floor() as usize + 1).saturating_sub(self.half_taps);
+        self.start += consumed;
+        self.position -= consumed as f64;
+    }
+}
+
+// The Kaiser window at `x` in `-1.0..=1.0`.
+fn kaiser(x: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+    bessel_i0(KAISER_BETA * (1.0 - x * x).sqrt()) / bessel_i0(KAISER_BETA)
+}
+
+// The zeroth-order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Resampler;
+    use crate::SampleRate;
+    use std::f64::consts::PI;
+
+    // Resample mono `input` in blocks of `block` frames, returning the output of every block.
+    fn resample(input: &[f32], from: u32, to: u32, block: usize) -> Vec<Vec<f32>> {
+        let mut resampler = Resampler::new(1, SampleRate(from), SampleRate(to));
+        resampler.reserve(block);
+        input
+            .chunks(block)
+            .map(|input| {
+                resampler.push(input);
+                let mut output = vec![0.0; resampler.available_frames()];
+                resampler.process(&mut output);
+                output
+            })
+            .collect()
+    }
+
+    fn sine(frequency: f64, rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|frame| (2.0 * PI * frequency * frame as f64 / rate as f64).sin() as f32)
+            .collect()
+    }
+
+    #[test]
+    fn test_dc_gain() {
+        for (from, to) in [(48_000, 44_100), (44_100, 48_000), (48_000, 16_000)] {
+            let output = resample(&[1.0; 9600], from, to, 480).concat();
+            // Skip the start, where the filter still covers the silence before the input.
+            for &sample in &output[500..] {
+                assert!(
+                    (sample - 1.0).abs() < 1e-4,
+                    "{} -> {}: {}",
+                    from,
+                    to,
+                    sample
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_passband() {
+        for (from, to) in [(48_000, 44_100), (44_100, 48_000), (48_000, 16_000)] {
+            let output = resample(&sine(1000.0, from, 9600), from, to, 480).concat();
+            // Output frame `n` is centered on the instant `n / to` of the input.
+            let expected = sine(1000.0, to, output.len());
+            for (sample, expected) in output.iter().zip(&expected).skip(500) {
+                assert!((sample - expected).abs() < 2e-3, "{} -> {}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stopband() {
+        // Above the Nyquist frequency of the output, which would alias to 4 kHz.
+        let output = resample(&sine(12_000.0, 48_000, 9600), 48_000, 16_000, 480).concat();
+        for &sample in &output[500..] {
+            assert!(sample.abs() < 1e-3, "{}", sample);
+        }
+    }
+
+    #[test]
+    fn test_frames_per_block() {
+        let (from, to) = (48_000, 44_100);
+        let blocks = resample(&[0.0; 48_000], from, to, 480);
+        // Every block yields the frames it covers, 441 on average.
+        for block in &blocks[1..] {
+            assert!((440..=442).contains(&block.len()), "{}", block.len());
+        }
+        // The filter has to look ahead by half its length before it can process a frame.
+        let step = from as f64 / to as f64;
+        let half_taps = Resampler::new(1, SampleRate(from), SampleRate(to)).half_taps;
+        let expected = (48_000 - half_taps) as f64 / step;
+        let total: usize = blocks.iter().map(Vec::len).sum();
+        assert!((total as f64 - expected).abs() <= 1.0, "{}", total);
+    }
+
+    #[test]
+    fn test_buffer_does_not_grow() {
+        let mut resampler = Resampler::new(2, SampleRate(44_100), SampleRate(48_000));
+        resampler.reserve(512);
+        let capacity = resampler.buffer.capacity();
+        let mut output = vec![0.0; 2 * resampler.output_frames_for(512)];
+        for _ in 0..100 {
+            resampler.push(&[0.0; 1024]);
+            let frames = resampler.available_frames();
+            resampler.process(&mut output[..2 * frames]);
+        }
+        assert_eq!(resampler.buffer.capacity(), capacity);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn test_timestamps() {
+        use crate::host::dummy::{Device, ManualClock};
+        use crate::traits::{DeviceTrait, StreamTrait};
+        use crate::{
+            BufferSize, SampleFormat, SampleLayout, StreamConfig, SupportedBufferSize,
+            SupportedStreamConfigRange,
+        };
+        use std::sync::{Arc, Mutex};
+
+        let clock = ManualClock::new();
+        let range = SupportedStreamConfigRange::new(
+            1,
+            SampleRate(48_000),
+            SampleRate(48_000),
+            SupportedBufferSize::Unknown,
+            SampleFormat::F32,
+        );
+        let device = Device::new("test")
+            .with_input_configs(vec![range])
+            .with_output_configs(vec![range])
+            .with_clock(clock.clone());
+        let config = StreamConfig {
+            channels: 1,
+            sample_rate: SampleRate(44_100),
+            buffer_size: BufferSize::Fixed(480),
+            layout: SampleLayout::Interleaved,
+        };
+
+        // The instants of the first frame of every callback, and their position.
+        let instants = Arc::new(Mutex::new(vec![]));
+        let input_instants = instants.clone();
+        let input = device
+            .build_input_stream_resampled(
+                &config,
+                move |_, info| {
+                    let capture = info.timestamp().capture;
+                    let frames = info.position().frames;
+                    input_instants.lock().unwrap().push((capture, frames));
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        let output_instants = instants.clone();
+        let output = device
+            .build_output_stream_resampled(
+                &config,
+                move |_, info| {
+                    let playback = info.timestamp().playback;
+                    let frames = info.position().frames;
+                    output_instants.lock().unwrap().push((playback, frames));
+                },
+                |err| panic!("{err}"),
+                None,
+            )
+            .unwrap();
+        input.play().unwrap();
+        output.play().unwrap();
+        for _ in 0..20 {
+            clock.advance(480);
+        }
+
+        // The dummy host reports the device frames without latency, so the first frame of every
+        // callback is captured or played at its position, up to rounding to a device frame.
+        let instants = instants.lock().unwrap();
+        assert!(instants.len() >= 38);
+        for &(instant, frames) in instants.iter() {
+            let expected = frames as f64 / 44_100.0;
+            let actual = instant.as_nanos() as f64 / 1e9;
+            assert!(
+                (actual - expected).abs() <= 1.0 / 48_000.0,
+                "{} at {}",
+                actual,
+                expected
+            );
+        }
+    }
+}
@@ -29,13 +29,19 @@ pub enum SampleFormat {
     /// `i16` with a valid range of `i16::MIN..=i16::MAX` with `0` being the origin.
     I16,
 
-    // /// `I24` with a valid range of '-(1 << 23)..(1 << 23)' with `0` being the origin
-    // I24,
+    /// `I24` with a valid range of '-(1 << 23)..(1 << 23)' with `0` being the origin.
+    ///
+    /// Stored in the low 24 bits of a sign-extended `i32`.
+    I24,
+
     /// `i32` with a valid range of `i32::MIN..=i32::MAX` with `0` being the origin.
     I32,
 
-    // /// `I24` with a valid range of '-(1 << 47)..(1 << 47)' with `0` being the origin
-    // I48,
+    /// `I48` with a valid range of '-(1 << 47)..(1 << 47)' with `0` being the origin.
+    ///
+    /// Stored in the low 48 bits of a sign-extended `i64`.
+    I48,
+
     /// `i64` with a valid range of `i64::MIN..=i64::MAX` with `0` being the origin.
     I64,
 
@@ -45,13 +51,19 @@ pub enum SampleFormat {
     /// `u16` with a valid range of `u16::MIN..=u16::MAX` with `1 << 15 == 32768` being the origin.
     U16,
 
-    // /// `U24` with a valid range of '0..16777216' with `1 << 23 == 8388608` being the origin
-    // U24,
+    /// `U24` with a valid range of '0..16777216' with `1 << 23 == 8388608` being the origin.
+    ///
+    /// Stored in the low 24 bits of an `i32`.
+    U24,
+
     /// `u32` with a valid range of `u32::MIN..=u32::MAX` with `1 << 31` being the origin.
     U32,
 
-    // /// `U48` with a valid range of '0..(1 << 48)' with `1 << 47` being the origin
-    // U48,
+    /// `U48` with a valid range of '0..(1 << 48)' with `1 << 47` being the origin.
+    ///
+    /// Stored in the low 48 bits of an `i64`.
+    U48,
+
     /// `u64` with a valid range of `u64::MIN..=u64::MAX` with `1 << 63` being the origin.
     U64,
 
@@ -70,9 +82,9 @@ impl SampleFormat {
         match *self {
             SampleFormat::I8 | SampleFormat::U8 => mem::size_of::<i8>(),
             SampleFormat::I16 | SampleFormat::U16 => mem::size_of::<i16>(),
-            // SampleFormat::I24 | SampleFormat::U24 => 3,
+            SampleFormat::I24 | SampleFormat::U24 => mem::size_of::<I24>(),
             SampleFormat::I32 | SampleFormat::U32 => mem::size_of::<i32>(),
-            // SampleFormat::I48 | SampleFormat::U48 => 6,
+            SampleFormat::I48 | SampleFormat::U48 => mem::size_of::<I48>(),
             SampleFormat::I64 | SampleFormat::U64 => mem::size_of::<i64>(),
             SampleFormat::F32 => mem::size_of::<f32>(),
             SampleFormat::F64 => mem::size_of::<f64>(),
@@ -82,20 +94,28 @@ impl SampleFormat {
     #[inline]
     #[must_use]
     pub fn is_int(&self) -> bool {
-        //matches!(*self, SampleFormat::I8 | SampleFormat::I16 | SampleFormat::I24 | SampleFormat::I32 | SampleFormat::I48 | SampleFormat::I64)
         matches!(
             *self,
-            SampleFormat::I8 | SampleFormat::I16 | SampleFormat::I32 | SampleFormat::I64
+            SampleFormat::I8
+                | SampleFormat::I16
+                | SampleFormat::I24
+                | SampleFormat::I32
+                | SampleFormat::I48
+                | SampleFormat::I64
         )
     }
 
     #[inline]
     #[must_use]
     pub fn is_uint(&self) -> bool {
-        //matches!(*self, SampleFormat::U8 | SampleFormat::U16 | SampleFormat::U24 | SampleFormat::U32 | SampleFormat::U48 | SampleFormat::U64)
         matches!(
             *self,
-            SampleFormat::U8 | SampleFormat::U16 | SampleFormat::U32 | SampleFormat::U64
+            SampleFormat::U8
+                | SampleFormat::U16
+                | SampleFormat::U24
+                | SampleFormat::U32
+                | SampleFormat::U48
+                | SampleFormat::U64
         )
     }
 
@@ -111,15 +131,15 @@ impl Display for SampleFormat {
         match *self {
             SampleFormat::I8 => "i8",
             SampleFormat::I16 => "i16",
-            // SampleFormat::I24 => "i24",
+            SampleFormat::I24 => "i24",
             SampleFormat::I32 => "i32",
-            // SampleFormat::I48 => "i48",
+            SampleFormat::I48 => "i48",
             SampleFormat::I64 => "i64",
             SampleFormat::U8 => "u8",
             SampleFormat::U16 => "u16",
-            // SampleFormat::U24 => "u24",
+            SampleFormat::U24 => "u24",
             SampleFormat::U32 => "u32",
-            // SampleFormat::U48 => "u48",
+            SampleFormat::U48 => "u48",
             SampleFormat::U64 => "u64",
             SampleFormat::F32 => "f32",
             SampleFormat::F64 => "f64",
@@ -140,13 +160,17 @@ impl SizedSample for i16 {
     const FORMAT: SampleFormat = SampleFormat::I16;
 }
 
-// impl SizedSample for I24 { const FORMAT: SampleFormat = SampleFormat::I24; }
+impl SizedSample for I24 {
+    const FORMAT: SampleFormat = SampleFormat::I24;
+}
 
 impl SizedSample for i32 {
     const FORMAT: SampleFormat = SampleFormat::I32;
 }
 
-// impl SizedSample for I48 { const FORMAT: SampleFormat = SampleFormat::I48; }
+impl SizedSample for I48 {
+    const FORMAT: SampleFormat = SampleFormat::I48;
+}
 
 impl SizedSample for i64 {
     const FORMAT: SampleFormat = SampleFormat::I64;
@@ -160,13 +184,17 @@ impl SizedSample for u16 {
     const FORMAT: SampleFormat = SampleFormat::U16;
 }
 
-// impl SizedSample for U24 { const FORMAT: SampleFormat = SampleFormat::U24; }
+impl SizedSample for U24 {
+    const FORMAT: SampleFormat = SampleFormat::U24;
+}
 
 impl SizedSample for u32 {
     const FORMAT: SampleFormat = SampleFormat::U32;
 }
 
-// impl SizedSample for U48 { const FORMAT: SampleFormat = SampleFormat::U48; }
+impl SizedSample for U48 {
+    const FORMAT: SampleFormat = SampleFormat::U48;
+}
 
 impl SizedSample for u64 {
     const FORMAT: SampleFormat = SampleFormat::U64;
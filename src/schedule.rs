@@ -0,0 +1,254 @@
+//! Starting the output of a data callback at a given instant.
+//!
+//! Streams start playing as soon as possible when played. To have the first frame of a callback
+//! reach the speaker at a precise instant instead, e.g. to sync several devices or to line audio
+//! up with a game clock, wrap the callback with [`scheduled_output`] and play the stream via the
+//! returned [`StartHandle`]:
+//!
+//! ```no_run
+//! use cpal::traits::{DeviceTrait, HostTrait};
+//! use std::time::Duration;
+//! # let host = cpal::default_host();
+//! # let device = host.default_output_device().unwrap();
+//! # let config: cpal::StreamConfig = device.default_output_config().unwrap().into();
+//! let (data_fn, start) = cpal::scheduled_output(&config, move |data: &mut [f32], _| {
+//!     data.fill(0.5);
+//! });
+//! let stream = device.build_output_stream(&config, data_fn, |_err| {}, None).unwrap();
+//! let target = cpal::StreamInstant::now().add(Duration::from_millis(200)).unwrap();
+//! start.play_at(&stream, target).unwrap();
+//! std::thread::sleep(Duration::from_millis(300));
+//! if let Some(accuracy) = start.accuracy() {
+//!     println!("started {:?} off target", accuracy.error());
+//! }
+//! ```
+
+use std::sync::atomic::{AtomicI64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::traits::StreamTrait;
+use crate::{
+    OutputCallbackInfo, OutputStreamTimestamp, PlayStreamError, Sample, SampleLayout, StreamConfig,
+    StreamInstant, StreamPosition,
+};
+
+// The states of a `StartHandle`.
+const IDLE: u8 = 0;
+const SCHEDULED: u8 = 1;
+const STARTED: u8 = 2;
+
+/// Wrap an output data callback, so that it only starts producing frames at an instant set via the
+/// returned [`StartHandle`].
+///
+/// Until then, the stream plays silence and the callback is not called. The callback whose
+/// buffer contains the target instant, according to the playback timestamp of the stream, fills
+/// the buffer with silence up to the frame closest to the target and hands the rest of it to the
+/// data callback, with timestamps and position adjusted accordingly.
+///
+/// # Panics
+///
+/// Panics if `config` uses the planar [`SampleLayout`](crate::SampleLayout).
+pub fn scheduled_output<T, D>(
+    config: &StreamConfig,
+    mut data_callback: D,
+) -> (
+    impl FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
+    StartHandle,
+)
+where
+    T: Sample + Send + 'static,
+    D: FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
+{
+    assert_eq!(
+        config.layout,
+        SampleLayout::Interleaved,
+        "scheduled output requires interleaved samples"
+    );
+    let channels = config.channels as usize;
+    let rate = config.sample_rate.0 as i128;
+    let shared = Arc::new(Shared {
+        state: AtomicU8::new(IDLE),
+        target_nanos: AtomicI64::new(0),
+        start_nanos: AtomicI64::new(0),
+    });
+    let handle = StartHandle {
+        shared: shared.clone(),
+    };
+
+    let data_fn = move |data: &mut [T], info: &OutputCallbackInfo| {
+        let (offset, starting) = match shared.state.load(Ordering::Acquire) {
+            STARTED => (0, false),
+            SCHEDULED => {
+                // The number of frames to play before the target, rounded to the closest frame.
+                let target = shared.target_nanos.load(Ordering::Relaxed) as i128;
+                let nanos = target - info.timestamp.playback.as_nanos();
+                let offset = (nanos.max(0) * rate + 500_000_000) / 1_000_000_000;
+                if offset >= (data.len() / channels) as i128 {
+                    data.fill(T::EQUILIBRIUM);
+                    return;
+                }
+                (offset as usize, true)
+            }
+            _ => {
+                data.fill(T::EQUILIBRIUM);
+                return;
+            }
+        };
+
+        let offset_nanos = offset as i128 * 1_000_000_000 / rate;
+        let playback = info
+            .timestamp
+            .playback
+            .add(Duration::from_nanos(offset_nanos as u64))
+            .unwrap_or(info.timestamp.playback);
+        if starting {
+            shared
+                .start_nanos
+                .store(playback.as_nanos() as i64, Ordering::Relaxed);
+            shared.state.store(STARTED, Ordering::Release);
+        }
+
+        let (silence, data) = data.split_at_mut(offset * channels);
+        silence.fill(T::EQUILIBRIUM);
+        let info = OutputCallbackInfo {
+            timestamp: OutputStreamTimestamp {
+                callback: info.timestamp.callback,
+                playback,
+            },
+            position: StreamPosition {
+                frames: info.position.frames + offset as u64,
+                discontinuity: info.position.discontinuity,
+            },
+        };
+        data_callback(data, &info);
+    };
+    (data_fn, handle)
+}
+
+/// Controls when the data callback wrapped by [`scheduled_output`] starts producing frames.
+#[derive(Clone, Debug)]
+pub struct StartHandle {
+    shared: Arc<Shared>,
+}
+
+impl StartHandle {
+    /// Play `stream`, whose data callback was wrapped together with this handle, so that the
+    /// first frame of the data callback is played at `instant`.
+    ///
+    /// The instant has to be further in the future than the latency of the stream, otherwise the
+    /// data callback starts as soon as possible. See [`StartHandle::accuracy`].
+    pub fn play_at<S: StreamTrait>(
+        &self,
+        stream: &S,
+        instant: StreamInstant,
+    ) -> Result<(), PlayStreamError> {
+        self.schedule(instant);
+        stream.play()
+    }
+
+    /// Start the data callback at `instant` without playing the stream, e.g. because it already
+    /// runs. If the data callback already started, it is silenced until then.
+    pub fn schedule(&self, instant: StreamInstant) {
+        let nanos = instant.as_nanos().clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        self.shared.target_nanos.store(nanos, Ordering::Relaxed);
+        self.shared.state.store(SCHEDULED, Ordering::Release);
+    }
+
+    /// How accurately the last scheduled start was hit, once the data callback has started.
+    pub fn accuracy(&self) -> Option<StartAccuracy> {
+        if self.shared.state.load(Ordering::Acquire) != STARTED {
+            return None;
+        }
+        Some(StartAccuracy {
+            target: StreamInstant::from_nanos(self.shared.target_nanos.load(Ordering::Relaxed)),
+            start: StreamInstant::from_nanos(self.shared.start_nanos.load(Ordering::Relaxed)),
+        })
+    }
+}
+
+/// The instant a scheduled data callback was supposed to start at, and the instant it did.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StartAccuracy {
+    /// The instant the first frame of the data callback was scheduled to be played at.
+    pub target: StreamInstant,
+    /// The instant the first frame of the data callback is played at, according to the playback
+    /// timestamp of the stream. This is within half a frame of the target, unless the start was
+    /// scheduled too late.
+    pub start: StreamInstant,
+}
+
+impl StartAccuracy {
+    /// The absolute difference between the target and the actual start.
+    pub fn error(&self) -> Duration {
+        let nanos = (self.start.as_nanos() - self.target.as_nanos()).unsigned_abs();
+        Duration::from_nanos(nanos.min(u64::MAX as u128) as u64)
+    }
+
+    /// Whether the data callback started after the target.
+    pub fn is_late(&self) -> bool {
+        self.start > self.target
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: AtomicU8,
+    // The target instant and the instant the data callback started at, as nanoseconds.
+    target_nanos: AtomicI64,
+    start_nanos: AtomicI64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scheduled_output;
+    use crate::{
+        BufferSize, OutputCallbackInfo, OutputStreamTimestamp, SampleLayout, SampleRate,
+        StreamConfig, StreamInstant, StreamPosition,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_scheduled_output() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(1000),
+            buffer_size: BufferSize::Default,
+            layout: SampleLayout::Interleaved,
+        };
+        let (mut data_fn, start) = scheduled_output(&config, |data: &mut [f32], info| {
+            // The first frame of the callback is played at the target, i.e. 6 ms.
+            assert!(info.timestamp().playback.as_nanos() >= 6_000_000);
+            data.fill(1.0);
+        });
+
+        let mut output = [0.5f32; 8];
+        for callback in 0..3 {
+            if callback == 1 {
+                start.schedule(StreamInstant::new(0, 6_000_000));
+            }
+            let instant = StreamInstant::new(0, callback * 4_000_000);
+            let info = OutputCallbackInfo {
+                timestamp: OutputStreamTimestamp {
+                    callback: instant,
+                    playback: instant,
+                },
+                position: StreamPosition {
+                    frames: callback as u64 * 4,
+                    discontinuity: false,
+                },
+            };
+            data_fn(&mut output, &info);
+            let expected: &[f32] = match callback {
+                0 => &[0.0; 8],
+                // Playback of this buffer starts at 4 ms, so 2 frames of silence come first.
+                1 => &[0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0],
+                _ => &[1.0; 8],
+            };
+            assert_eq!(output, expected);
+        }
+        let accuracy = start.accuracy().unwrap();
+        assert_eq!(accuracy.start, accuracy.target);
+        assert_eq!(accuracy.error(), Duration::ZERO);
+    }
+}
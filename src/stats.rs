@@ -0,0 +1,220 @@
+//! Collecting timing statistics of the data callback of a stream.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{SampleRate, StreamStats, StreamStatsError};
+
+/// The statistics of a stream, updated by its worker without locking and read by the stream
+/// handle. Nothing is recorded until the collection is enabled.
+#[derive(Debug)]
+pub(crate) struct StatsCollector {
+    sample_rate: SampleRate,
+    enabled: AtomicBool,
+    callbacks: AtomicU64,
+    // The time spent in the data callback and the number of frames it processed, in total.
+    total_nanos: AtomicU64,
+    total_frames: AtomicU64,
+    min_nanos: AtomicU64,
+    max_nanos: AtomicU64,
+    // The bits of the highest load of a single call as an `f64`. Positive floats compare like
+    // their bits, so the maximum can be updated atomically.
+    max_load_bits: AtomicU64,
+    xruns: AtomicU64,
+    late_wakeups: AtomicU64,
+}
+
+impl StatsCollector {
+    pub(crate) fn new(sample_rate: SampleRate) -> Self {
+        StatsCollector {
+            sample_rate,
+            enabled: AtomicBool::new(false),
+            callbacks: AtomicU64::new(0),
+            total_nanos: AtomicU64::new(0),
+            total_frames: AtomicU64::new(0),
+            min_nanos: AtomicU64::new(u64::MAX),
+            max_nanos: AtomicU64::new(0),
+            max_load_bits: AtomicU64::new(0),
+            xruns: AtomicU64::new(0),
+            late_wakeups: AtomicU64::new(0),
+        }
+    }
+
+    /// Start recording the statistics.
+    pub(crate) fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// The instant a call of the data callback starts at, to pass to
+    /// [`record_callback`](Self::record_callback). `None` unless enabled, so that streams not
+    /// collecting statistics do not read the clock.
+    pub(crate) fn start(&self) -> Option<Instant> {
+        self.is_enabled().then(Instant::now)
+    }
+
+    /// Record a call of the data callback that started at `start` and processed `frames` frames.
+    pub(crate) fn record_callback(&self, start: Option<Instant>, frames: usize) {
+        if let Some(start) = start {
+            self.record_duration(start.elapsed(), frames);
+        }
+    }
+
+    // Record a call of the data callback that took `duration` to process `frames` frames.
+    fn record_duration(&self, duration: Duration, frames: usize) {
+        let nanos = duration.as_nanos().min(u64::MAX as u128) as u64;
+        self.callbacks.fetch_add(1, Ordering::Relaxed);
+        self.total_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.total_frames
+            .fetch_add(frames as u64, Ordering::Relaxed);
+        self.min_nanos.fetch_min(nanos, Ordering::Relaxed);
+        self.max_nanos.fetch_max(nanos, Ordering::Relaxed);
+        if frames > 0 {
+            let load = nanos as f64 / self.frames_to_nanos(frames as u64);
+            self.max_load_bits
+                .fetch_max(load.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Record an xrun of the stream.
+    pub(crate) fn record_xrun(&self) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.xruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that the worker woke up too late to process a buffer in time.
+    pub(crate) fn record_late_wakeup(&self) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.late_wakeups.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A snapshot of the statistics collected since they were enabled.
+    pub(crate) fn snapshot(&self) -> Result<StreamStats, StreamStatsError> {
+        if !self.is_enabled() {
+            return Err(StreamStatsError::NotEnabled);
+        }
+        let callbacks = self.callbacks.load(Ordering::Relaxed);
+        if callbacks == 0 {
+            return Ok(StreamStats {
+                xruns: self.xruns.load(Ordering::Relaxed),
+                late_wakeups: self.late_wakeups.load(Ordering::Relaxed),
+                ..StreamStats::default()
+            });
+        }
+        let total_nanos = self.total_nanos.load(Ordering::Relaxed);
+        let total_frames = self.total_frames.load(Ordering::Relaxed);
+        let avg_load = if total_frames > 0 {
+            total_nanos as f64 / self.frames_to_nanos(total_frames)
+        } else {
+            0.0
+        };
+        Ok(StreamStats {
+            callbacks,
+            min_callback_duration: Duration::from_nanos(self.min_nanos.load(Ordering::Relaxed)),
+            avg_callback_duration: Duration::from_nanos(total_nanos / callbacks),
+            max_callback_duration: Duration::from_nanos(self.max_nanos.load(Ordering::Relaxed)),
+            avg_load,
+            max_load: f64::from_bits(self.max_load_bits.load(Ordering::Relaxed)),
+            xruns: self.xruns.load(Ordering::Relaxed),
+            late_wakeups: self.late_wakeups.load(Ordering::Relaxed),
+        })
+    }
+
+    // The duration of the given number of frames in nanoseconds.
+    fn frames_to_nanos(&self, frames: u64) -> f64 {
+        frames as f64 * 1_000_000_000.0 / self.sample_rate.0 as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatsCollector;
+    use crate::{SampleRate, StreamStats, StreamStatsError};
+    use std::time::Duration;
+
+    #[test]
+    fn test_disabled() {
+        let stats = StatsCollector::new(SampleRate(1000));
+        assert_eq!(stats.start(), None);
+        stats.record_callback(stats.start(), 10);
+        stats.record_xrun();
+        assert!(matches!(
+            stats.snapshot(),
+            Err(StreamStatsError::NotEnabled)
+        ));
+        stats.enable();
+        assert_eq!(stats.snapshot().unwrap(), StreamStats::default());
+    }
+
+    #[test]
+    fn test_stream_stats() {
+        let stats = StatsCollector::new(SampleRate(1000));
+        stats.enable();
+        assert_eq!(stats.snapshot().unwrap(), StreamStats::default());
+        // 10 frames last 10 ms at 1 kHz.
+        stats.record_duration(Duration::from_millis(2), 10);
+        stats.record_duration(Duration::from_millis(6), 10);
+        stats.record_xrun();
+        let snapshot = stats.snapshot().unwrap();
+        assert_eq!(snapshot.callbacks, 2);
+        assert_eq!(snapshot.min_callback_duration, Duration::from_millis(2));
+        assert_eq!(snapshot.avg_callback_duration, Duration::from_millis(4));
+        assert_eq!(snapshot.max_callback_duration, Duration::from_millis(6));
+        assert!((snapshot.avg_load - 0.4).abs() < 1e-9);
+        assert!((snapshot.max_load - 0.6).abs() < 1e-9);
+        assert_eq!(snapshot.xruns, 1);
+        assert_eq!(snapshot.late_wakeups, 0);
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn test_platform_stream_stats() {
+        use crate::host::dummy::{self, ManualClock};
+        use crate::traits::{DeviceTrait, StreamTrait};
+        use crate::{
+            BufferSize, Device, SampleFormat, SampleLayout, StreamConfig, SupportedBufferSize,
+            SupportedStreamConfigRange,
+        };
+
+        let range = SupportedStreamConfigRange::new(
+            1,
+            SampleRate(1000),
+            SampleRate(1000),
+            SupportedBufferSize::Unknown,
+            SampleFormat::F32,
+        );
+        let clock = ManualClock::new();
+        let device = Device::from(
+            dummy::Device::new("test")
+                .with_output_configs(vec![range])
+                .with_clock(clock.clone()),
+        );
+        let config = StreamConfig {
+            channels: 1,
+            sample_rate: SampleRate(1000),
+            buffer_size: BufferSize::Fixed(4),
+            layout: SampleLayout::Interleaved,
+        };
+        let stream = device
+            .build_output_stream(&config, |_: &mut [f32], _| {}, |_| {}, None)
+            .unwrap();
+        stream.play().unwrap();
+
+        // The platform stream forwards to the dummy stream.
+        clock.advance(4);
+        assert!(matches!(stream.stats(), Err(StreamStatsError::NotEnabled)));
+        stream.enable_stats().unwrap();
+        clock.advance(8);
+        clock.skip(4);
+        let snapshot = stream.stats().unwrap();
+        assert_eq!(snapshot.callbacks, 2);
+        assert_eq!(snapshot.xruns, 1);
+    }
+}
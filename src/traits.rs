@@ -3,10 +3,14 @@
 use std::time::Duration;
 
 use crate::{
-    BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError, DevicesError,
+    AdapterConfig, BuildStreamError, ChannelMapping, ChannelMix, Data, DefaultStreamConfigError,
+    DeviceDescription, DeviceEvent, DeviceId, DeviceIdError, DeviceNameError, DeviceWatcher,
+    DevicesError, Dither, DuplexCallbackInfo, DuplexStream, DuplexStreamConfig, FromSample,
     InputCallbackInfo, InputDevices, OutputCallbackInfo, OutputDevices, PauseStreamError,
-    PlayStreamError, SampleFormat, SizedSample, StreamConfig, StreamError, SupportedStreamConfig,
-    SupportedStreamConfigRange, SupportedStreamConfigsError,
+    PlayStreamError, SampleFormat, SampleLayout, SizedSample, StreamBufferSize,
+    StreamBufferSizeError, StreamConfig, StreamError, StreamLatencyError, StreamPositionError,
+    StreamStats, StreamStatsError, SupportedStreamConfig, SupportedStreamConfigRange,
+    SupportedStreamConfigsError, WatchDevicesError,
 };
 
 /// A [`Host`] provides access to the available audio devices on the system.
@@ -69,6 +73,29 @@ pub trait HostTrait {
     fn output_devices(&self) -> Result<OutputDevices<Self::Devices>, DevicesError> {
         Ok(self.devices()?.filter(DeviceTrait::supports_output))
     }
+
+    /// The device with the given id, as returned by [`DeviceTrait::id`].
+    ///
+    /// Returns `None` if the id belongs to another host or the device is not available.
+    fn device_by_id(&self, id: &DeviceId) -> Option<Self::Device> {
+        self.devices()
+            .ok()?
+            .find(|device| matches!(device.id(), Ok(ref device_id) if device_id == id))
+    }
+
+    /// Watch for devices being added or removed, and for the default devices changing.
+    ///
+    /// The callback is called from a thread managed by the host until the returned
+    /// [`DeviceWatcher`] is dropped.
+    ///
+    /// Returns [`WatchDevicesError::NotSupported`] if the host provides no way of observing
+    /// device changes.
+    fn watch_devices<F>(&self, _callback: F) -> Result<DeviceWatcher, WatchDevicesError>
+    where
+        F: FnMut(DeviceEvent) + Send + 'static,
+    {
+        Err(WatchDevicesError::NotSupported)
+    }
 }
 
 /// A device that is capable of audio input and/or output.
@@ -89,6 +116,24 @@ pub trait DeviceTrait {
     /// The human-readable name of the device.
     fn name(&self) -> Result<String, DeviceNameError>;
 
+    /// A stable identifier of the device, which unlike the name is unique within the host and
+    /// survives restarts of the application.
+    ///
+    /// Returns [`DeviceIdError::NotSupported`] if the host provides no such identifier.
+    fn id(&self) -> Result<DeviceId, DeviceIdError> {
+        Err(DeviceIdError::NotSupported)
+    }
+
+    /// Metadata describing the device, e.g. to present it to users.
+    ///
+    /// Hosts that do not provide any metadata only fill in the name.
+    fn description(&self) -> Result<DeviceDescription, DeviceNameError> {
+        Ok(DeviceDescription {
+            name: self.name()?,
+            ..Default::default()
+        })
+    }
+
     /// True if the device supports audio input, otherwise false
     fn supports_input(&self) -> bool {
         self.supported_input_configs()
@@ -124,6 +169,9 @@ pub trait DeviceTrait {
     fn default_output_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError>;
 
     /// Create an input stream.
+    ///
+    /// Fails with [`BuildStreamError::StreamConfigNotSupported`] for the split
+    /// [`SampleLayout`](crate::SampleLayout), which only raw callbacks can access.
     fn build_input_stream<T, D, E>(
         &self,
         config: &StreamConfig,
@@ -136,6 +184,9 @@ pub trait DeviceTrait {
         D: FnMut(&[T], &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout == SampleLayout::Split {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
         self.build_input_stream_raw(
             config,
             T::FORMAT,
@@ -152,6 +203,9 @@ pub trait DeviceTrait {
     }
 
     /// Create an output stream.
+    ///
+    /// Fails with [`BuildStreamError::StreamConfigNotSupported`] for the split
+    /// [`SampleLayout`](crate::SampleLayout), which only raw callbacks can access.
     fn build_output_stream<T, D, E>(
         &self,
         config: &StreamConfig,
@@ -164,6 +218,9 @@ pub trait DeviceTrait {
         D: FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if config.layout == SampleLayout::Split {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
         self.build_output_stream_raw(
             config,
             T::FORMAT,
@@ -179,6 +236,232 @@ pub trait DeviceTrait {
         )
     }
 
+    /// Create an input stream of sample type `T` with the given config, adapted from what the
+    /// device supports as allowed by `adapter`.
+    ///
+    /// The device is opened at `T::FORMAT` if it supports it for the given config, and otherwise
+    /// at a supported floating point format, or else at the most precise supported integer
+    /// format. Samples are converted via `f64`, and the dither of `adapter` is applied when
+    /// converting from floating point to integer samples. The channels and the sample rate are
+    /// adapted as configured by [`AdapterConfig::channels`] and [`AdapterConfig::resample`], and
+    /// the planar [`SampleLayout`](crate::SampleLayout) is supported in any case. The split layout
+    /// is not supported.
+    fn build_input_stream_adapted<T, D, E>(
+        &self,
+        config: &StreamConfig,
+        adapter: &AdapterConfig,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        Self: Sized,
+        T: SizedSample + FromSample<f64> + Send + 'static,
+        f64: FromSample<T>,
+        D: FnMut(&[T], &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        crate::adapt::build_input_stream_adapted(
+            self,
+            config,
+            adapter,
+            data_callback,
+            error_callback,
+            timeout,
+        )
+    }
+
+    /// Create an output stream of sample type `T` with the given config, adapted to what the
+    /// device supports as allowed by `adapter`.
+    ///
+    /// See [`build_input_stream_adapted`](Self::build_input_stream_adapted).
+    fn build_output_stream_adapted<T, D, E>(
+        &self,
+        config: &StreamConfig,
+        adapter: &AdapterConfig,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        Self: Sized,
+        T: SizedSample + FromSample<f64> + Send + 'static,
+        f64: FromSample<T>,
+        D: FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        crate::adapt::build_output_stream_adapted(
+            self,
+            config,
+            adapter,
+            data_callback,
+            error_callback,
+            timeout,
+        )
+    }
+
+    /// Create an input stream of sample type `T`, converting from the sample format of the device
+    /// if it does not support `T`.
+    ///
+    /// A shorthand for [`build_input_stream_adapted`](Self::build_input_stream_adapted) that only
+    /// adapts the sample format and the layout.
+    fn build_input_stream_converted<T, D, E>(
+        &self,
+        config: &StreamConfig,
+        dither: Dither,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        Self: Sized,
+        T: SizedSample + FromSample<f64> + Send + 'static,
+        f64: FromSample<T>,
+        D: FnMut(&[T], &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let adapter = AdapterConfig {
+            dither,
+            ..Default::default()
+        };
+        self.build_input_stream_adapted(config, &adapter, data_callback, error_callback, timeout)
+    }
+
+    /// Create an output stream of sample type `T`, converting to the sample format of the device
+    /// if it does not support `T`.
+    ///
+    /// A shorthand for [`build_output_stream_adapted`](Self::build_output_stream_adapted) that
+    /// only adapts the sample format and the layout.
+    fn build_output_stream_converted<T, D, E>(
+        &self,
+        config: &StreamConfig,
+        dither: Dither,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        Self: Sized,
+        T: SizedSample + FromSample<f64> + Send + 'static,
+        f64: FromSample<T>,
+        D: FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let adapter = AdapterConfig {
+            dither,
+            ..Default::default()
+        };
+        self.build_output_stream_adapted(config, &adapter, data_callback, error_callback, timeout)
+    }
+
+    /// Create an input stream delivering samples at the sample rate of `config`, resampling from
+    /// the closest rate the device supports if it does not support that rate.
+    ///
+    /// A shorthand for [`build_input_stream_adapted`](Self::build_input_stream_adapted) with
+    /// [`AdapterConfig::resample`] set. The capture timestamps account for the delay of the
+    /// resampling filter.
+    fn build_input_stream_resampled<D, E>(
+        &self,
+        config: &StreamConfig,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        Self: Sized,
+        D: FnMut(&[f32], &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let adapter = AdapterConfig {
+            resample: true,
+            ..Default::default()
+        };
+        self.build_input_stream_adapted(config, &adapter, data_callback, error_callback, timeout)
+    }
+
+    /// Create an output stream expecting samples at the sample rate of `config`, resampling to
+    /// the closest rate the device supports if it does not support that rate.
+    ///
+    /// A shorthand for [`build_output_stream_adapted`](Self::build_output_stream_adapted) with
+    /// [`AdapterConfig::resample`] set. The playback timestamps account for the delay of the
+    /// resampling filter.
+    fn build_output_stream_resampled<D, E>(
+        &self,
+        config: &StreamConfig,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        Self: Sized,
+        D: FnMut(&mut [f32], &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let adapter = AdapterConfig {
+            resample: true,
+            ..Default::default()
+        };
+        self.build_output_stream_adapted(config, &adapter, data_callback, error_callback, timeout)
+    }
+
+    /// Create an input stream delivering the number of channels of `config`, mixed from the
+    /// channels of the device with `mix`.
+    ///
+    /// The device is opened with the source channels of `mix`, which must have `config.channels`
+    /// destination channels. Without a mix, the device is opened with the requested number of
+    /// channels if it supports it, otherwise with the closest supported number, and the channels
+    /// are mixed with [`ChannelMix::standard`]. A shorthand for
+    /// [`build_input_stream_adapted`](Self::build_input_stream_adapted) with the corresponding
+    /// [`ChannelMapping`].
+    fn build_input_stream_mixed<D, E>(
+        &self,
+        config: &StreamConfig,
+        mix: Option<ChannelMix>,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        Self: Sized,
+        D: FnMut(&[f32], &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let adapter = AdapterConfig {
+            channels: mix.map_or(ChannelMapping::Standard, ChannelMapping::Mix),
+            ..Default::default()
+        };
+        self.build_input_stream_adapted(config, &adapter, data_callback, error_callback, timeout)
+    }
+
+    /// Create an output stream expecting the number of channels of `config`, mixed into the
+    /// channels of the device with `mix`.
+    ///
+    /// The device is opened with the destination channels of `mix`, which must have
+    /// `config.channels` source channels. Without a mix, the device is opened with the requested
+    /// number of channels if it supports it, otherwise with the closest supported number, and the
+    /// channels are mixed with [`ChannelMix::standard`]. A shorthand for
+    /// [`build_output_stream_adapted`](Self::build_output_stream_adapted) with the corresponding
+    /// [`ChannelMapping`].
+    fn build_output_stream_mixed<D, E>(
+        &self,
+        config: &StreamConfig,
+        mix: Option<ChannelMix>,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        Self: Sized,
+        D: FnMut(&mut [f32], &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let adapter = AdapterConfig {
+            channels: mix.map_or(ChannelMapping::Standard, ChannelMapping::Mix),
+            ..Default::default()
+        };
+        self.build_output_stream_adapted(config, &adapter, data_callback, error_callback, timeout)
+    }
+
     /// Create a dynamically typed input stream.
     fn build_input_stream_raw<D, E>(
         &self,
@@ -204,6 +487,71 @@ pub trait DeviceTrait {
     where
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static;
+
+    /// Create a full-duplex stream.
+    ///
+    /// The data callback receives the captured input samples together with the output buffer to
+    /// fill, both covering the same number of frames.
+    fn build_duplex_stream<T, D, E>(
+        &self,
+        config: &DuplexStreamConfig,
+        mut data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<DuplexStream<Self::Stream>, BuildStreamError>
+    where
+        Self: Sized,
+        T: SizedSample,
+        D: FnMut(&[T], &mut [T], &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        self.build_duplex_stream_raw(
+            config,
+            T::FORMAT,
+            move |input, output, info| {
+                data_callback(
+                    input
+                        .as_slice()
+                        .expect("host supplied incorrect sample type"),
+                    output
+                        .as_slice_mut()
+                        .expect("host supplied incorrect sample type"),
+                    info,
+                )
+            },
+            error_callback,
+            timeout,
+        )
+    }
+
+    /// Create a dynamically typed full-duplex stream.
+    ///
+    /// Hosts without native duplex support fall back to running an input and an output stream
+    /// on this device, passing captured data to the output callback through an internal queue.
+    /// This adds up to one input buffer of latency and is not drift-compensated, see
+    /// [`DuplexStream::is_native`].
+    fn build_duplex_stream_raw<D, E>(
+        &self,
+        config: &DuplexStreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+        timeout: Option<Duration>,
+    ) -> Result<DuplexStream<Self::Stream>, BuildStreamError>
+    where
+        Self: Sized,
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        crate::duplex::build_duplex_stream_fallback(
+            self,
+            config,
+            sample_format,
+            data_callback,
+            error_callback,
+            timeout,
+        )
+    }
 }
 
 /// A stream created from [`Device`](DeviceTrait), with methods to control playback.
@@ -220,4 +568,73 @@ pub trait StreamTrait {
     /// Note: Not all devices support suspending the stream at the hardware level. This method may
     /// fail in these cases.
     fn pause(&self) -> Result<(), PauseStreamError>;
+
+    /// The time it takes for a frame to travel between the data callback and the device, i.e.
+    /// from being captured to reaching an input callback, or from being written by an output
+    /// callback to being played. For duplex streams this is the sum of both.
+    ///
+    /// The latency is queried from the host and may change while the stream runs. This method
+    /// can be called from any thread.
+    fn latency(&self) -> Result<Duration, StreamLatencyError> {
+        Err(StreamLatencyError::NotSupported)
+    }
+
+    /// The buffer size the host chose for the stream, and the maximum number of frames passed to
+    /// a single data callback. For duplex streams, this is the buffer size of the output.
+    ///
+    /// Some hosts, e.g. JACK, may change the buffer size while the stream runs.
+    fn buffer_size(&self) -> Result<StreamBufferSize, StreamBufferSizeError> {
+        Err(StreamBufferSizeError::NotSupported)
+    }
+
+    /// The number of frames passed to or from the data callback since the stream was built, i.e.
+    /// the position of the next buffer. For duplex streams, this counts the frames of the output.
+    ///
+    /// Frames lost due to xruns are not counted. This method can be called from any thread, e.g.
+    /// to display a playhead.
+    fn position(&self) -> Result<u64, StreamPositionError> {
+        Err(StreamPositionError::NotSupported)
+    }
+
+    /// Start collecting the statistics returned by [`stats`](Self::stats).
+    ///
+    /// Statistics are not collected by default, as timing every call of the data callback reads
+    /// the clock twice on the audio thread.
+    fn enable_stats(&self) -> Result<(), StreamStatsError> {
+        Err(StreamStatsError::NotSupported)
+    }
+
+    /// A snapshot of the timing statistics of the data callback since they were enabled via
+    /// [`enable_stats`](Self::enable_stats), e.g. to display a DSP load meter or to check in
+    /// tests that processing stays within its budget. For duplex streams, xruns and late wakeups
+    /// of both directions are counted.
+    ///
+    /// The statistics are collected without locking, so a snapshot taken while the callback runs
+    /// may combine values from before and after the call. This method can be called from any
+    /// thread.
+    fn stats(&self) -> Result<StreamStats, StreamStatsError> {
+        Err(StreamStatsError::NotSupported)
+    }
+
+    /// Run all of the given streams, starting them at the same moment if the host supports it.
+    /// Returns whether they were started together. See [`StreamGroup`](crate::StreamGroup).
+    ///
+    /// The default implementation plays the streams one after the other.
+    fn play_all(streams: &[&Self]) -> Result<bool, PlayStreamError>
+    where
+        Self: Sized,
+    {
+        crate::group::play_each(streams)
+    }
+
+    /// Pause all of the given streams, at the same moment if the host supports it. Returns
+    /// whether they were paused together. See [`StreamGroup`](crate::StreamGroup).
+    ///
+    /// The default implementation pauses the streams one after the other.
+    fn pause_all(streams: &[&Self]) -> Result<bool, PauseStreamError>
+    where
+        Self: Sized,
+    {
+        crate::group::pause_each(streams)
+    }
 }